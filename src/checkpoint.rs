@@ -0,0 +1,1338 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use nncombinator::arr::{Arr, Arr4};
+use nncombinator::device::Device;
+use nncombinator::error::{ConfigReadError, PersistenceError};
+use nncombinator::layer::{ActivationLayer, InputLayer, LinearLayer, LinearOutputLayer};
+use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+use nncombinator::ope::UnitValue;
+use nncombinator::optimizer::Optimizer;
+use nncombinator::persistence::{Linear, LinearPersistence, Persistence};
+
+use crate::collection::{Images, VecImages};
+use crate::layer::{BatchNormalization, ConvolutionLayer};
+use crate::optimizer::{ConvolutionOptimizer, OptimizerValueState, ParameterOptimizer};
+
+/// Trait that defines the element types that can be stored in a checkpoint
+pub trait TensorElement: Copy + Default + Send {
+    /// Name of the data type in the header
+    const DTYPE:&'static str;
+    /// Number of bytes per element
+    const SIZE:usize;
+    /// Append the little-endian representation of the value to the buffer
+    fn write_le(&self,buffer:&mut Vec<u8>);
+    /// Read a value from its little-endian representation
+    fn read_le(bytes:&[u8]) -> Self;
+}
+impl TensorElement for f32 {
+    const DTYPE: &'static str = "F32";
+    const SIZE: usize = 4;
+
+    fn write_le(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        f32::from_le_bytes([bytes[0],bytes[1],bytes[2],bytes[3]])
+    }
+}
+impl TensorElement for f64 {
+    const DTYPE: &'static str = "F64";
+    const SIZE: usize = 8;
+
+    fn write_le(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        f64::from_le_bytes([bytes[0],bytes[1],bytes[2],bytes[3],bytes[4],bytes[5],bytes[6],bytes[7]])
+    }
+}
+impl TensorElement for u8 {
+    const DTYPE: &'static str = "U8";
+    const SIZE: usize = 1;
+
+    fn write_le(&self, buffer: &mut Vec<u8>) {
+        buffer.push(*self);
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+}
+/// Information about a tensor recorded in the header of the checkpoint
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct TensorInfo {
+    /// Name of the data type
+    pub dtype:String,
+    /// Shape of the tensor
+    pub shape:Vec<usize>,
+    /// Start and end offsets of the data, relative to the end of the header
+    pub data_offsets:(usize,usize),
+}
+/// Implementation of a writer for a checkpoint that stores named tensors in one file
+///
+/// The file consists of the length of the header as a little-endian u64,
+/// the header in JSON (name, dtype, shape and data offsets of each tensor)
+/// padded with spaces to a multiple of 8 bytes, and the raw little-endian data.
+/// This is the same layout as the safetensors format.
+pub struct CheckpointWriter {
+    tensors:BTreeMap<String,TensorInfo>,
+    data:Vec<u8>,
+}
+impl CheckpointWriter {
+    /// Create an instance of CheckpointWriter
+    pub fn new() -> CheckpointWriter {
+        CheckpointWriter {
+            tensors:BTreeMap::new(),
+            data:Vec::new(),
+        }
+    }
+
+    /// Add a tensor given as a raw slice
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    /// * `shape` - Shape of the tensor
+    /// * `data` - Elements of the tensor in row-major order
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`PersistenceError`]
+    pub fn add<T: TensorElement>(&mut self,name:&str,shape:&[usize],data:&[T]) -> Result<(),PersistenceError> {
+        if self.tensors.contains_key(name) {
+            return Err(PersistenceError::IOError(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,format!("The tensor {} has already been added.",name)
+            )));
+        } else if shape.iter().product::<usize>() != data.len() {
+            return Err(PersistenceError::IOError(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("The shape {:?} of the tensor {} does not match the number of elements {}.",shape,name,data.len())
+            )));
+        }
+
+        let begin = self.data.len();
+
+        for v in data.iter() {
+            v.write_le(&mut self.data);
+        }
+
+        self.tensors.insert(name.to_string(),TensorInfo {
+            dtype:T::DTYPE.to_string(),
+            shape:shape.to_vec(),
+            data_offsets:(begin,self.data.len())
+        });
+
+        Ok(())
+    }
+
+    /// Add a tensor of Arr
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    /// * `arr` - Tensor to be added
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`PersistenceError`]
+    pub fn add_arr<T: TensorElement,const N:usize>(&mut self,name:&str,arr:&Arr<T,N>) -> Result<(),PersistenceError> {
+        self.add(name,&[N],arr)
+    }
+
+    /// Add a tensor of Arr4
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    /// * `arr` - Tensor to be added
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`PersistenceError`]
+    pub fn add_arr4<T: TensorElement,const N1:usize,const N2:usize,const N3:usize,const N4:usize>(
+        &mut self,name:&str,arr:&Arr4<T,N1,N2,N3,N4>) -> Result<(),PersistenceError> {
+        self.add(name,&[N1,N2,N3,N4],arr.as_raw_slice())
+    }
+
+    /// Add a tensor of Images
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    /// * `images` - Tensor to be added
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`PersistenceError`]
+    pub fn add_images<T: TensorElement,const C:usize,const H:usize,const W:usize>(
+        &mut self,name:&str,images:&Images<T,C,H,W>) -> Result<(),PersistenceError> {
+        self.add(name,&[C,H,W],images.as_raw_slice())
+    }
+
+    /// Add a tensor of VecImages
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    /// * `images` - Tensor to be added
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`PersistenceError`]
+    pub fn add_vec_images<T: TensorElement,const C:usize,const H:usize,const W:usize>(
+        &mut self,name:&str,images:&VecImages<T,C,H,W>) -> Result<(),PersistenceError> {
+        self.add(name,&[images.len(),C,H,W],images.as_raw_slice())
+    }
+
+    /// Add all parameters of a network as one named tensor per parameter, such as layer1.kernel and layer1.bias
+    ///
+    /// The values are taken in the order of nncombinator's persistence and split as described by CheckpointNetwork.
+    /// # Arguments
+    /// * `prefix` - Prefix of the names of the tensors
+    /// * `network` - Network to be added
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`PersistenceError`]
+    pub fn add_network<U,N>(&mut self,prefix:&str,network:&mut N) -> Result<(),PersistenceError>
+        where U: UnitValue<U> + TensorElement, N: Persistence<U,CheckpointPersistence<U>,Linear> + CheckpointNetwork {
+        let mut persistence = CheckpointPersistence::new();
+
+        network.save(&mut persistence)?;
+
+        let layout = CheckpointLayout::of::<N>();
+
+        if layout.len() != persistence.values().len() {
+            return Err(PersistenceError::IOError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("The network saved {} values, but its tensors hold {} values.",persistence.values().len(),layout.len())
+            )));
+        }
+
+        let mut values = persistence.values();
+
+        for tensor in layout.tensors() {
+            let (l,r) = values.split_at(tensor.len());
+
+            self.add(&format!("{}{}",prefix,tensor.name),&tensor.shape,l)?;
+
+            values = r;
+        }
+
+        Ok(())
+    }
+
+    /// Add the parameters and running statistics of a batch normalization
+    /// # Arguments
+    /// * `prefix` - Prefix of the names of the tensors
+    /// * `batch_norm` - Batch normalization to be added
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`PersistenceError`]
+    pub fn add_batch_norm<U,const K:usize>(&mut self,prefix:&str,batch_norm:&BatchNormalization<U,K>) -> Result<(),PersistenceError>
+        where U: UnitValue<U> + TensorElement {
+        self.add_arr(&format!("{}weight",prefix),batch_norm.scale())?;
+        self.add_arr(&format!("{}bias",prefix),batch_norm.shift())?;
+        self.add_arr(&format!("{}running_mean",prefix),batch_norm.mean())?;
+        self.add_arr(&format!("{}running_var",prefix),batch_norm.variance())?;
+        self.add(&format!("{}eps",prefix),&[1],&[batch_norm.epsilon()])
+    }
+
+    /// Build the contents of the checkpoint file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut header = String::from("{");
+
+        for (i,(name,info)) in self.tensors.iter().enumerate() {
+            if i > 0 {
+                header.push(',');
+            }
+
+            header.push_str(&format!("{}:{{\"dtype\":\"{}\",\"shape\":[{}],\"data_offsets\":[{},{}]}}",
+                                     json_string(name),
+                                     info.dtype,
+                                     info.shape.iter().map(|s| s.to_string()).collect::<Vec<String>>().join(","),
+                                     info.data_offsets.0,info.data_offsets.1));
+        }
+
+        header.push('}');
+
+        while (8 + header.len()) % 8 != 0 {
+            header.push(' ');
+        }
+
+        let mut buffer = Vec::with_capacity(8 + header.len() + self.data.len());
+
+        buffer.extend_from_slice(&(header.len() as u64).to_le_bytes());
+        buffer.extend_from_slice(header.as_bytes());
+        buffer.extend_from_slice(&self.data);
+
+        buffer
+    }
+
+    /// Write the checkpoint to a file
+    /// # Arguments
+    /// * `file` - Path of the file to be written
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`PersistenceError`]
+    pub fn save<P: AsRef<Path>>(&self,file:P) -> Result<(),PersistenceError> {
+        fs::write(file,self.to_bytes())?;
+
+        Ok(())
+    }
+}
+/// Implementation of a reader for a checkpoint that refers to the data without copying
+///
+/// Since the data starts at an offset aligned to 8 bytes from the start of the file,
+/// the bytes may also be those of a memory-mapped file.
+pub struct Checkpoint<'a> {
+    tensors:BTreeMap<String,TensorInfo>,
+    data:&'a [u8],
+}
+impl<'a> Checkpoint<'a> {
+    /// Parse the contents of a checkpoint file
+    /// # Arguments
+    /// * `bytes` - Contents of the checkpoint file
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn from_bytes(bytes:&'a [u8]) -> Result<Checkpoint<'a>,ConfigReadError> {
+        if bytes.len() < 8 {
+            return Err(ConfigReadError::InvalidState(String::from("The checkpoint is too short to contain a header.")));
+        }
+
+        let len = u64::from_le_bytes([bytes[0],bytes[1],bytes[2],bytes[3],bytes[4],bytes[5],bytes[6],bytes[7]]) as usize;
+
+        if bytes.len() - 8 < len {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The length of the header ({}) exceeds the size of the checkpoint ({}).",len,bytes.len()
+            )));
+        }
+
+        let header = std::str::from_utf8(&bytes[8..8+len]).map_err(|e| {
+            ConfigReadError::InvalidState(format!("The header of the checkpoint is not valid UTF-8. ({})",e))
+        })?;
+
+        let data = &bytes[8+len..];
+
+        let tensors = parse_header(header)?;
+
+        for (name,info) in tensors.iter() {
+            if info.data_offsets.0 > info.data_offsets.1 || info.data_offsets.1 > data.len() {
+                return Err(ConfigReadError::InvalidState(format!(
+                    "The data offsets of the tensor {} are out of range.",name
+                )));
+            }
+        }
+
+        Ok(Checkpoint {
+            tensors:tensors,
+            data:data
+        })
+    }
+
+    /// get the names of the tensors
+    pub fn names(&self) -> impl Iterator<Item=&str> {
+        self.tensors.keys().map(|k| k.as_str())
+    }
+
+    /// get the header information of a tensor
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    pub fn info(&self,name:&str) -> Option<&TensorInfo> {
+        self.tensors.get(name)
+    }
+
+    /// get the raw little-endian data of a tensor
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    pub fn raw_data(&self,name:&str) -> Option<&'a [u8]> {
+        self.tensors.get(name).map(|info| &self.data[info.data_offsets.0..info.data_offsets.1])
+    }
+
+    /// Read a tensor into a raw slice after checking its data type and shape
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    /// * `shape` - Expected shape of the tensor
+    /// * `dst` - Slice to which the elements are written
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read<T: TensorElement>(&self,name:&str,shape:&[usize],dst:&mut [T]) -> Result<(),ConfigReadError> {
+        let info = self.tensors.get(name).ok_or_else(|| {
+            ConfigReadError::InvalidState(format!("The tensor {} does not exist in the checkpoint.",name))
+        })?;
+
+        if info.dtype != T::DTYPE {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The data type of the tensor {} does not match. (expected {}, actual {})",name,T::DTYPE,info.dtype
+            )));
+        } else if info.shape != shape {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The shape of the tensor {} does not match. (expected {:?}, actual {:?})",name,shape,info.shape
+            )));
+        } else if info.data_offsets.1 - info.data_offsets.0 != dst.len() * T::SIZE {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The data size of the tensor {} does not match its shape.",name
+            )));
+        }
+
+        for (d,s) in dst.iter_mut().zip(self.data[info.data_offsets.0..info.data_offsets.1].chunks(T::SIZE)) {
+            *d = T::read_le(s);
+        }
+
+        Ok(())
+    }
+
+    /// Read a tensor into Arr
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    /// * `dst` - Arr to which the elements are written
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read_arr<T: TensorElement,const N:usize>(&self,name:&str,dst:&mut Arr<T,N>) -> Result<(),ConfigReadError> {
+        self.read(name,&[N],dst)
+    }
+
+    /// Read a tensor into Arr4
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    /// * `dst` - Arr4 to which the elements are written
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read_arr4<T: TensorElement,const N1:usize,const N2:usize,const N3:usize,const N4:usize>(
+        &self,name:&str,dst:&mut Arr4<T,N1,N2,N3,N4>) -> Result<(),ConfigReadError> {
+        self.read(name,&[N1,N2,N3,N4],dst.as_raw_mut_slice())
+    }
+
+    /// Read a tensor into Images
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    /// * `dst` - Images to which the elements are written
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read_images<T: TensorElement,const C:usize,const H:usize,const W:usize>(
+        &self,name:&str,dst:&mut Images<T,C,H,W>) -> Result<(),ConfigReadError> {
+        self.read(name,&[C,H,W],dst.as_raw_mut_slice())
+    }
+
+    /// Read a tensor as VecImages
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read_vec_images<T: TensorElement,const C:usize,const H:usize,const W:usize>(
+        &self,name:&str) -> Result<VecImages<T,C,H,W>,ConfigReadError> {
+        let info = self.tensors.get(name).ok_or_else(|| {
+            ConfigReadError::InvalidState(format!("The tensor {} does not exist in the checkpoint.",name))
+        })?;
+
+        let len = info.shape.first().cloned().unwrap_or(0);
+
+        // The number of images comes from the header, so the size of the data is checked before anything is allocated
+        if len.checked_mul(C * H * W * T::SIZE) != Some(info.data_offsets.1 - info.data_offsets.0) {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The data size of the tensor {} does not match its shape.",name
+            )));
+        }
+
+        let mut images = VecImages::with_size(len);
+
+        self.read(name,&[len,C,H,W],images.as_raw_mut_slice())?;
+
+        Ok(images)
+    }
+
+    /// Restore all parameters of a network from the tensors added by CheckpointWriter::add_network
+    ///
+    /// The shapes are those of the network, not those of the header, so nothing is allocated from the contents of the file.
+    /// # Arguments
+    /// * `prefix` - Prefix of the names of the tensors
+    /// * `network` - Network to be restored
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read_network<U,N>(&self,prefix:&str,network:&mut N) -> Result<(),ConfigReadError>
+        where U: UnitValue<U> + TensorElement, N: Persistence<U,CheckpointPersistence<U>,Linear> + CheckpointNetwork {
+        let layout = CheckpointLayout::of::<N>();
+
+        let mut values = vec![U::default(); layout.len()];
+        let mut rest = &mut values[..];
+
+        for tensor in layout.tensors() {
+            let (l,r) = rest.split_at_mut(tensor.len());
+
+            self.read(&format!("{}{}",prefix,tensor.name),&tensor.shape,l)?;
+
+            rest = r;
+        }
+
+        let mut persistence = CheckpointPersistence::with_values(values);
+
+        network.load(&mut persistence)?;
+
+        persistence.verify_eof()
+    }
+}
+impl<'a> Checkpoint<'a> {
+    /// Read the parameters and running statistics of a batch normalization added by CheckpointWriter::add_batch_norm
+    /// # Arguments
+    /// * `prefix` - Prefix of the names of the tensors
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read_batch_norm<U,const K:usize>(&self,prefix:&str) -> Result<BatchNormalization<U,K>,ConfigReadError>
+        where U: UnitValue<U> + TensorElement {
+        let mut scale = Arr::new();
+        let mut shift = Arr::new();
+        let mut mean = Arr::new();
+        let mut variance = Arr::new();
+        let mut epsilon = [0f64];
+
+        self.read_arr(&format!("{}weight",prefix),&mut scale)?;
+        self.read_arr(&format!("{}bias",prefix),&mut shift)?;
+        self.read_arr(&format!("{}running_mean",prefix),&mut mean)?;
+        self.read_arr(&format!("{}running_var",prefix),&mut variance)?;
+        self.read(&format!("{}eps",prefix),&[1],&mut epsilon)?;
+
+        Ok(BatchNormalization::new(scale,shift,mean,variance,epsilon[0]))
+    }
+}
+/// Implementation of a checkpoint file read into memory
+pub struct CheckpointFile {
+    bytes:Vec<u8>,
+}
+impl CheckpointFile {
+    /// Read a checkpoint file
+    /// # Arguments
+    /// * `file` - Path of the file to be read
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn open<P: AsRef<Path>>(file:P) -> Result<CheckpointFile,ConfigReadError> {
+        let bytes = fs::read(file)?;
+
+        Checkpoint::from_bytes(&bytes)?;
+
+        Ok(CheckpointFile {
+            bytes:bytes
+        })
+    }
+
+    /// Obtaining a reader of the checkpoint
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint::from_bytes(&self.bytes).expect("The checkpoint has already been validated.")
+    }
+}
+/// Prefix of the names of the tensors holding the parameters of the network in a training checkpoint
+pub const NETWORK_PREFIX:&str = "network.";
+/// Prefix of the names of the tensors holding the state of the optimizer in a training checkpoint
+pub const OPTIMIZER_PREFIX:&str = "optimizer.";
+/// Implementation of LinearPersistence that keeps the values of a network in memory
+///
+/// Layers save and load their parameters in the order of nncombinator's persistence,
+/// so a whole network, including the nncombinator layers, is walked through it.
+pub struct CheckpointPersistence<U> {
+    values:Vec<U>,
+    position:usize,
+}
+impl<U> CheckpointPersistence<U> where U: TensorElement {
+    /// Create an instance of CheckpointPersistence to which a network is saved
+    pub fn new() -> CheckpointPersistence<U> {
+        CheckpointPersistence {
+            values:Vec::new(),
+            position:0
+        }
+    }
+
+    /// Create an instance of CheckpointPersistence from which a network is loaded
+    /// # Arguments
+    /// * `values` - Values saved by a network
+    pub fn with_values(values:Vec<U>) -> CheckpointPersistence<U> {
+        CheckpointPersistence {
+            values:values,
+            position:0
+        }
+    }
+
+    /// get the values saved so far
+    pub fn values(&self) -> &[U] {
+        &self.values
+    }
+}
+impl<U> LinearPersistence<U> for CheckpointPersistence<U> where U: UnitValue<U> + TensorElement {
+    fn read(&mut self) -> Result<U, ConfigReadError> {
+        let v = self.values.get(self.position).cloned().ok_or_else(|| {
+            ConfigReadError::InvalidState(String::from("The checkpoint does not contain enough values for the network."))
+        })?;
+
+        self.position += 1;
+
+        Ok(v)
+    }
+
+    fn write(&mut self, u: U) -> Result<(), PersistenceError> {
+        self.values.push(u);
+
+        Ok(())
+    }
+
+    fn verify_eof(&mut self) -> Result<(), ConfigReadError> {
+        if self.position == self.values.len() {
+            Ok(())
+        } else {
+            Err(ConfigReadError::InvalidState(format!(
+                "The checkpoint contains {} values that were not read by the network.",self.values.len() - self.position
+            )))
+        }
+    }
+}
+/// Name and shape of a tensor of the parameters of a layer
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct LayerTensor {
+    /// Name of the tensor without the prefix
+    pub name:String,
+    /// Shape of the tensor
+    pub shape:Vec<usize>,
+}
+impl LayerTensor {
+    /// Number of elements of the tensor
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+}
+/// Tensors of the parameters of a network in the order in which the network saves its values
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct CheckpointLayout {
+    layers:usize,
+    tensors:Vec<LayerTensor>,
+}
+impl CheckpointLayout {
+    /// Create an empty instance of CheckpointLayout
+    pub fn new() -> CheckpointLayout {
+        CheckpointLayout {
+            layers:0,
+            tensors:Vec::new()
+        }
+    }
+
+    /// Create the layout of a network
+    pub fn of<N: CheckpointNetwork>() -> CheckpointLayout {
+        let mut layout = CheckpointLayout::new();
+
+        N::layout(&mut layout);
+
+        layout
+    }
+
+    /// Start the tensors of the next layer and return its index
+    pub fn next_layer(&mut self) -> usize {
+        self.layers += 1;
+        self.layers - 1
+    }
+
+    /// Append a tensor of a layer, named layer{index}.{name}
+    /// # Arguments
+    /// * `layer` - Index of the layer returned by next_layer
+    /// * `name` - Name of the parameter
+    /// * `shape` - Shape of the tensor
+    pub fn push(&mut self,layer:usize,name:&str,shape:&[usize]) {
+        self.tensors.push(LayerTensor {
+            name:format!("layer{}.{}",layer,name),
+            shape:shape.to_vec()
+        });
+    }
+
+    /// get the tensors in the order in which the network saves them
+    pub fn tensors(&self) -> &[LayerTensor] {
+        &self.tensors
+    }
+
+    /// Total number of values of the tensors
+    pub fn len(&self) -> usize {
+        self.tensors.iter().map(|t| t.len()).sum()
+    }
+}
+/// Trait that defines how the values saved by a layer and its upper layers are split into named tensors
+///
+/// Layers whose parameters can be inspected store each of them as its own tensor.
+/// The nncombinator layers keep their parameters private, so their values are stored as saved in one tensor named layer{index}.values.
+pub trait CheckpointNetwork {
+    /// Append the tensors of the upper layers and then those of this layer
+    /// # Arguments
+    /// * `layout` - Layout to which the tensors are appended
+    fn layout(layout:&mut CheckpointLayout);
+}
+impl<U,O,LI> CheckpointNetwork for InputLayer<U,O,LI> where U: UnitValue<U> {
+    fn layout(_: &mut CheckpointLayout) {}
+}
+impl<U,P,D,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH:usize,const FW:usize,const PAD:usize,const S:usize> CheckpointNetwork for ConvolutionLayer<U,P,D,C,K,H,W,FH,FW,PAD,S>
+    where U: UnitValue<U>, P: CheckpointNetwork {
+    fn layout(layout: &mut CheckpointLayout) {
+        P::layout(layout);
+
+        let layer = layout.next_layer();
+
+        layout.push(layer,"kernel_shape",&[4]);
+        layout.push(layer,"bias",&[K]);
+        layout.push(layer,"kernel",&[K,C,FH,FW]);
+    }
+}
+impl<U,C,P,D,I,const NI:usize,const NO:usize> CheckpointNetwork for LinearLayer<U,C,P,D,I,NI,NO>
+    where U: UnitValue<U>, P: CheckpointNetwork, D: Device<U> {
+    fn layout(layout: &mut CheckpointLayout) {
+        P::layout(layout);
+
+        let layer = layout.next_layer();
+
+        layout.push(layer,"values",&[NO + NI * NO]);
+    }
+}
+impl<U,P,A,I,PI,D,const N:usize> CheckpointNetwork for ActivationLayer<U,P,A,I,PI,D,N>
+    where U: UnitValue<U>, P: CheckpointNetwork, D: Device<U> {
+    fn layout(layout: &mut CheckpointLayout) {
+        P::layout(layout);
+    }
+}
+impl<U,P,D,I,IO> CheckpointNetwork for LinearOutputLayer<U,P,D,I,IO>
+    where U: UnitValue<U>, P: CheckpointNetwork, D: Device<U> {
+    fn layout(layout: &mut CheckpointLayout) {
+        P::layout(layout);
+    }
+}
+/// Trait defining optimizers whose state can be stored in a checkpoint
+pub trait CheckpointOptimizer<T> where T: TensorElement {
+    /// Add the tensors of the state to a checkpoint
+    /// # Arguments
+    /// * `prefix` - Prefix of the names of the tensors
+    /// * `writer` - Writer of the checkpoint
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`PersistenceError`]
+    fn save_state(&self,prefix:&str,writer:&mut CheckpointWriter) -> Result<(),PersistenceError>;
+
+    /// Restore the state from the tensors of a checkpoint
+    /// # Arguments
+    /// * `prefix` - Prefix of the names of the tensors
+    /// * `checkpoint` - Checkpoint to be read
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    fn load_state(&mut self,prefix:&str,checkpoint:&Checkpoint) -> Result<(),ConfigReadError>;
+}
+impl<U,O> CheckpointOptimizer<U> for ParameterOptimizer<U,O>
+    where U: UnitValue<U> + TensorElement, O: Optimizer<U> + OptimizerValueState<U> {
+    fn save_state(&self, prefix: &str, writer: &mut CheckpointWriter) -> Result<(), PersistenceError> {
+        let len = self.optimizers().first().map(|o| o.state_len()).unwrap_or(0);
+
+        let mut state = vec![U::default(); self.optimizers().len() * len];
+
+        if len > 0 {
+            for (o,dst) in self.optimizers().iter().zip(state.chunks_mut(len)) {
+                o.write_state(dst);
+            }
+        }
+
+        writer.add(prefix,&[self.optimizers().len(),len],&state)
+    }
+
+    fn load_state(&mut self, prefix: &str, checkpoint: &Checkpoint) -> Result<(), ConfigReadError> {
+        let len = self.optimizers().first().map(|o| o.state_len()).unwrap_or(0);
+
+        let mut state = vec![U::default(); self.optimizers().len() * len];
+
+        checkpoint.read(prefix,&[self.optimizers().len(),len],&mut state)?;
+
+        if len > 0 {
+            for (o,src) in self.optimizers_mut().iter_mut().zip(state.chunks(len)) {
+                o.read_state(src);
+            }
+        }
+
+        Ok(())
+    }
+}
+impl<U,O,const K:usize,const C:usize,const FH:usize,const FW:usize> CheckpointOptimizer<U> for ConvolutionOptimizer<U,O,K,C,FH,FW>
+    where U: UnitValue<U> + TensorElement, O: Optimizer<U> + OptimizerValueState<U> {
+    fn save_state(&self, prefix: &str, writer: &mut CheckpointWriter) -> Result<(), PersistenceError> {
+        self.kernel_optimizer().save_state(&format!("{}kernel",prefix),writer)?;
+        self.bias_optimizer().save_state(&format!("{}bias",prefix),writer)
+    }
+
+    fn load_state(&mut self, prefix: &str, checkpoint: &Checkpoint) -> Result<(), ConfigReadError> {
+        self.kernel_optimizer_mut().load_state(&format!("{}kernel",prefix),checkpoint)?;
+        self.bias_optimizer_mut().load_state(&format!("{}bias",prefix),checkpoint)
+    }
+}
+/// Save a whole network and the state of its optimizer to one checkpoint file
+/// # Arguments
+/// * `file` - Path of the file to be written
+/// * `network` - Network to be saved, walked through nncombinator's persistence
+/// * `optimizer` - Optimizer whose state is saved
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`PersistenceError`]
+pub fn save_training_state<U,N,O,P>(file:P,network:&mut N,optimizer:&O) -> Result<(),PersistenceError>
+    where U: UnitValue<U> + TensorElement,
+          N: Persistence<U,CheckpointPersistence<U>,Linear> + CheckpointNetwork,
+          O: CheckpointOptimizer<U>,
+          P: AsRef<Path> {
+    let mut writer = CheckpointWriter::new();
+
+    writer.add_network(NETWORK_PREFIX,network)?;
+    optimizer.save_state(OPTIMIZER_PREFIX,&mut writer)?;
+
+    writer.save(file)
+}
+/// Restore a whole network and the state of its optimizer from a checkpoint file written by save_training_state
+/// # Arguments
+/// * `file` - Path of the file to be read
+/// * `network` - Network to be restored
+/// * `optimizer` - Optimizer whose state is restored
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`ConfigReadError`]
+pub fn load_training_state<U,N,O,P>(file:P,network:&mut N,optimizer:&mut O) -> Result<(),ConfigReadError>
+    where U: UnitValue<U> + TensorElement,
+          N: Persistence<U,CheckpointPersistence<U>,Linear> + CheckpointNetwork,
+          O: CheckpointOptimizer<U>,
+          P: AsRef<Path> {
+    let file = CheckpointFile::open(file)?;
+    let checkpoint = file.checkpoint();
+
+    checkpoint.read_network(NETWORK_PREFIX,network)?;
+    optimizer.load_state(OPTIMIZER_PREFIX,&checkpoint)
+}
+/// Escape a string as a JSON string literal
+fn json_string(s:&str) -> String {
+    let mut r = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => r.push_str("\\\""),
+            '\\' => r.push_str("\\\\"),
+            '\n' => r.push_str("\\n"),
+            '\r' => r.push_str("\\r"),
+            '\t' => r.push_str("\\t"),
+            c if (c as u32) < 0x20 => r.push_str(&format!("\\u{:04x}",c as u32)),
+            c => r.push(c)
+        }
+    }
+
+    r.push('"');
+    r
+}
+/// Values of the JSON used in the header
+#[derive(Debug)]
+enum JsonValue {
+    String(String),
+    Number(f64),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String,JsonValue)>),
+    Other,
+}
+/// Minimal JSON parser for the header of the checkpoint
+struct JsonParser<'a> {
+    chars:std::iter::Peekable<std::str::Chars<'a>>,
+}
+impl<'a> JsonParser<'a> {
+    fn error(message:&str) -> ConfigReadError {
+        ConfigReadError::InvalidState(format!("The header of the checkpoint is not valid JSON. ({})",message))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self,expected:char) -> Result<(),ConfigReadError> {
+        self.skip_whitespace();
+
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(Self::error(&format!("expected '{}' but found '{}'",expected,c))),
+            None => Err(Self::error(&format!("expected '{}' but reached the end",expected)))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue,ConfigReadError> {
+        self.skip_whitespace();
+
+        match self.chars.peek().cloned() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => Ok(JsonValue::String(self.parse_string()?)),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                while let Some(c) = self.chars.peek() {
+                    if c.is_ascii_alphabetic() {
+                        self.chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                Ok(JsonValue::Other)
+            },
+            Some(c) => Err(Self::error(&format!("unexpected character '{}'",c))),
+            None => Err(Self::error("unexpected end"))
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue,ConfigReadError> {
+        self.expect('{')?;
+
+        let mut members = Vec::new();
+
+        self.skip_whitespace();
+
+        if self.chars.peek() == Some(&'}') {
+            self.chars.next();
+            return Ok(JsonValue::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.expect(':')?;
+            let value = self.parse_value()?;
+
+            members.push((key,value));
+
+            self.skip_whitespace();
+
+            match self.chars.next() {
+                Some(',') => (),
+                Some('}') => return Ok(JsonValue::Object(members)),
+                _ => return Err(Self::error("expected ',' or '}'"))
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue,ConfigReadError> {
+        self.expect('[')?;
+
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+
+        if self.chars.peek() == Some(&']') {
+            self.chars.next();
+            return Ok(JsonValue::Array(items));
+        }
+
+        loop {
+            items.push(self.parse_value()?);
+
+            self.skip_whitespace();
+
+            match self.chars.next() {
+                Some(',') => (),
+                Some(']') => return Ok(JsonValue::Array(items)),
+                _ => return Err(Self::error("expected ',' or ']'"))
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String,ConfigReadError> {
+        self.expect('"')?;
+
+        let mut r = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(r),
+                Some('\\') => {
+                    match self.chars.next() {
+                        Some('"') => r.push('"'),
+                        Some('\\') => r.push('\\'),
+                        Some('/') => r.push('/'),
+                        Some('b') => r.push('\u{8}'),
+                        Some('f') => r.push('\u{c}'),
+                        Some('n') => r.push('\n'),
+                        Some('r') => r.push('\r'),
+                        Some('t') => r.push('\t'),
+                        Some('u') => {
+                            let code = (0..4).map(|_| self.chars.next()).collect::<Option<String>>()
+                                .and_then(|h| u32::from_str_radix(&h,16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| Self::error("invalid unicode escape"))?;
+                            r.push(code);
+                        },
+                        _ => return Err(Self::error("invalid escape"))
+                    }
+                },
+                Some(c) => r.push(c),
+                None => return Err(Self::error("unterminated string"))
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue,ConfigReadError> {
+        let mut s = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' || c.is_ascii_digit() {
+                s.push(c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+
+        s.parse::<f64>().map(JsonValue::Number).map_err(|_| Self::error("invalid number"))
+    }
+}
+/// Parse the JSON header of the checkpoint
+fn parse_header(header:&str) -> Result<BTreeMap<String,TensorInfo>,ConfigReadError> {
+    let mut parser = JsonParser {
+        chars:header.chars().peekable()
+    };
+
+    let members = match parser.parse_value()? {
+        JsonValue::Object(members) => members,
+        _ => return Err(JsonParser::error("the header is not an object"))
+    };
+
+    let mut tensors = BTreeMap::new();
+
+    for (name,value) in members.into_iter() {
+        if name == "__metadata__" {
+            continue;
+        }
+
+        let fields = match value {
+            JsonValue::Object(fields) => fields,
+            _ => return Err(JsonParser::error(&format!("the entry of {} is not an object",name)))
+        };
+
+        let mut dtype = None;
+        let mut shape = None;
+        let mut offsets = None;
+
+        for (key,value) in fields.into_iter() {
+            match (key.as_str(),value) {
+                ("dtype",JsonValue::String(s)) => dtype = Some(s),
+                ("shape",JsonValue::Array(a)) => shape = Some(json_usize_array(&a)?),
+                ("data_offsets",JsonValue::Array(a)) => offsets = Some(json_usize_array(&a)?),
+                _ => ()
+            }
+        }
+
+        let (dtype,shape,offsets) = match (dtype,shape,offsets) {
+            (Some(dtype),Some(shape),Some(offsets)) if offsets.len() == 2 => (dtype,shape,offsets),
+            _ => return Err(JsonParser::error(&format!("the entry of {} is missing dtype, shape or data_offsets",name)))
+        };
+
+        tensors.insert(name,TensorInfo {
+            dtype:dtype,
+            shape:shape,
+            data_offsets:(offsets[0],offsets[1])
+        });
+    }
+
+    Ok(tensors)
+}
+/// Convert a JSON array of non-negative integers into a Vec of usize
+fn json_usize_array(a:&[JsonValue]) -> Result<Vec<usize>,ConfigReadError> {
+    a.iter().map(|v| {
+        match v {
+            JsonValue::Number(n) if *n >= 0. && n.fract() == 0. => Ok(*n as usize),
+            _ => Err(JsonParser::error("expected a non-negative integer"))
+        }
+    }).collect()
+}
+#[cfg(test)]
+mod tests {
+    use nncombinator::arr::Arr;
+    use nncombinator::error::{ConfigReadError, PersistenceError};
+    use nncombinator::mem::AsRawSlice;
+    use nncombinator::optimizer::Optimizer;
+    use nncombinator::persistence::{Linear, LinearPersistence, Persistence};
+
+    use crate::checkpoint::{load_training_state, save_training_state, Checkpoint, CheckpointLayout, CheckpointNetwork, CheckpointPersistence, CheckpointWriter, NETWORK_PREFIX};
+    use crate::collection::VecImages;
+    use crate::layer::{BatchNormalization, ConvolutionLayer};
+    use crate::optimizer::{ConvolutionOptimizer, OptimizerValueState};
+
+    struct InputLayer {
+        values:Vec<f32>,
+    }
+    impl<T> Persistence<f32,T,Linear> for InputLayer where T: LinearPersistence<f32> {
+        fn load(&mut self, persistence: &mut T) -> Result<(), ConfigReadError> {
+            for v in self.values.iter_mut() {
+                *v = persistence.read()?;
+            }
+
+            Ok(())
+        }
+
+        fn save(&mut self, persistence: &mut T) -> Result<(), PersistenceError> {
+            for v in self.values.iter() {
+                persistence.write(*v)?;
+            }
+
+            Ok(())
+        }
+    }
+    impl CheckpointNetwork for InputLayer {
+        fn layout(layout: &mut CheckpointLayout) {
+            let layer = layout.next_layer();
+
+            layout.push(layer,"values",&[5]);
+        }
+    }
+
+    struct Momentum {
+        velocity:f32,
+    }
+    impl Optimizer<f32> for Momentum {
+        fn update(&mut self, e: f32, w: &mut f32) {
+            self.velocity = 0.9 * self.velocity - 0.01 * e;
+            *w += self.velocity;
+        }
+    }
+    impl OptimizerValueState<f32> for Momentum {
+        fn state_len(&self) -> usize {
+            1
+        }
+
+        fn write_state(&self, dst: &mut [f32]) {
+            dst[0] = self.velocity;
+        }
+
+        fn read_state(&mut self, src: &[f32]) {
+            self.velocity = src[0];
+        }
+    }
+
+    type Layer = ConvolutionLayer<f32,InputLayer,(),2,3,4,4,3,3,1,1>;
+
+    fn layer(seed:f32) -> Layer {
+        let mut n = seed;
+
+        ConvolutionLayer::new(InputLayer { values:vec![seed; 5] },&(),|| { n += 0.25; n },|| { n -= 0.5; n })
+    }
+
+    #[test]
+    fn test_training_state_round_trip() {
+        let mut network = layer(1.);
+        let mut optimizer = ConvolutionOptimizer::<f32,Momentum,3,2,3,3>::new(|| Momentum { velocity:0. });
+
+        let kernel_gradient = network.kernel().clone();
+        let bias_gradient = network.bias().clone();
+
+        network.update(&mut optimizer,&kernel_gradient,&bias_gradient).unwrap();
+
+        let file = std::env::temp_dir().join(format!("nncombinator-convolution-checkpoint-{}.safetensors",std::process::id()));
+
+        save_training_state(&file,&mut network,&optimizer).unwrap();
+
+        let mut restored = layer(-3.);
+        let mut restored_optimizer = ConvolutionOptimizer::<f32,Momentum,3,2,3,3>::new(|| Momentum { velocity:0. });
+
+        load_training_state(&file,&mut restored,&mut restored_optimizer).unwrap();
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(network.parent().values,restored.parent().values);
+        assert_eq!(network.kernel().as_raw_slice(),restored.kernel().as_raw_slice());
+        assert_eq!(&network.bias()[..],&restored.bias()[..]);
+
+        for (a,b) in optimizer.kernel_optimizer().optimizers().iter().zip(restored_optimizer.kernel_optimizer().optimizers().iter()) {
+            assert_eq!(a.velocity,b.velocity);
+        }
+
+        for (a,b) in optimizer.bias_optimizer().optimizers().iter().zip(restored_optimizer.bias_optimizer().optimizers().iter()) {
+            assert_eq!(a.velocity,b.velocity);
+        }
+
+        network.update(&mut optimizer,&kernel_gradient,&bias_gradient).unwrap();
+        restored.update(&mut restored_optimizer,&kernel_gradient,&bias_gradient).unwrap();
+
+        assert_eq!(network.kernel().as_raw_slice(),restored.kernel().as_raw_slice());
+        assert_eq!(&network.bias()[..],&restored.bias()[..]);
+    }
+
+    #[test]
+    fn test_network_rejects_trailing_values() {
+        let mut network = layer(1.);
+        let mut persistence = CheckpointPersistence::new();
+
+        network.save(&mut persistence).unwrap();
+
+        let mut values = persistence.values().to_vec();
+
+        values.push(0.);
+
+        let mut persistence = CheckpointPersistence::with_values(values);
+
+        network.load(&mut persistence).unwrap();
+
+        assert!(persistence.verify_eof().is_err());
+    }
+
+    #[test]
+    fn test_network_rejects_truncated_values() {
+        let mut network = layer(1.);
+        let mut persistence = CheckpointPersistence::new();
+
+        network.save(&mut persistence).unwrap();
+
+        let values = persistence.values()[..persistence.values().len() - 1].to_vec();
+
+        let mut persistence = CheckpointPersistence::with_values(values);
+
+        assert!(network.load(&mut persistence).is_err());
+    }
+
+    #[test]
+    fn test_network_tensors_are_named_per_parameter() {
+        let mut network = layer(1.);
+        let mut writer = CheckpointWriter::new();
+
+        writer.add_network(NETWORK_PREFIX,&mut network).unwrap();
+
+        let bytes = writer.to_bytes();
+        let checkpoint = Checkpoint::from_bytes(&bytes).unwrap();
+
+        assert_eq!(checkpoint.names().collect::<Vec<&str>>(),vec![
+            "network.layer0.values",
+            "network.layer1.bias",
+            "network.layer1.kernel",
+            "network.layer1.kernel_shape"
+        ]);
+
+        assert_eq!(checkpoint.info("network.layer0.values").unwrap().shape,vec![5]);
+        assert_eq!(checkpoint.info("network.layer1.kernel_shape").unwrap().shape,vec![4]);
+        assert_eq!(checkpoint.info("network.layer1.bias").unwrap().shape,vec![3]);
+        assert_eq!(checkpoint.info("network.layer1.kernel").unwrap().shape,vec![3,2,3,3]);
+
+        let mut bias = Arr::<f32,3>::new();
+
+        checkpoint.read_arr("network.layer1.bias",&mut bias).unwrap();
+
+        assert_eq!(&bias[..],&network.bias()[..]);
+
+        let mut kernel = vec![0f32; 3 * 2 * 3 * 3];
+
+        checkpoint.read("network.layer1.kernel",&[3,2,3,3],&mut kernel).unwrap();
+
+        assert_eq!(&kernel[..],network.kernel().as_raw_slice());
+
+        let mut restored = layer(-3.);
+
+        checkpoint.read_network(NETWORK_PREFIX,&mut restored).unwrap();
+
+        assert_eq!(network.parent().values,restored.parent().values);
+        assert_eq!(network.kernel().as_raw_slice(),restored.kernel().as_raw_slice());
+        assert_eq!(&network.bias()[..],&restored.bias()[..]);
+    }
+
+    #[test]
+    fn test_network_rejects_tensor_of_other_shape() {
+        let mut writer = CheckpointWriter::new();
+
+        writer.add_network(NETWORK_PREFIX,&mut layer(1.)).unwrap();
+
+        let bytes = writer.to_bytes();
+        let checkpoint = Checkpoint::from_bytes(&bytes).unwrap();
+
+        assert!(checkpoint.read_network(NETWORK_PREFIX,&mut ConvolutionLayer::<f32,InputLayer,(),2,3,4,4,1,1,0,1>::new(
+            InputLayer { values:vec![0.; 5] },&(),|| 0.,|| 0.
+        )).is_err());
+    }
+
+    #[test]
+    fn test_batch_norm_round_trip() {
+        let mut scale = Arr::<f32,3>::new();
+        let mut shift = Arr::<f32,3>::new();
+        let mut mean = Arr::<f32,3>::new();
+        let mut variance = Arr::<f32,3>::new();
+
+        for k in 0..3 {
+            scale[k] = 1. + k as f32;
+            shift[k] = -0.5 * k as f32;
+            mean[k] = 0.25 * k as f32;
+            variance[k] = 2. + k as f32;
+        }
+
+        let batch_norm = BatchNormalization::new(scale,shift,mean,variance,1e-3);
+
+        let mut writer = CheckpointWriter::new();
+
+        writer.add_batch_norm("bn.",&batch_norm).unwrap();
+
+        let bytes = writer.to_bytes();
+        let checkpoint = Checkpoint::from_bytes(&bytes).unwrap();
+
+        let restored = checkpoint.read_batch_norm::<f32,3>("bn.").unwrap();
+
+        assert_eq!(&batch_norm.scale()[..],&restored.scale()[..]);
+        assert_eq!(&batch_norm.shift()[..],&restored.shift()[..]);
+        assert_eq!(&batch_norm.mean()[..],&restored.mean()[..]);
+        assert_eq!(&batch_norm.variance()[..],&restored.variance()[..]);
+        assert_eq!(batch_norm.epsilon(),restored.epsilon());
+    }
+
+    #[test]
+    fn test_vec_images_rejects_corrupted_length() {
+        let mut writer = CheckpointWriter::new();
+        let images = VecImages::<f32,1,2,2>::with_size(2);
+
+        writer.add_vec_images("images",&images).unwrap();
+
+        let bytes = writer.to_bytes();
+        let len = u64::from_le_bytes([bytes[0],bytes[1],bytes[2],bytes[3],bytes[4],bytes[5],bytes[6],bytes[7]]) as usize;
+        let header = std::str::from_utf8(&bytes[8..8+len]).unwrap().replace("[2,1,2,2]","[1000000000000000,1,2,2]");
+
+        let mut corrupted = Vec::new();
+
+        corrupted.extend_from_slice(&(header.len() as u64).to_le_bytes());
+        corrupted.extend_from_slice(header.as_bytes());
+        corrupted.extend_from_slice(&bytes[8+len..]);
+
+        let checkpoint = Checkpoint::from_bytes(&corrupted).unwrap();
+
+        assert!(checkpoint.read_vec_images::<f32,1,2,2>("images").is_err());
+        assert!(checkpoint.read_vec_images::<f32,1,2,2>("missing").is_err());
+    }
+}
@@ -1,9 +1,9 @@
-use std::ops::{Index, IndexMut};
+use std::ops::{Bound, Index, IndexMut, RangeBounds};
 use nncombinator::arr::{Arr, ArrView, ArrViewMut};
 use nncombinator::error::SizeMismatchError;
 use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
 use rayon::iter::plumbing;
-use rayon::prelude::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::prelude::{IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 
 /// Images implementation
 #[derive(Debug,Eq,PartialEq)]
@@ -37,6 +37,173 @@ impl<T,const C:usize,const H:usize,const W:usize> Images<T,C,H,W> where T: Defau
     pub fn iter_mut<'a>(&'a mut self) -> ImagesIterMut<'a,T,H,W> {
         ImagesIterMut { arr: &mut *self.arr }
     }
+
+    /// Returns a reference to the element at the specified position, or None if it is out of bounds
+    pub fn get(&self, (c,y,x): (usize, usize, usize)) -> Option<&T> {
+        if c >= C || y >= H || x >= W {
+            None
+        } else {
+            self.arr.get(c * H * W + y * W + x)
+        }
+    }
+
+    /// Returns a mutable reference to the element at the specified position, or None if it is out of bounds
+    pub fn get_mut(&mut self, (c,y,x): (usize, usize, usize)) -> Option<&mut T> {
+        if c >= C || y >= H || x >= W {
+            None
+        } else {
+            self.arr.get_mut(c * H * W + y * W + x)
+        }
+    }
+
+    /// Returns a reference to the element at the specified position without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior
+    pub unsafe fn get_unchecked(&self, (c,y,x): (usize, usize, usize)) -> &T {
+        self.arr.get_unchecked(c * H * W + y * W + x)
+    }
+
+    /// Returns a mutable reference to the element at the specified position without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior
+    pub unsafe fn get_unchecked_mut(&mut self, (c,y,x): (usize, usize, usize)) -> &mut T {
+        self.arr.get_unchecked_mut(c * H * W + y * W + x)
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> Images<T,C,H,W> where T: Default + Clone + Send {
+    /// Elements of a channel, which is empty if H or W is 0
+    fn channel_slice(&self,c:usize) -> &[T] {
+        &self.arr[c * H * W..(c + 1) * H * W]
+    }
+
+    /// Create a new Images by applying a function to each element
+    /// # Arguments
+    /// * `f` - Function applied to each element
+    pub fn map<R,F>(&self,f:F) -> Images<R,C,H,W>
+        where R: Default + Clone + Send, F: Fn(&T) -> R {
+        Images {
+            arr:self.arr.iter().map(f).collect::<Vec<R>>().into_boxed_slice()
+        }
+    }
+
+    /// Create a new Images by applying a function to each pair of elements at the same position
+    /// # Arguments
+    /// * `other` - Images to be paired with
+    /// * `f` - Function applied to each pair of elements
+    pub fn zip_map<T2,R,F>(&self,other:&Images<T2,C,H,W>,f:F) -> Images<R,C,H,W>
+        where T2: Default + Clone + Send, R: Default + Clone + Send, F: Fn(&T,&T2) -> R {
+        Images {
+            arr:self.arr.iter().zip(other.arr.iter()).map(|(a,b)| f(a,b)).collect::<Vec<R>>().into_boxed_slice()
+        }
+    }
+
+    /// Create a new Images by applying a function to each channel
+    /// # Arguments
+    /// * `f` - Function that receives the index of the channel and its image
+    pub fn map_channels<R,F>(&self,f:F) -> Images<R,C,H,W>
+        where R: Default + Clone + Send, F: Fn(usize,ImageView<T,H,W>) -> Image<R,H,W> {
+        let mut buffer = Vec::with_capacity(C * H * W);
+
+        for (c,image) in self.iter().enumerate() {
+            buffer.extend_from_slice(&f(c,image).arr);
+        }
+
+        Images {
+            arr:buffer.into_boxed_slice()
+        }
+    }
+
+    /// Fold the elements of each channel
+    /// # Arguments
+    /// * `init` - Initial value of the accumulator
+    /// * `f` - Function that combines the accumulator and an element
+    pub fn fold_channels<A,F>(&self,init:A,f:F) -> Arr<A,C>
+        where A: Default + Clone + Send, F: Fn(A,&T) -> A {
+        (0..C).map(|c| {
+            self.channel_slice(c).iter().fold(init.clone(),&f)
+        }).collect::<Vec<A>>().try_into().expect("An error occurred in the conversion from Vec to Arr. The sizes do not match.")
+    }
+
+    /// Reduce the elements of each channel
+    /// # Arguments
+    /// * `f` - Function that combines two elements
+    pub fn reduce_channels<F>(&self,f:F) -> Arr<T,C> where F: Fn(T,T) -> T {
+        (0..C).map(|c| {
+            self.channel_slice(c).iter().cloned().reduce(&f).unwrap_or_default()
+        }).collect::<Vec<T>>().try_into().expect("An error occurred in the conversion from Vec to Arr. The sizes do not match.")
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> Images<T,C,H,W> where T: Default + Clone + Send + Sync {
+    /// Create a new Images by applying a function to each element in parallel
+    /// # Arguments
+    /// * `f` - Function applied to each element
+    pub fn par_map<R,F>(&self,f:F) -> Images<R,C,H,W>
+        where R: Default + Clone + Send, F: Fn(&T) -> R + Send + Sync {
+        Images {
+            arr:self.arr.par_iter().map(f).collect::<Vec<R>>().into_boxed_slice()
+        }
+    }
+
+    /// Create a new Images by applying a function to each pair of elements at the same position in parallel
+    /// # Arguments
+    /// * `other` - Images to be paired with
+    /// * `f` - Function applied to each pair of elements
+    pub fn par_zip_map<T2,R,F>(&self,other:&Images<T2,C,H,W>,f:F) -> Images<R,C,H,W>
+        where T2: Default + Clone + Send + Sync, R: Default + Clone + Send, F: Fn(&T,&T2) -> R + Send + Sync {
+        Images {
+            arr:self.arr.par_iter().zip(other.arr.par_iter()).map(|(a,b)| f(a,b)).collect::<Vec<R>>().into_boxed_slice()
+        }
+    }
+
+    /// Create a new Images by applying a function to each channel in parallel
+    /// # Arguments
+    /// * `f` - Function that receives the index of the channel and its image
+    pub fn par_map_channels<R,F>(&self,f:F) -> Images<R,C,H,W>
+        where R: Default + Clone + Send, F: Fn(usize,ImageView<T,H,W>) -> Image<R,H,W> + Send + Sync {
+        let images = (0..C).into_par_iter().map(|c| {
+            f(c,ImageView { arr: self.channel_slice(c) })
+        }).collect::<Vec<Image<R,H,W>>>();
+
+        let mut buffer = Vec::with_capacity(C * H * W);
+
+        for image in images.into_iter() {
+            buffer.extend_from_slice(&image.arr);
+        }
+
+        Images {
+            arr:buffer.into_boxed_slice()
+        }
+    }
+
+    /// Fold the elements of each channel in parallel
+    /// # Arguments
+    /// * `identity` - Function that produces the initial value of the accumulator
+    /// * `fold_op` - Function that combines the accumulator and an element
+    /// * `reduce_op` - Function that combines two accumulators
+    pub fn par_fold_channels<A,ID,F,RF>(&self,identity:ID,fold_op:F,reduce_op:RF) -> Arr<A,C>
+        where A: Default + Clone + Send,
+              ID: Fn() -> A + Send + Sync,
+              F: Fn(A,&T) -> A + Send + Sync,
+              RF: Fn(A,A) -> A + Send + Sync {
+        (0..C).into_par_iter().map(|c| {
+            self.channel_slice(c).par_iter().fold(&identity,&fold_op).reduce(&identity,&reduce_op)
+        }).collect::<Vec<A>>().try_into().expect("An error occurred in the conversion from Vec to Arr. The sizes do not match.")
+    }
+
+    /// Reduce the elements of each channel in parallel
+    /// # Arguments
+    /// * `identity` - Function that produces the identity element of `op`
+    /// * `op` - Function that combines two elements
+    pub fn par_reduce_channels<ID,F>(&self,identity:ID,op:F) -> Arr<T,C>
+        where ID: Fn() -> T + Send + Sync, F: Fn(T,T) -> T + Send + Sync {
+        (0..C).into_par_iter().map(|c| {
+            self.channel_slice(c).par_iter().cloned().reduce(&identity,&op)
+        }).collect::<Vec<T>>().try_into().expect("An error occurred in the conversion from Vec to Arr. The sizes do not match.")
+    }
 }
 impl<T,const C:usize, const H:usize, const W:usize> Index<(usize,usize,usize)> for Images<T,C,H,W> where T: Default + Clone + Send {
     type Output = T;
@@ -141,6 +308,22 @@ impl<'a,T,const C:usize,const H:usize,const W:usize> AsRawSlice<T> for Images<T,
         &self.arr
     }
 }
+impl<'a,T,const C:usize,const H:usize,const W:usize> AsRawMutSlice<'a,T> for Images<T,C,H,W> where T: Default + Clone + Send {
+    fn as_raw_mut_slice(&'a mut self) -> &'a mut [T] {
+        &mut self.arr
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> Images<T,C,H,W> where T: Default + Clone + Send {
+    /// Obtaining a immutable view
+    pub fn view(&self) -> ImagesView<T,C,H,W> {
+        ImagesView { arr: &self.arr }
+    }
+
+    /// Obtaining a mutable view
+    pub fn view_mut(&mut self) -> ImagesViewMut<T,C,H,W> {
+        ImagesViewMut { arr: &mut self.arr }
+    }
+}
 /// Implementation of an immutable view of a Images
 #[derive(Debug,Eq,PartialEq)]
 pub struct ImagesView<'a,T,const C:usize, const H:usize, const W:usize> where T: Default + Clone + Send {
@@ -151,6 +334,24 @@ impl<'a,T,const C:usize,const H:usize,const W:usize> ImagesView<'a,T,C,H,W> wher
     pub fn iter(&self) -> ImagesIter<'a,T,H,W> {
         ImagesIter { arr: self.arr }
     }
+
+    /// Returns a reference to the element at the specified position, or None if it is out of bounds
+    pub fn get(&self, (c,y,x): (usize, usize, usize)) -> Option<&T> {
+        if c >= C || y >= H || x >= W {
+            None
+        } else {
+            self.arr.get(c * H * W + y * W + x)
+        }
+    }
+
+    /// Returns a reference to the element at the specified position without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior
+    pub unsafe fn get_unchecked(&self, (c,y,x): (usize, usize, usize)) -> &T {
+        self.arr.get_unchecked(c * H * W + y * W + x)
+    }
 }
 impl<'a,T,const C:usize,const H:usize,const W:usize> Clone for ImagesView<'a,T,C,H,W> where T: Default + Clone + Send {
     fn clone(&self) -> Self {
@@ -192,6 +393,42 @@ impl<'a,T,const C:usize,const H:usize,const W:usize> ImagesViewMut<'a,T,C,H,W> w
     pub fn iter_mut(&'a mut self) -> ImagesIterMut<'a,T,H,W> {
         ImagesIterMut { arr: &mut self.arr }
     }
+
+    /// Returns a reference to the element at the specified position, or None if it is out of bounds
+    pub fn get(&self, (c,y,x): (usize, usize, usize)) -> Option<&T> {
+        if c >= C || y >= H || x >= W {
+            None
+        } else {
+            self.arr.get(c * H * W + y * W + x)
+        }
+    }
+
+    /// Returns a mutable reference to the element at the specified position, or None if it is out of bounds
+    pub fn get_mut(&mut self, (c,y,x): (usize, usize, usize)) -> Option<&mut T> {
+        if c >= C || y >= H || x >= W {
+            None
+        } else {
+            self.arr.get_mut(c * H * W + y * W + x)
+        }
+    }
+
+    /// Returns a reference to the element at the specified position without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior
+    pub unsafe fn get_unchecked(&self, (c,y,x): (usize, usize, usize)) -> &T {
+        self.arr.get_unchecked(c * H * W + y * W + x)
+    }
+
+    /// Returns a mutable reference to the element at the specified position without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior
+    pub unsafe fn get_unchecked_mut(&mut self, (c,y,x): (usize, usize, usize)) -> &mut T {
+        self.arr.get_unchecked_mut(c * H * W + y * W + x)
+    }
 }
 impl<'a,T,const C:usize, const H:usize, const W:usize> Index<(usize,usize,usize)> for ImagesViewMut<'a,T,C,H,W>
     where T: Default + Clone + Send {
@@ -221,6 +458,112 @@ impl<'a,T,const C:usize, const H:usize, const W:usize> IndexMut<(usize,usize,usi
         &mut self.arr[c * H * W + y * W + x]
     }
 }
+impl<'a,T,const C:usize,const H:usize,const W:usize> AsRawSlice<T> for ImagesViewMut<'a,T,C,H,W> where T: Default + Clone + Send {
+    fn as_raw_slice(&self) -> &[T] {
+        self.arr
+    }
+}
+impl<'a,'b,T,const C:usize,const H:usize,const W:usize> AsRawMutSlice<'b,T> for ImagesViewMut<'a,T,C,H,W> where T: Default + Clone + Send {
+    fn as_raw_mut_slice(&'b mut self) -> &'b mut [T] {
+        self.arr
+    }
+}
+/// Implementation of an element of a VecImages borrowed by indexing
+///
+/// Index and IndexMut have to return a reference, so this is an unsized type with the layout of a slice
+/// that keeps the number of channels, height and width of the element in its type.
+#[derive(Debug,Eq,PartialEq)]
+#[repr(transparent)]
+pub struct ImagesSlice<T,const C:usize, const H:usize, const W:usize> where T: Default + Clone + Send {
+    arr:[T],
+}
+impl<T,const C:usize,const H:usize,const W:usize> ImagesSlice<T,C,H,W> where T: Default + Clone + Send {
+    fn from_slice(arr:&[T]) -> &ImagesSlice<T,C,H,W> {
+        // ImagesSlice is repr(transparent) over [T], so the pointer can be reinterpreted with its length
+        unsafe { &*(arr as *const [T] as *const ImagesSlice<T,C,H,W>) }
+    }
+
+    fn from_slice_mut(arr:&mut [T]) -> &mut ImagesSlice<T,C,H,W> {
+        // ImagesSlice is repr(transparent) over [T], so the pointer can be reinterpreted with its length
+        unsafe { &mut *(arr as *mut [T] as *mut ImagesSlice<T,C,H,W>) }
+    }
+
+    /// Obtaining a immutable view
+    pub fn view(&self) -> ImagesView<T,C,H,W> {
+        ImagesView { arr: &self.arr }
+    }
+
+    /// Obtaining a mutable view
+    pub fn view_mut(&mut self) -> ImagesViewMut<T,C,H,W> {
+        ImagesViewMut { arr: &mut self.arr }
+    }
+
+    /// Obtaining a immutable iterator
+    pub fn iter(&self) -> ImagesIter<T,H,W> {
+        ImagesIter { arr: &self.arr }
+    }
+
+    /// Obtaining a mutable iterator
+    pub fn iter_mut(&mut self) -> ImagesIterMut<T,H,W> {
+        ImagesIterMut { arr: &mut self.arr }
+    }
+
+    /// Returns a reference to the element at the specified position, or None if it is out of bounds
+    pub fn get(&self, (c,y,x): (usize, usize, usize)) -> Option<&T> {
+        if c >= C || y >= H || x >= W {
+            None
+        } else {
+            self.arr.get(c * H * W + y * W + x)
+        }
+    }
+
+    /// Returns a mutable reference to the element at the specified position, or None if it is out of bounds
+    pub fn get_mut(&mut self, (c,y,x): (usize, usize, usize)) -> Option<&mut T> {
+        if c >= C || y >= H || x >= W {
+            None
+        } else {
+            self.arr.get_mut(c * H * W + y * W + x)
+        }
+    }
+}
+impl<T,const C:usize, const H:usize, const W:usize> Index<(usize,usize,usize)> for ImagesSlice<T,C,H,W>
+    where T: Default + Clone + Send {
+    type Output = T;
+
+    fn index(&self, (c,y,x): (usize, usize, usize)) -> &Self::Output {
+        if c >= C {
+            panic!("index out of bounds: the len is {} but the index is {}",C,c);
+        } else if y >= H {
+            panic!("index out of bounds: the len is {} but the index is {}",H,y);
+        } else if x >= W {
+            panic!("index out of bounds: the len is {} but the index is {}",W,x);
+        }
+        &self.arr[c * H * W + y * W + x]
+    }
+}
+impl<T,const C:usize, const H:usize, const W:usize> IndexMut<(usize,usize,usize)> for ImagesSlice<T,C,H,W>
+    where T: Default + Clone + Send {
+    fn index_mut(&mut self, (c,y,x): (usize, usize, usize)) -> &mut Self::Output {
+        if c >= C {
+            panic!("index out of bounds: the len is {} but the index is {}",C,c);
+        } else if y >= H {
+            panic!("index out of bounds: the len is {} but the index is {}",H,y);
+        } else if x >= W {
+            panic!("index out of bounds: the len is {} but the index is {}",W,x);
+        }
+        &mut self.arr[c * H * W + y * W + x]
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> AsRawSlice<T> for ImagesSlice<T,C,H,W> where T: Default + Clone + Send {
+    fn as_raw_slice(&self) -> &[T] {
+        &self.arr
+    }
+}
+impl<'a,T,const C:usize,const H:usize,const W:usize> AsRawMutSlice<'a,T> for ImagesSlice<T,C,H,W> where T: Default + Clone + Send {
+    fn as_raw_mut_slice(&'a mut self) -> &'a mut [T] {
+        &mut self.arr
+    }
+}
 /// Image Implementation
 #[derive(Debug,Eq,PartialEq)]
 pub struct Image<T,const H:usize,const W:usize> where T: Default + Clone + Send {
@@ -245,6 +588,123 @@ impl<T,const H:usize,const W:usize> Image<T,H,W> where T: Default + Clone + Send
     pub fn iter_mut<'a>(&'a mut self) -> ImageViewMut<'a,T,H,W> {
         ImageViewMut{ arr: &mut *self.arr }
     }
+
+    /// Returns a reference to the element at the specified position, or None if it is out of bounds
+    pub fn get(&self, (y,x): (usize, usize)) -> Option<&T> {
+        if y >= H || x >= W {
+            None
+        } else {
+            self.arr.get(y * W + x)
+        }
+    }
+
+    /// Returns a mutable reference to the element at the specified position, or None if it is out of bounds
+    pub fn get_mut(&mut self, (y,x): (usize, usize)) -> Option<&mut T> {
+        if y >= H || x >= W {
+            None
+        } else {
+            self.arr.get_mut(y * W + x)
+        }
+    }
+
+    /// Returns a reference to the element at the specified position without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior
+    pub unsafe fn get_unchecked(&self, (y,x): (usize, usize)) -> &T {
+        self.arr.get_unchecked(y * W + x)
+    }
+
+    /// Returns a mutable reference to the element at the specified position without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior
+    pub unsafe fn get_unchecked_mut(&mut self, (y,x): (usize, usize)) -> &mut T {
+        self.arr.get_unchecked_mut(y * W + x)
+    }
+}
+impl<T,const H:usize,const W:usize> Image<T,H,W> where T: Default + Clone + Send {
+    /// Create a new Image by applying a function to each element
+    /// # Arguments
+    /// * `f` - Function applied to each element
+    pub fn map<R,F>(&self,f:F) -> Image<R,H,W>
+        where R: Default + Clone + Send, F: Fn(&T) -> R {
+        Image {
+            arr:self.arr.iter().map(f).collect::<Vec<R>>().into_boxed_slice()
+        }
+    }
+
+    /// Create a new Image by applying a function to each pair of elements at the same position
+    /// # Arguments
+    /// * `other` - Image to be paired with
+    /// * `f` - Function applied to each pair of elements
+    pub fn zip_map<T2,R,F>(&self,other:&Image<T2,H,W>,f:F) -> Image<R,H,W>
+        where T2: Default + Clone + Send, R: Default + Clone + Send, F: Fn(&T,&T2) -> R {
+        Image {
+            arr:self.arr.iter().zip(other.arr.iter()).map(|(a,b)| f(a,b)).collect::<Vec<R>>().into_boxed_slice()
+        }
+    }
+
+    /// Fold the elements
+    /// # Arguments
+    /// * `init` - Initial value of the accumulator
+    /// * `f` - Function that combines the accumulator and an element
+    pub fn fold<A,F>(&self,init:A,f:F) -> A where F: Fn(A,&T) -> A {
+        self.arr.iter().fold(init,f)
+    }
+
+    /// Reduce the elements
+    /// # Arguments
+    /// * `f` - Function that combines two elements
+    pub fn reduce<F>(&self,f:F) -> T where F: Fn(T,T) -> T {
+        self.arr.iter().cloned().reduce(f).unwrap_or_default()
+    }
+}
+impl<T,const H:usize,const W:usize> Image<T,H,W> where T: Default + Clone + Send + Sync {
+    /// Create a new Image by applying a function to each element in parallel
+    /// # Arguments
+    /// * `f` - Function applied to each element
+    pub fn par_map<R,F>(&self,f:F) -> Image<R,H,W>
+        where R: Default + Clone + Send, F: Fn(&T) -> R + Send + Sync {
+        Image {
+            arr:self.arr.par_iter().map(f).collect::<Vec<R>>().into_boxed_slice()
+        }
+    }
+
+    /// Create a new Image by applying a function to each pair of elements at the same position in parallel
+    /// # Arguments
+    /// * `other` - Image to be paired with
+    /// * `f` - Function applied to each pair of elements
+    pub fn par_zip_map<T2,R,F>(&self,other:&Image<T2,H,W>,f:F) -> Image<R,H,W>
+        where T2: Default + Clone + Send + Sync, R: Default + Clone + Send, F: Fn(&T,&T2) -> R + Send + Sync {
+        Image {
+            arr:self.arr.par_iter().zip(other.arr.par_iter()).map(|(a,b)| f(a,b)).collect::<Vec<R>>().into_boxed_slice()
+        }
+    }
+
+    /// Fold the elements in parallel
+    /// # Arguments
+    /// * `identity` - Function that produces the initial value of the accumulator
+    /// * `fold_op` - Function that combines the accumulator and an element
+    /// * `reduce_op` - Function that combines two accumulators
+    pub fn par_fold<A,ID,F,RF>(&self,identity:ID,fold_op:F,reduce_op:RF) -> A
+        where A: Send,
+              ID: Fn() -> A + Send + Sync,
+              F: Fn(A,&T) -> A + Send + Sync,
+              RF: Fn(A,A) -> A + Send + Sync {
+        self.arr.par_iter().fold(&identity,fold_op).reduce(&identity,reduce_op)
+    }
+
+    /// Reduce the elements in parallel
+    /// # Arguments
+    /// * `identity` - Function that produces the identity element of `op`
+    /// * `op` - Function that combines two elements
+    pub fn par_reduce<ID,F>(&self,identity:ID,op:F) -> T
+        where ID: Fn() -> T + Send + Sync, F: Fn(T,T) -> T + Send + Sync {
+        self.arr.par_iter().cloned().reduce(identity,op)
+    }
 }
 impl<T,const H:usize,const W:usize> Clone for Image<T,H,W> where T: Default + Clone + Send {
     fn clone(&self) -> Self {
@@ -265,6 +725,16 @@ impl<T,const H:usize, const W:usize> Index<(usize,usize)> for Image<T,H,W> where
         &self.arr[y * W + x]
     }
 }
+impl<T,const H:usize, const W:usize> IndexMut<(usize,usize)> for Image<T,H,W> where T: Default + Clone + Send {
+    fn index_mut(&mut self, (y,x): (usize, usize)) -> &mut Self::Output {
+        if y >= H {
+            panic!("index out of bounds: the len is {} but the index is {}",H,y);
+        } else if x >= W {
+            panic!("index out of bounds: the len is {} but the index is {}",W,x);
+        }
+        &mut self.arr[y * W + x]
+    }
+}
 impl<T,const H:usize,const W:usize> TryFrom<Vec<Arr<T,W>>> for Image<T,H,W> where T: Default + Clone + Send {
     type Error = SizeMismatchError;
 
@@ -362,6 +832,37 @@ impl<'a,T,const H:usize,const W:usize> ImageView<'a,T,H,W> where T: Default + Cl
     const fn element_size(&self) -> usize {
         W
     }
+
+    /// Returns a reference to the element at the specified position, or None if it is out of bounds
+    pub fn get(&self, (y,x): (usize, usize)) -> Option<&T> {
+        if y >= H || x >= W {
+            None
+        } else {
+            self.arr.get(y * W + x)
+        }
+    }
+
+    /// Returns a reference to the element at the specified position without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior
+    pub unsafe fn get_unchecked(&self, (y,x): (usize, usize)) -> &T {
+        self.arr.get_unchecked(y * W + x)
+    }
+}
+impl<'a,T,const H:usize,const W:usize> ImageView<'a,T,H,W> where T: Default + Clone + Send {
+    /// Create a new Image by applying a function to each element
+    ///
+    /// This is not named map because ImageView is an Iterator over its rows and Iterator::map must stay reachable.
+    /// # Arguments
+    /// * `f` - Function applied to each element
+    pub fn map_elements<R,F>(&self,f:F) -> Image<R,H,W>
+        where R: Default + Clone + Send, F: Fn(&T) -> R {
+        Image {
+            arr:self.arr.iter().map(f).collect::<Vec<R>>().into_boxed_slice()
+        }
+    }
 }
 impl<'a,T,const H:usize,const W:usize> Iterator for ImageView<'a,T,H,W> where T: Default + Clone + Send {
     type Item = ArrView<'a,T,W>;
@@ -407,6 +908,42 @@ impl<'a,T,const H:usize,const W:usize> ImageViewMut<'a,T,H,W> where T: Default +
     const fn element_size(&self) -> usize {
         W
     }
+
+    /// Returns a reference to the element at the specified position, or None if it is out of bounds
+    pub fn get(&self, (y,x): (usize, usize)) -> Option<&T> {
+        if y >= H || x >= W {
+            None
+        } else {
+            self.arr.get(y * W + x)
+        }
+    }
+
+    /// Returns a mutable reference to the element at the specified position, or None if it is out of bounds
+    pub fn get_mut(&mut self, (y,x): (usize, usize)) -> Option<&mut T> {
+        if y >= H || x >= W {
+            None
+        } else {
+            self.arr.get_mut(y * W + x)
+        }
+    }
+
+    /// Returns a reference to the element at the specified position without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior
+    pub unsafe fn get_unchecked(&self, (y,x): (usize, usize)) -> &T {
+        self.arr.get_unchecked(y * W + x)
+    }
+
+    /// Returns a mutable reference to the element at the specified position without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with an out-of-bounds index is undefined behavior
+    pub unsafe fn get_unchecked_mut(&mut self, (y,x): (usize, usize)) -> &mut T {
+        self.arr.get_unchecked_mut(y * W + x)
+    }
 }
 impl<'a,T,const H:usize,const W:usize> Iterator for ImageViewMut<'a,T,H,W> where T: Default + Clone + Send {
     type Item = ArrViewMut<'a,T,W>;
@@ -683,7 +1220,7 @@ impl<'data,T, const H:usize, const W:usize> IntoParallelRefIterator<'data> for I
 /// Implement a fixed-length image array whose size is not specified by a type parameter.
 #[derive(Debug,Eq,PartialEq,Clone)]
 pub struct VecImages<T,const C:usize,const H:usize,const W:usize> where T: Default + Clone + Send {
-    arr:Box<[T]>,
+    arr:Vec<T>,
     len:usize,
 }
 impl<T,const C:usize,const H:usize,const W:usize> VecImages<T,C,H,W> where T: Default + Clone + Copy + Send {
@@ -702,7 +1239,7 @@ impl<T,const C:usize,const H:usize,const W:usize> VecImages<T,C,H,W> where T: De
         arr.resize_with(C * H * W * size,Default::default);
 
         VecImages {
-            arr:arr.into_boxed_slice(),
+            arr:arr,
             len:size,
         }
     }
@@ -716,21 +1253,486 @@ impl<T,const C:usize,const H:usize,const W:usize> VecImages<T,C,H,W> where T: De
     pub fn iter_mut(&mut self) -> VecImagesIterMut<T,C,H,W> {
         VecImagesIterMut { arr: &mut *self.arr }
     }
-}
-impl<T,const C:usize,const H:usize,const W:usize> From<Vec<Images<T,C,H,W>>> for VecImages<T,C,H,W>
-    where T: Default + Clone + Copy + Send {
-
-    fn from(items: Vec<Images<T,C,H,W>>) -> Self {
-        let len = items.len();
-
-        let mut buffer = Vec::with_capacity(len * C * H * W);
 
-        for item in items.into_iter() {
-            buffer.extend_from_slice(&item.arr);
+    /// Create an empty VecImages instance
+    pub fn new() -> VecImages<T,C,H,W> {
+        VecImages {
+            arr:Vec::new(),
+            len:0,
         }
+    }
 
+    /// Create an empty VecImages instance with room for the specified number of elements
+    /// # Arguments
+    /// * `capacity`- Number of elements to reserve
+    pub fn with_capacity(capacity:usize) -> VecImages<T,C,H,W> {
         VecImages {
-            arr:buffer.into_boxed_slice(),
+            arr:Vec::with_capacity(C * H * W * capacity),
+            len:0,
+        }
+    }
+
+    /// Returns true if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append an element to the end
+    /// # Arguments
+    /// * `images`- Element to be added
+    pub fn push(&mut self,images:Images<T,C,H,W>) {
+        self.arr.extend_from_slice(&images.arr);
+        self.len += 1;
+    }
+
+    /// Shorten to the specified number of elements
+    /// # Arguments
+    /// * `len`- Number of elements to keep
+    pub fn truncate(&mut self,len:usize) {
+        if len < self.len {
+            self.arr.truncate(C * H * W * len);
+            self.len = len;
+        }
+    }
+
+    /// Divide into two views at the specified position
+    /// # Arguments
+    /// * `mid`- Position to split at
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > len`
+    pub fn split_at(&self,mid:usize) -> (VecImagesView<T,C,H,W>,VecImagesView<T,C,H,W>) {
+        if mid > self.len {
+            panic!("index out of bounds: the len is {} but the index is {}",self.len,mid);
+        }
+
+        let (l,r) = self.arr.split_at(C * H * W * mid);
+
+        (VecImagesView { arr: l, len: mid }, VecImagesView { arr: r, len: self.len - mid })
+    }
+
+    /// Obtaining a view of the elements in the specified range
+    /// # Arguments
+    /// * `range`- Range of elements
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds
+    pub fn slice<R: RangeBounds<usize>>(&self,range:R) -> VecImagesView<T,C,H,W> {
+        let (start,end) = slice_range(range,self.len);
+
+        VecImagesView {
+            arr: &self.arr[C * H * W * start..C * H * W * end],
+            len: end - start
+        }
+    }
+
+    /// Obtaining a view of the whole
+    pub fn as_view(&self) -> VecImagesView<T,C,H,W> {
+        VecImagesView {
+            arr: &self.arr,
+            len: self.len
+        }
+    }
+
+    /// Obtaining a immutable view of the element at the specified position
+    /// # Arguments
+    /// * `index`- Position of element
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len`
+    pub fn view(&self,index:usize) -> ImagesView<T,C,H,W> {
+        if index >= self.len {
+            panic!("index out of bounds: the len is {} but the index is {}",self.len,index);
+        }
+
+        ImagesView { arr: &self.arr[C * H * W * index..C * H * W * (index + 1)] }
+    }
+
+    /// Obtaining a mutable view of the element at the specified position
+    /// # Arguments
+    /// * `index`- Position of element
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len`
+    pub fn view_mut(&mut self,index:usize) -> ImagesViewMut<T,C,H,W> {
+        if index >= self.len {
+            panic!("index out of bounds: the len is {} but the index is {}",self.len,index);
+        }
+
+        ImagesViewMut { arr: &mut self.arr[C * H * W * index..C * H * W * (index + 1)] }
+    }
+
+    /// Obtaining a immutable view of the element at the specified position, or None if it is out of bounds
+    /// # Arguments
+    /// * `index`- Position of element
+    pub fn get(&self,index:usize) -> Option<ImagesView<T,C,H,W>> {
+        if index >= self.len {
+            None
+        } else {
+            Some(ImagesView { arr: &self.arr[C * H * W * index..C * H * W * (index + 1)] })
+        }
+    }
+
+    /// Obtaining a mutable view of the element at the specified position, or None if it is out of bounds
+    /// # Arguments
+    /// * `index`- Position of element
+    pub fn get_mut(&mut self,index:usize) -> Option<ImagesViewMut<T,C,H,W>> {
+        if index >= self.len {
+            None
+        } else {
+            Some(ImagesViewMut { arr: &mut self.arr[C * H * W * index..C * H * W * (index + 1)] })
+        }
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> VecImages<T,C,H,W> where T: Default + Clone + Copy + Send {
+    /// Elements of the i-th channel counted across all elements, which is empty if H or W is 0
+    fn plane(&self,i:usize) -> &[T] {
+        &self.arr[i * H * W..(i + 1) * H * W]
+    }
+
+    /// Create a new VecImages by applying a function to each element
+    /// # Arguments
+    /// * `f` - Function applied to each element
+    pub fn map<R,F>(&self,f:F) -> VecImages<R,C,H,W>
+        where R: Default + Clone + Copy + Send, F: Fn(&T) -> R {
+        VecImages {
+            arr:self.arr.iter().map(f).collect::<Vec<R>>(),
+            len:self.len
+        }
+    }
+
+    /// Create a new VecImages by applying a function to each pair of elements at the same position
+    /// # Arguments
+    /// * `other` - VecImages to be paired with
+    /// * `f` - Function applied to each pair of elements
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`SizeMismatchError`]
+    pub fn zip_map<T2,R,F>(&self,other:&VecImages<T2,C,H,W>,f:F) -> Result<VecImages<R,C,H,W>,SizeMismatchError>
+        where T2: Default + Clone + Copy + Send, R: Default + Clone + Copy + Send, F: Fn(&T,&T2) -> R {
+        if self.len != other.len {
+            Err(SizeMismatchError(other.len,self.len))
+        } else {
+            Ok(VecImages {
+                arr:self.arr.iter().zip(other.arr.iter()).map(|(a,b)| f(a,b)).collect::<Vec<R>>(),
+                len:self.len
+            })
+        }
+    }
+
+    /// Create a new VecImages by applying a function to each channel of each element
+    /// # Arguments
+    /// * `f` - Function that receives the index of the channel and its image
+    pub fn map_channels<R,F>(&self,f:F) -> VecImages<R,C,H,W>
+        where R: Default + Clone + Copy + Send, F: Fn(usize,ImageView<T,H,W>) -> Image<R,H,W> {
+        let mut buffer = Vec::with_capacity(self.len * C * H * W);
+
+        for i in 0..self.len * C {
+            buffer.extend_from_slice(&f(i % C,ImageView { arr: self.plane(i) }).arr);
+        }
+
+        VecImages {
+            arr:buffer,
+            len:self.len
+        }
+    }
+
+    /// Fold the elements of each channel across all elements
+    /// # Arguments
+    /// * `init` - Initial value of the accumulator
+    /// * `f` - Function that combines the accumulator and an element
+    pub fn fold_channels<A,F>(&self,init:A,f:F) -> Arr<A,C>
+        where A: Default + Clone + Send, F: Fn(A,&T) -> A {
+        (0..C).map(|c| {
+            (0..self.len).flat_map(|i| self.plane(i * C + c).iter()).fold(init.clone(),&f)
+        }).collect::<Vec<A>>().try_into().expect("An error occurred in the conversion from Vec to Arr. The sizes do not match.")
+    }
+
+    /// Reduce the elements of each channel across all elements
+    /// # Arguments
+    /// * `f` - Function that combines two elements
+    pub fn reduce_channels<F>(&self,f:F) -> Arr<T,C> where F: Fn(T,T) -> T {
+        (0..C).map(|c| {
+            (0..self.len).flat_map(|i| self.plane(i * C + c).iter()).cloned().reduce(&f).unwrap_or_default()
+        }).collect::<Vec<T>>().try_into().expect("An error occurred in the conversion from Vec to Arr. The sizes do not match.")
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> VecImages<T,C,H,W> where T: Default + Clone + Copy + Send + Sync {
+    /// Create a new VecImages by applying a function to each element in parallel
+    /// # Arguments
+    /// * `f` - Function applied to each element
+    pub fn par_map<R,F>(&self,f:F) -> VecImages<R,C,H,W>
+        where R: Default + Clone + Copy + Send, F: Fn(&T) -> R + Send + Sync {
+        VecImages {
+            arr:self.arr.par_iter().map(f).collect::<Vec<R>>(),
+            len:self.len
+        }
+    }
+
+    /// Create a new VecImages by applying a function to each pair of elements at the same position in parallel
+    /// # Arguments
+    /// * `other` - VecImages to be paired with
+    /// * `f` - Function applied to each pair of elements
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`SizeMismatchError`]
+    pub fn par_zip_map<T2,R,F>(&self,other:&VecImages<T2,C,H,W>,f:F) -> Result<VecImages<R,C,H,W>,SizeMismatchError>
+        where T2: Default + Clone + Copy + Send + Sync, R: Default + Clone + Copy + Send, F: Fn(&T,&T2) -> R + Send + Sync {
+        if self.len != other.len {
+            Err(SizeMismatchError(other.len,self.len))
+        } else {
+            Ok(VecImages {
+                arr:self.arr.par_iter().zip(other.arr.par_iter()).map(|(a,b)| f(a,b)).collect::<Vec<R>>(),
+                len:self.len
+            })
+        }
+    }
+
+    /// Create a new VecImages by applying a function to each channel of each element in parallel
+    /// # Arguments
+    /// * `f` - Function that receives the index of the channel and its image
+    pub fn par_map_channels<R,F>(&self,f:F) -> VecImages<R,C,H,W>
+        where R: Default + Clone + Copy + Send, F: Fn(usize,ImageView<T,H,W>) -> Image<R,H,W> + Send + Sync {
+        let images = (0..self.len * C).into_par_iter().map(|i| {
+            f(i % C,ImageView { arr: self.plane(i) })
+        }).collect::<Vec<Image<R,H,W>>>();
+
+        let mut buffer = Vec::with_capacity(self.len * C * H * W);
+
+        for image in images.into_iter() {
+            buffer.extend_from_slice(&image.arr);
+        }
+
+        VecImages {
+            arr:buffer,
+            len:self.len
+        }
+    }
+
+    /// Fold the elements of each channel across all elements in parallel
+    /// # Arguments
+    /// * `identity` - Function that produces the initial value of the accumulator
+    /// * `fold_op` - Function that combines the accumulator and an element
+    /// * `reduce_op` - Function that combines two accumulators
+    pub fn par_fold_channels<A,ID,F,RF>(&self,identity:ID,fold_op:F,reduce_op:RF) -> Arr<A,C>
+        where A: Default + Clone + Send,
+              ID: Fn() -> A + Send + Sync,
+              F: Fn(A,&T) -> A + Send + Sync,
+              RF: Fn(A,A) -> A + Send + Sync {
+        (0..C).into_par_iter().map(|c| {
+            (0..self.len).into_par_iter()
+                .flat_map(|i| self.plane(i * C + c).par_iter())
+                .fold(&identity,&fold_op)
+                .reduce(&identity,&reduce_op)
+        }).collect::<Vec<A>>().try_into().expect("An error occurred in the conversion from Vec to Arr. The sizes do not match.")
+    }
+
+    /// Reduce the elements of each channel across all elements in parallel
+    /// # Arguments
+    /// * `identity` - Function that produces the identity element of `op`
+    /// * `op` - Function that combines two elements
+    pub fn par_reduce_channels<ID,F>(&self,identity:ID,op:F) -> Arr<T,C>
+        where ID: Fn() -> T + Send + Sync, F: Fn(T,T) -> T + Send + Sync {
+        (0..C).into_par_iter().map(|c| {
+            (0..self.len).into_par_iter()
+                .flat_map(|i| self.plane(i * C + c).par_iter().cloned())
+                .reduce(&identity,&op)
+        }).collect::<Vec<T>>().try_into().expect("An error occurred in the conversion from Vec to Arr. The sizes do not match.")
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> Index<usize> for VecImages<T,C,H,W> where T: Default + Clone + Copy + Send {
+    type Output = ImagesSlice<T,C,H,W>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        if index >= self.len {
+            panic!("index out of bounds: the len is {} but the index is {}",self.len,index);
+        }
+        ImagesSlice::from_slice(&self.arr[C * H * W * index..C * H * W * (index + 1)])
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> IndexMut<usize> for VecImages<T,C,H,W> where T: Default + Clone + Copy + Send {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        if index >= self.len {
+            panic!("index out of bounds: the len is {} but the index is {}",self.len,index);
+        }
+        ImagesSlice::from_slice_mut(&mut self.arr[C * H * W * index..C * H * W * (index + 1)])
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> Extend<Images<T,C,H,W>> for VecImages<T,C,H,W>
+    where T: Default + Clone + Copy + Send {
+    fn extend<I: IntoIterator<Item=Images<T,C,H,W>>>(&mut self, iter: I) {
+        for images in iter {
+            self.push(images);
+        }
+    }
+}
+impl<'data,T,const C:usize,const H:usize,const W:usize> Extend<ImagesView<'data,T,C,H,W>> for VecImages<T,C,H,W>
+    where T: Default + Clone + Copy + Send {
+    fn extend<I: IntoIterator<Item=ImagesView<'data,T,C,H,W>>>(&mut self, iter: I) {
+        for images in iter {
+            self.arr.extend_from_slice(images.arr);
+            self.len += 1;
+        }
+    }
+}
+/// Convert a range into the start and end positions for a sequence of the specified length
+fn slice_range<R: RangeBounds<usize>>(range:R,len:usize) -> (usize,usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&s) => s,
+        Bound::Excluded(&s) => s + 1,
+        Bound::Unbounded => 0,
+    };
+
+    let end = match range.end_bound() {
+        Bound::Included(&e) => e + 1,
+        Bound::Excluded(&e) => e,
+        Bound::Unbounded => len,
+    };
+
+    if start > end {
+        panic!("slice index starts at {} but ends at {}",start,end);
+    } else if end > len {
+        panic!("range end index {} out of range for slice of length {}",end,len);
+    }
+
+    (start,end)
+}
+/// Implementation of an immutable view of a VecImages
+#[derive(Debug,Eq,PartialEq)]
+pub struct VecImagesView<'a,T,const C:usize,const H:usize,const W:usize> where T: Default + Clone + Send {
+    arr:&'a [T],
+    len:usize,
+}
+impl<'a,T,const C:usize,const H:usize,const W:usize> Clone for VecImagesView<'a,T,C,H,W> where T: Default + Clone + Send {
+    fn clone(&self) -> Self {
+        VecImagesView {
+            arr: self.arr,
+            len: self.len
+        }
+    }
+}
+impl<'a,T,const C:usize,const H:usize,const W:usize> VecImagesView<'a,T,C,H,W> where T: Default + Clone + Copy + Send {
+    /// get the number of element
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Obtaining a immutable iterator
+    pub fn iter(&self) -> VecImagesIter<'a,T,C,H,W> {
+        VecImagesIter { arr: self.arr }
+    }
+
+    /// Divide into two views at the specified position
+    /// # Arguments
+    /// * `mid`- Position to split at
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > len`
+    pub fn split_at(&self,mid:usize) -> (VecImagesView<'a,T,C,H,W>,VecImagesView<'a,T,C,H,W>) {
+        if mid > self.len {
+            panic!("index out of bounds: the len is {} but the index is {}",self.len,mid);
+        }
+
+        let (l,r) = self.arr.split_at(C * H * W * mid);
+
+        (VecImagesView { arr: l, len: mid }, VecImagesView { arr: r, len: self.len - mid })
+    }
+
+    /// Obtaining a view of the elements in the specified range
+    /// # Arguments
+    /// * `range`- Range of elements
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds
+    pub fn slice<R: RangeBounds<usize>>(&self,range:R) -> VecImagesView<'a,T,C,H,W> {
+        let (start,end) = slice_range(range,self.len);
+
+        VecImagesView {
+            arr: &self.arr[C * H * W * start..C * H * W * end],
+            len: end - start
+        }
+    }
+
+    /// Obtaining a immutable view of the element at the specified position
+    /// # Arguments
+    /// * `index`- Position of element
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len`
+    pub fn view(&self,index:usize) -> ImagesView<'a,T,C,H,W> {
+        if index >= self.len {
+            panic!("index out of bounds: the len is {} but the index is {}",self.len,index);
+        }
+
+        ImagesView { arr: &self.arr[C * H * W * index..C * H * W * (index + 1)] }
+    }
+}
+impl<'a,T,const C:usize,const H:usize,const W:usize> Index<usize> for VecImagesView<'a,T,C,H,W> where T: Default + Clone + Copy + Send {
+    type Output = ImagesSlice<T,C,H,W>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        if index >= self.len {
+            panic!("index out of bounds: the len is {} but the index is {}",self.len,index);
+        }
+        ImagesSlice::from_slice(&self.arr[C * H * W * index..C * H * W * (index + 1)])
+    }
+}
+impl<'a,T,const C:usize,const H:usize,const W:usize> AsRawSlice<T> for VecImagesView<'a,T,C,H,W> where T: Default + Clone + Send {
+    fn as_raw_slice(&self) -> &[T] {
+        &self.arr
+    }
+}
+impl<'a,T,const C:usize,const H:usize,const W:usize> From<VecImagesView<'a,T,C,H,W>> for VecImages<T,C,H,W>
+    where T: Default + Clone + Copy + Send {
+    fn from(view: VecImagesView<'a,T,C,H,W>) -> Self {
+        VecImages {
+            arr:view.arr.to_vec(),
+            len:view.len,
+        }
+    }
+}
+impl<'data,T, const C:usize, const H:usize, const W:usize> IntoParallelRefIterator<'data> for VecImagesView<'data,T,C,H,W>
+    where T: Default + Clone + Copy + Send + Sync + 'static {
+    type Iter = VecImagesParIter<'data,T,C,H,W>;
+    type Item = ImagesView<'data,T,C,H,W>;
+
+    fn par_iter(&'data self) -> Self::Iter {
+        VecImagesParIter {
+            arr: self.arr,
+            len: self.len
+        }
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> From<Vec<Images<T,C,H,W>>> for VecImages<T,C,H,W>
+    where T: Default + Clone + Copy + Send {
+
+    fn from(items: Vec<Images<T,C,H,W>>) -> Self {
+        let len = items.len();
+
+        let mut buffer = Vec::with_capacity(len * C * H * W);
+
+        for item in items.into_iter() {
+            buffer.extend_from_slice(&item.arr);
+        }
+
+        VecImages {
+            arr:buffer,
             len:len,
         }
     }
@@ -748,7 +1750,7 @@ for VecImages<T,C,H,W> where T: Default + Clone + Copy + Send {
         }
 
         VecImages {
-            arr:buffer.into_boxed_slice(),
+            arr:buffer,
             len:len,
         }
     }
@@ -957,3 +1959,500 @@ impl<'data,T, const C:usize, const H:usize, const W:usize> IntoParallelRefIterat
         }
     }
 }
+/// Implementation of Images whose number of channels, height and width are determined at runtime
+#[derive(Debug,Eq,PartialEq,Clone)]
+pub struct DynImages<T> where T: Default + Clone + Send {
+    arr:Box<[T]>,
+    c:usize,
+    h:usize,
+    w:usize,
+}
+impl<T> DynImages<T> where T: Default + Clone + Send {
+    /// Create an instance of DynImages
+    /// # Arguments
+    /// * `c` - Number of channels
+    /// * `h` - Height
+    /// * `w` - Width
+    pub fn new(c:usize,h:usize,w:usize) -> DynImages<T> {
+        let mut arr = Vec::with_capacity(c * h * w);
+        arr.resize_with(c * h * w,Default::default);
+
+        DynImages {
+            arr:arr.into_boxed_slice(),
+            c:c,
+            h:h,
+            w:w
+        }
+    }
+
+    /// Create an instance of DynImages from a slice
+    /// # Arguments
+    /// * `arr` - Slice of length c * h * w
+    /// * `c` - Number of channels
+    /// * `h` - Height
+    /// * `w` - Width
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`SizeMismatchError`]
+    pub fn from_slice(arr:&[T],c:usize,h:usize,w:usize) -> Result<DynImages<T>,SizeMismatchError> {
+        if arr.len() != c * h * w {
+            Err(SizeMismatchError(arr.len(),c * h * w))
+        } else {
+            Ok(DynImages {
+                arr:arr.to_vec().into_boxed_slice(),
+                c:c,
+                h:h,
+                w:w
+            })
+        }
+    }
+
+    /// get the number of channels
+    pub fn channels(&self) -> usize {
+        self.c
+    }
+
+    /// get the height
+    pub fn height(&self) -> usize {
+        self.h
+    }
+
+    /// get the width
+    pub fn width(&self) -> usize {
+        self.w
+    }
+}
+impl<T> Index<(usize,usize,usize)> for DynImages<T> where T: Default + Clone + Send {
+    type Output = T;
+
+    fn index(&self, (c,y,x): (usize, usize, usize)) -> &Self::Output {
+        if c >= self.c {
+            panic!("index out of bounds: the len is {} but the index is {}",self.c,c);
+        } else if y >= self.h {
+            panic!("index out of bounds: the len is {} but the index is {}",self.h,y);
+        } else if x >= self.w {
+            panic!("index out of bounds: the len is {} but the index is {}",self.w,x);
+        }
+        &self.arr[c * self.h * self.w + y * self.w + x]
+    }
+}
+impl<T> IndexMut<(usize,usize,usize)> for DynImages<T> where T: Default + Clone + Send {
+    fn index_mut(&mut self, (c,y,x): (usize, usize, usize)) -> &mut Self::Output {
+        if c >= self.c {
+            panic!("index out of bounds: the len is {} but the index is {}",self.c,c);
+        } else if y >= self.h {
+            panic!("index out of bounds: the len is {} but the index is {}",self.h,y);
+        } else if x >= self.w {
+            panic!("index out of bounds: the len is {} but the index is {}",self.w,x);
+        }
+        &mut self.arr[c * self.h * self.w + y * self.w + x]
+    }
+}
+impl<T> AsRawSlice<T> for DynImages<T> where T: Default + Clone + Send {
+    fn as_raw_slice(&self) -> &[T] {
+        &self.arr
+    }
+}
+impl<'a,T> AsRawMutSlice<'a,T> for DynImages<T> where T: Default + Clone + Send {
+    fn as_raw_mut_slice(&'a mut self) -> &'a mut [T] {
+        &mut self.arr
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> From<Images<T,C,H,W>> for DynImages<T> where T: Default + Clone + Send {
+    fn from(images: Images<T,C,H,W>) -> Self {
+        DynImages {
+            arr:images.arr,
+            c:C,
+            h:H,
+            w:W
+        }
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> TryFrom<DynImages<T>> for Images<T,C,H,W> where T: Default + Clone + Send {
+    type Error = SizeMismatchError;
+
+    fn try_from(images: DynImages<T>) -> Result<Self,SizeMismatchError> {
+        if images.c != C {
+            Err(SizeMismatchError(images.c,C))
+        } else if images.h != H {
+            Err(SizeMismatchError(images.h,H))
+        } else if images.w != W {
+            Err(SizeMismatchError(images.w,W))
+        } else {
+            Ok(Images {
+                arr:images.arr
+            })
+        }
+    }
+}
+/// Implementation of an immutable view of a DynImages
+#[derive(Debug,Eq,PartialEq)]
+pub struct DynImagesView<'a,T> where T: Default + Clone + Send {
+    arr:&'a [T],
+    c:usize,
+    h:usize,
+    w:usize,
+}
+impl<'a,T> DynImagesView<'a,T> where T: Default + Clone + Send {
+    /// get the number of channels
+    pub fn channels(&self) -> usize {
+        self.c
+    }
+
+    /// get the height
+    pub fn height(&self) -> usize {
+        self.h
+    }
+
+    /// get the width
+    pub fn width(&self) -> usize {
+        self.w
+    }
+}
+impl<'a,T> Clone for DynImagesView<'a,T> where T: Default + Clone + Send {
+    fn clone(&self) -> Self {
+        DynImagesView {
+            arr:self.arr,
+            c:self.c,
+            h:self.h,
+            w:self.w
+        }
+    }
+}
+impl<'a,T> Index<(usize,usize,usize)> for DynImagesView<'a,T> where T: Default + Clone + Send {
+    type Output = T;
+
+    fn index(&self, (c,y,x): (usize, usize, usize)) -> &Self::Output {
+        if c >= self.c {
+            panic!("index out of bounds: the len is {} but the index is {}",self.c,c);
+        } else if y >= self.h {
+            panic!("index out of bounds: the len is {} but the index is {}",self.h,y);
+        } else if x >= self.w {
+            panic!("index out of bounds: the len is {} but the index is {}",self.w,x);
+        }
+        &self.arr[c * self.h * self.w + y * self.w + x]
+    }
+}
+impl<'a,T> AsRawSlice<T> for DynImagesView<'a,T> where T: Default + Clone + Send {
+    fn as_raw_slice(&self) -> &[T] {
+        &self.arr
+    }
+}
+/// Implementation of VecImages whose number of channels, height and width are determined at runtime
+#[derive(Debug,Eq,PartialEq,Clone)]
+pub struct DynVecImages<T> where T: Default + Clone + Send {
+    arr:Box<[T]>,
+    len:usize,
+    c:usize,
+    h:usize,
+    w:usize,
+}
+impl<T> DynVecImages<T> where T: Default + Clone + Send {
+    /// Create a DynVecImages instance of the specified size
+    /// # Arguments
+    /// * `size`- Size to be secured
+    /// * `c` - Number of channels
+    /// * `h` - Height
+    /// * `w` - Width
+    pub fn with_size(size:usize,c:usize,h:usize,w:usize) -> DynVecImages<T> {
+        let mut arr = Vec::with_capacity(c * h * w * size);
+
+        arr.resize_with(c * h * w * size,Default::default);
+
+        DynVecImages {
+            arr:arr.into_boxed_slice(),
+            len:size,
+            c:c,
+            h:h,
+            w:w
+        }
+    }
+
+    /// get the number of element
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// get the number of channels
+    pub fn channels(&self) -> usize {
+        self.c
+    }
+
+    /// get the height
+    pub fn height(&self) -> usize {
+        self.h
+    }
+
+    /// get the width
+    pub fn width(&self) -> usize {
+        self.w
+    }
+
+    /// Obtaining a immutable iterator
+    pub fn iter(&self) -> DynVecImagesIter<T> {
+        DynVecImagesIter {
+            arr: &*self.arr,
+            c:self.c,
+            h:self.h,
+            w:self.w
+        }
+    }
+}
+impl<T> TryFrom<Vec<DynImages<T>>> for DynVecImages<T> where T: Default + Clone + Send {
+    type Error = SizeMismatchError;
+
+    fn try_from(items: Vec<DynImages<T>>) -> Result<Self,SizeMismatchError> {
+        let len = items.len();
+
+        let (c,h,w) = items.first().map(|i| (i.c,i.h,i.w)).unwrap_or((0,0,0));
+
+        let mut buffer = Vec::with_capacity(len * c * h * w);
+
+        for item in items.into_iter() {
+            if item.c != c {
+                return Err(SizeMismatchError(item.c,c));
+            } else if item.h != h {
+                return Err(SizeMismatchError(item.h,h));
+            } else if item.w != w {
+                return Err(SizeMismatchError(item.w,w));
+            }
+            buffer.extend_from_slice(&item.arr);
+        }
+
+        Ok(DynVecImages {
+            arr:buffer.into_boxed_slice(),
+            len:len,
+            c:c,
+            h:h,
+            w:w
+        })
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> From<VecImages<T,C,H,W>> for DynVecImages<T> where T: Default + Clone + Send {
+    fn from(images: VecImages<T,C,H,W>) -> Self {
+        DynVecImages {
+            arr:images.arr.into_boxed_slice(),
+            len:images.len,
+            c:C,
+            h:H,
+            w:W
+        }
+    }
+}
+impl<T,const C:usize,const H:usize,const W:usize> TryFrom<DynVecImages<T>> for VecImages<T,C,H,W> where T: Default + Clone + Send {
+    type Error = SizeMismatchError;
+
+    fn try_from(images: DynVecImages<T>) -> Result<Self,SizeMismatchError> {
+        if images.c != C {
+            Err(SizeMismatchError(images.c,C))
+        } else if images.h != H {
+            Err(SizeMismatchError(images.h,H))
+        } else if images.w != W {
+            Err(SizeMismatchError(images.w,W))
+        } else {
+            Ok(VecImages {
+                arr:images.arr.into_vec(),
+                len:images.len
+            })
+        }
+    }
+}
+impl<T> AsRawSlice<T> for DynVecImages<T> where T: Default + Clone + Send {
+    fn as_raw_slice(&self) -> &[T] {
+        &self.arr
+    }
+}
+impl<'a,T> AsRawMutSlice<'a,T> for DynVecImages<T> where T: Default + Clone + Send {
+    fn as_raw_mut_slice(&'a mut self) -> &'a mut [T] {
+        &mut self.arr
+    }
+}
+/// DynVecImages's Immutable Iterator
+#[derive(Debug,Eq,PartialEq)]
+pub struct DynVecImagesIter<'a,T> where T: Default + Clone + Send {
+    arr:&'a [T],
+    c:usize,
+    h:usize,
+    w:usize,
+}
+impl<'a,T> DynVecImagesIter<'a,T> where T: Default + Clone + Send {
+    /// Number of elements encompassed by the iterator element
+    fn element_size(&self) -> usize {
+        self.c * self.h * self.w
+    }
+}
+impl<'a,T> Iterator for DynVecImagesIter<'a,T> where T: Default + Clone + Send {
+    type Item = DynImagesView<'a,T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slice = std::mem::replace(&mut self.arr, &mut []);
+        if slice.is_empty() {
+            None
+        } else {
+            let (l,r) = slice.split_at(self.element_size());
+
+            self.arr = r;
+
+            Some(DynImagesView {
+                arr: l,
+                c:self.c,
+                h:self.h,
+                w:self.w
+            })
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+
+    use crate::collection::{Image, Images, VecImages};
+
+    fn image() -> Image<f32,2,3> {
+        let mut image = Image::new();
+
+        for y in 0..2 {
+            for x in 0..3 {
+                image[(y,x)] = (y * 3 + x) as f32;
+            }
+        }
+
+        image
+    }
+
+    #[test]
+    fn test_image_view_map_elements_and_rows() {
+        let image = image();
+
+        let doubled = image.iter().map_elements(|&v| v * 2.);
+
+        for y in 0..2 {
+            for x in 0..3 {
+                assert_eq!(doubled[(y,x)],image[(y,x)] * 2.);
+            }
+        }
+
+        let sums = image.iter().map(|row| row.iter().sum::<f32>()).collect::<Vec<f32>>();
+
+        assert_eq!(sums,vec![3.,12.]);
+    }
+
+    fn images() -> Images<f32,2,2,3> {
+        let mut images = Images::new();
+
+        for (i,p) in images.as_raw_mut_slice().iter_mut().enumerate() {
+            *p = i as f32;
+        }
+
+        images
+    }
+
+    fn vec_images() -> VecImages<f32,2,2,3> {
+        let mut images = VecImages::with_size(3);
+
+        for (i,p) in images.as_raw_mut_slice().iter_mut().enumerate() {
+            *p = i as f32;
+        }
+
+        images
+    }
+
+    #[test]
+    fn test_images_map() {
+        let images = images();
+
+        let doubled = images.map(|&v| v * 2.);
+        let sums = images.zip_map(&doubled,|&a,&b| a + b);
+
+        assert_eq!(doubled.as_raw_slice(),&(0..12).map(|i| i as f32 * 2.).collect::<Vec<f32>>()[..]);
+        assert_eq!(sums.as_raw_slice(),&(0..12).map(|i| i as f32 * 3.).collect::<Vec<f32>>()[..]);
+
+        assert_eq!(images.par_map(|&v| v * 2.),doubled);
+        assert_eq!(images.par_zip_map(&doubled,|&a,&b| a + b),sums);
+
+        let shifted = images.map_channels(|c,image| image.map_elements(|&v| v + 100. * c as f32));
+
+        assert_eq!(shifted.as_raw_slice(),&[
+            0.,1.,2.,3.,4.,5.,
+            106.,107.,108.,109.,110.,111.
+        ]);
+        assert_eq!(images.par_map_channels(|c,image| image.map_elements(|&v| v + 100. * c as f32)),shifted);
+    }
+
+    #[test]
+    fn test_images_fold_and_reduce_channels() {
+        let images = images();
+
+        let sums = images.fold_channels(0.,|acc,&v| acc + v);
+        let maxima = images.reduce_channels(f32::max);
+
+        assert_eq!(&sums[..],&[15.,51.]);
+        assert_eq!(&maxima[..],&[5.,11.]);
+
+        assert_eq!(&images.par_fold_channels(|| 0.,|acc,&v| acc + v,|a,b| a + b)[..],&sums[..]);
+        assert_eq!(&images.par_reduce_channels(|| f32::MIN,f32::max)[..],&maxima[..]);
+    }
+
+    #[test]
+    fn test_vec_images_map() {
+        let images = vec_images();
+
+        let doubled = images.map(|&v| v * 2.);
+        let sums = images.zip_map(&doubled,|&a,&b| a + b).unwrap();
+
+        assert_eq!(doubled.as_raw_slice(),&(0..36).map(|i| i as f32 * 2.).collect::<Vec<f32>>()[..]);
+        assert_eq!(sums.as_raw_slice(),&(0..36).map(|i| i as f32 * 3.).collect::<Vec<f32>>()[..]);
+
+        assert_eq!(images.par_map(|&v| v * 2.),doubled);
+        assert_eq!(images.par_zip_map(&doubled,|&a,&b| a + b).unwrap(),sums);
+
+        assert!(images.zip_map(&VecImages::<f32,2,2,3>::with_size(2),|&a,&b| a + b).is_err());
+        assert!(images.par_zip_map(&VecImages::<f32,2,2,3>::with_size(2),|&a,&b| a + b).is_err());
+
+        let shifted = images.map_channels(|c,image| image.map_elements(|&v| v + 100. * c as f32));
+
+        for (i,(&a,&b)) in shifted.as_raw_slice().iter().zip(images.as_raw_slice().iter()).enumerate() {
+            assert_eq!(a,b + 100. * ((i / 6) % 2) as f32);
+        }
+
+        assert_eq!(images.par_map_channels(|c,image| image.map_elements(|&v| v + 100. * c as f32)),shifted);
+    }
+
+    #[test]
+    fn test_vec_images_fold_and_reduce_channels() {
+        let images = vec_images();
+
+        let sums = images.fold_channels(0.,|acc,&v| acc + v);
+        let maxima = images.reduce_channels(f32::max);
+
+        // Channel 0 holds 0..6, 12..18 and 24..30, channel 1 holds 6..12, 18..24 and 30..36
+        assert_eq!(&sums[..],&[15. + 87. + 159.,51. + 123. + 195.]);
+        assert_eq!(&maxima[..],&[29.,35.]);
+
+        assert_eq!(&images.par_fold_channels(|| 0.,|acc,&v| acc + v,|a,b| a + b)[..],&sums[..]);
+        assert_eq!(&images.par_reduce_channels(|| f32::MIN,f32::max)[..],&maxima[..]);
+    }
+
+    #[test]
+    fn test_zero_sized_images() {
+        let images = Images::<f32,2,0,3>::new();
+
+        assert_eq!(&images.fold_channels(1.,|acc,&v| acc + v)[..],&[1.,1.]);
+        assert_eq!(&images.reduce_channels(f32::max)[..],&[0.,0.]);
+        assert_eq!(&images.par_fold_channels(|| 1.,|acc,&v| acc + v,|a,b| a + b)[..],&[1.,1.]);
+        assert_eq!(&images.par_reduce_channels(|| 0.,f32::max)[..],&[0.,0.]);
+        assert!(images.map_channels(|_,image| image.map_elements(|&v| v)).as_raw_slice().is_empty());
+        assert!(images.par_map_channels(|_,image| image.map_elements(|&v| v)).as_raw_slice().is_empty());
+
+        let images = VecImages::<f32,2,3,0>::with_size(4);
+
+        assert_eq!(&images.fold_channels(1.,|acc,&v| acc + v)[..],&[1.,1.]);
+        assert_eq!(&images.reduce_channels(f32::max)[..],&[0.,0.]);
+        assert_eq!(&images.par_fold_channels(|| 1.,|acc,&v| acc + v,|a,b| a + b)[..],&[1.,1.]);
+        assert_eq!(&images.par_reduce_channels(|| 0.,f32::max)[..],&[0.,0.]);
+        assert!(images.map_channels(|_,image| image.map_elements(|&v| v)).as_raw_slice().is_empty());
+        assert!(images.par_map_channels(|_,image| image.map_elements(|&v| v)).as_raw_slice().is_empty());
+    }
+}
@@ -0,0 +1,356 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, sync_channel};
+use std::thread;
+use std::thread::JoinHandle;
+use nncombinator::error::SizeMismatchError;
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand_xorshift::XorShiftRng;
+
+use crate::collection::VecImages;
+
+/// Error raised when a BatchLoader is created
+#[derive(Debug)]
+pub enum BatchLoaderError {
+    /// The batch size is 0
+    ZeroBatchSize,
+    /// The number of images and the number of labels do not match
+    SizeMismatchError(SizeMismatchError),
+}
+impl fmt::Display for BatchLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchLoaderError::ZeroBatchSize => write!(f, "The batch size must be at least 1."),
+            BatchLoaderError::SizeMismatchError(e) => write!(f, "The number of images and the number of labels do not match. ({})", e),
+        }
+    }
+}
+impl Error for BatchLoaderError {}
+impl From<SizeMismatchError> for BatchLoaderError {
+    fn from(err: SizeMismatchError) -> BatchLoaderError {
+        BatchLoaderError::SizeMismatchError(err)
+    }
+}
+/// Order of the samples of one epoch, from which mini-batches are assembled
+struct BatchPlan<T,L,const C:usize,const H:usize,const W:usize> where T: Default + Clone + Copy + Send {
+    images:Arc<VecImages<T,C,H,W>>,
+    labels:Arc<Vec<L>>,
+    indices:Vec<usize>,
+    position:usize,
+    batch_size:usize,
+    drop_last:bool,
+}
+impl<T,L,const C:usize,const H:usize,const W:usize> BatchPlan<T,L,C,H,W>
+    where T: Default + Clone + Copy + Send, L: Clone {
+    /// Assemble the next mini-batch, or None at the end of the epoch
+    fn next_batch(&mut self) -> Option<(VecImages<T,C,H,W>,Vec<L>)> {
+        let rest = self.indices.len() - self.position;
+
+        if rest == 0 || (self.drop_last && rest < self.batch_size) {
+            return None;
+        }
+
+        let indices = &self.indices[self.position..self.position + rest.min(self.batch_size)];
+
+        self.position += indices.len();
+
+        let mut images = VecImages::with_capacity(indices.len());
+
+        images.extend(indices.iter().map(|&i| self.images.view(i)));
+
+        let labels = indices.iter().map(|&i| self.labels[i].clone()).collect::<Vec<L>>();
+
+        Some((images,labels))
+    }
+}
+/// Implementation of a loader that yields shuffled mini-batches of images and the corresponding labels
+pub struct BatchLoader<T,L,const C:usize,const H:usize,const W:usize> where T: Default + Clone + Copy + Send {
+    images:Arc<VecImages<T,C,H,W>>,
+    labels:Arc<Vec<L>>,
+    batch_size:usize,
+    drop_last:bool,
+    shuffle:bool,
+    prefetch:usize,
+    rnd:XorShiftRng,
+}
+impl<T,L,const C:usize,const H:usize,const W:usize> BatchLoader<T,L,C,H,W>
+    where T: Default + Clone + Copy + Send + Sync + 'static,
+          L: Clone + Send + Sync + 'static {
+    /// Create an instance of BatchLoader
+    /// # Arguments
+    /// * `images` - Images of the dataset
+    /// * `labels` - Labels of the dataset, one per image
+    /// * `batch_size` - Number of samples per mini-batch
+    /// * `seed` - Seed of the random number generator used for shuffling
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`BatchLoaderError`]
+    pub fn new(images:VecImages<T,C,H,W>,labels:Vec<L>,batch_size:usize,seed:u64) -> Result<BatchLoader<T,L,C,H,W>,BatchLoaderError> {
+        if batch_size == 0 {
+            return Err(BatchLoaderError::ZeroBatchSize);
+        } else if images.len() != labels.len() {
+            return Err(BatchLoaderError::from(SizeMismatchError(images.len(),labels.len())));
+        }
+
+        Ok(BatchLoader {
+            images:Arc::new(images),
+            labels:Arc::new(labels),
+            batch_size:batch_size,
+            drop_last:false,
+            shuffle:true,
+            prefetch:0,
+            rnd:XorShiftRng::seed_from_u64(seed),
+        })
+    }
+
+    /// Set whether to drop the last mini-batch if it is smaller than the batch size (default false)
+    /// # Arguments
+    /// * `drop_last` - true to drop the last partial mini-batch
+    pub fn drop_last(mut self,drop_last:bool) -> BatchLoader<T,L,C,H,W> {
+        self.drop_last = drop_last;
+        self
+    }
+
+    /// Set whether to shuffle the samples at the start of each epoch (default true)
+    /// # Arguments
+    /// * `shuffle` - false to yield the samples in the order of the dataset
+    pub fn shuffle(mut self,shuffle:bool) -> BatchLoader<T,L,C,H,W> {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Set the number of mini-batches prepared in advance on a background thread (default 0, no background thread)
+    /// # Arguments
+    /// * `prefetch` - Number of mini-batches to prepare in advance
+    pub fn prefetch(mut self,prefetch:usize) -> BatchLoader<T,L,C,H,W> {
+        self.prefetch = prefetch;
+        self
+    }
+
+    /// get the number of samples
+    pub fn sample_count(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// get the number of mini-batches per epoch
+    pub fn len(&self) -> usize {
+        if self.drop_last {
+            self.labels.len() / self.batch_size
+        } else {
+            (self.labels.len() + self.batch_size - 1) / self.batch_size
+        }
+    }
+
+    /// Returns true if an epoch yields no mini-batch
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Start an epoch, shuffling the samples with the random number generator of this loader
+    pub fn epoch(&mut self) -> BatchIter<T,L,C,H,W> {
+        let mut indices = (0..self.labels.len()).collect::<Vec<usize>>();
+
+        if self.shuffle {
+            indices.shuffle(&mut self.rnd);
+        }
+
+        let mut plan = BatchPlan {
+            images:Arc::clone(&self.images),
+            labels:Arc::clone(&self.labels),
+            indices:indices,
+            position:0,
+            batch_size:self.batch_size,
+            drop_last:self.drop_last,
+        };
+
+        let source = if self.prefetch == 0 {
+            BatchSource::Direct(plan)
+        } else {
+            let (sender,receiver) = sync_channel(self.prefetch);
+
+            let handle = thread::spawn(move || {
+                while let Some(batch) = plan.next_batch() {
+                    if sender.send(batch).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            BatchSource::Prefetch {
+                receiver:receiver,
+                handle:Some(handle)
+            }
+        };
+
+        BatchIter {
+            source:source
+        }
+    }
+}
+/// Where the mini-batches of an epoch come from
+enum BatchSource<T,L,const C:usize,const H:usize,const W:usize> where T: Default + Clone + Copy + Send {
+    /// Mini-batches are assembled when requested
+    Direct(BatchPlan<T,L,C,H,W>),
+    /// Mini-batches are assembled in advance on a background thread
+    Prefetch {
+        receiver:Receiver<(VecImages<T,C,H,W>,Vec<L>)>,
+        handle:Option<JoinHandle<()>>,
+    }
+}
+/// Iterator over the mini-batches of one epoch
+pub struct BatchIter<T,L,const C:usize,const H:usize,const W:usize> where T: Default + Clone + Copy + Send {
+    source:BatchSource<T,L,C,H,W>,
+}
+impl<T,L,const C:usize,const H:usize,const W:usize> Iterator for BatchIter<T,L,C,H,W>
+    where T: Default + Clone + Copy + Send, L: Clone {
+    type Item = (VecImages<T,C,H,W>,Vec<L>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.source {
+            BatchSource::Direct(plan) => plan.next_batch(),
+            BatchSource::Prefetch { receiver, handle } => {
+                match receiver.recv() {
+                    Ok(batch) => Some(batch),
+                    Err(_) => {
+                        if let Some(handle) = handle.take() {
+                            if let Err(e) = handle.join() {
+                                std::panic::resume_unwind(e);
+                            }
+                        }
+                        None
+                    }
+                }
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+
+    use crate::collection::VecImages;
+    use crate::dataset::batch::{BatchLoader, BatchLoaderError};
+
+    /// Create a loader of n images whose pixels and label are all the index of the sample
+    fn loader(n:usize,batch_size:usize,seed:u64) -> BatchLoader<u32,u32,2,2,3> {
+        let mut images = VecImages::<u32,2,2,3>::with_size(n);
+
+        for (i,image) in images.as_raw_mut_slice().chunks_mut(2 * 2 * 3).enumerate() {
+            image.iter_mut().for_each(|p| *p = i as u32);
+        }
+
+        BatchLoader::new(images,(0..n as u32).collect(),batch_size,seed).unwrap()
+    }
+
+    fn collect(loader:&mut BatchLoader<u32,u32,2,2,3>) -> Vec<(Vec<u32>,Vec<u32>)> {
+        loader.epoch().map(|(images,labels)| (images.as_raw_slice().to_vec(),labels)).collect()
+    }
+
+    #[test]
+    fn test_zero_batch_size() {
+        let loader = BatchLoader::<u8,u8,1,2,2>::new(VecImages::with_capacity(0),Vec::new(),0,1);
+
+        assert!(matches!(loader,Err(BatchLoaderError::ZeroBatchSize)));
+    }
+
+    #[test]
+    fn test_label_count_mismatch() {
+        let loader = BatchLoader::<u8,u8,1,2,2>::new(VecImages::with_capacity(0),vec![0],4,1);
+
+        assert!(matches!(loader,Err(BatchLoaderError::SizeMismatchError(_))));
+    }
+
+    #[test]
+    fn test_same_seed_same_order() {
+        let a = collect(&mut loader(23,4,7));
+        let b = collect(&mut loader(23,4,7));
+        let c = collect(&mut loader(23,4,8));
+
+        assert_eq!(a,b);
+        assert_ne!(a,c);
+
+        let mut loader = loader(23,4,7);
+
+        let first = collect(&mut loader);
+        let second = collect(&mut loader);
+
+        assert_eq!(first,a);
+        assert_ne!(first,second);
+    }
+
+    #[test]
+    fn test_labels_aligned_with_images() {
+        let mut seen = Vec::new();
+
+        for (images,labels) in loader(23,4,3).epoch() {
+            assert_eq!(images.len(),labels.len());
+
+            for (image,label) in images.as_raw_slice().chunks(2 * 2 * 3).zip(labels.iter()) {
+                assert!(image.iter().all(|p| p == label));
+            }
+
+            seen.extend(labels);
+        }
+
+        seen.sort();
+
+        assert_eq!(seen,(0..23).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_no_shuffle_keeps_order() {
+        let labels = collect(&mut loader(10,4,1).shuffle(false)).into_iter().flat_map(|(_,l)| l).collect::<Vec<u32>>();
+
+        assert_eq!(labels,(0..10).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_drop_last_and_len_agree() {
+        for &(n,batch_size) in [(23,4),(24,4),(3,4),(0,4),(1,1)].iter() {
+            for &drop_last in [false,true].iter() {
+                let mut loader = loader(n,batch_size,5).drop_last(drop_last);
+
+                let batches = collect(&mut loader);
+
+                assert_eq!(batches.len(),loader.len(),"n={} batch_size={} drop_last={}",n,batch_size,drop_last);
+                assert_eq!(loader.is_empty(),batches.is_empty());
+
+                for (i,(_,labels)) in batches.iter().enumerate() {
+                    if drop_last || i + 1 < batches.len() {
+                        assert_eq!(labels.len(),batch_size);
+                    } else {
+                        assert!(labels.len() >= 1 && labels.len() <= batch_size);
+                    }
+                }
+
+                let count = batches.iter().map(|(_,l)| l.len()).sum::<usize>();
+
+                assert_eq!(count,if drop_last { n / batch_size * batch_size } else { n });
+            }
+        }
+    }
+
+    #[test]
+    fn test_prefetch_yields_same_batches() {
+        let mut direct = loader(37,5,11);
+
+        let expected = (0..3).map(|_| collect(&mut direct)).collect::<Vec<_>>();
+
+        for &prefetch in [1,2,16].iter() {
+            let mut prefetched = loader(37,5,11).prefetch(prefetch);
+
+            let actual = (0..3).map(|_| collect(&mut prefetched)).collect::<Vec<_>>();
+
+            assert_eq!(actual,expected,"prefetch={}",prefetch);
+        }
+
+        let mut prefetched = loader(37,5,11).prefetch(2);
+
+        assert_eq!(prefetched.epoch().take(2).count(),2);
+        assert_eq!(collect(&mut prefetched),expected[1]);
+    }
+}
@@ -0,0 +1,257 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use nncombinator::error::ConfigReadError;
+use nncombinator::mem::AsRawMutSlice;
+
+use crate::collection::{Images, VecImages};
+use crate::dataset::read_record;
+
+/// Number of bytes of the image of a record
+const CIFAR_IMAGE_SIZE:usize = 3 * 32 * 32;
+
+/// Kind of binary batch files of CIFAR
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum CifarKind {
+    /// CIFAR-10, whose records have one label byte
+    Cifar10,
+    /// CIFAR-100, whose records have a coarse label byte and a fine label byte
+    Cifar100,
+}
+impl CifarKind {
+    /// Number of label bytes of a record
+    fn label_size(&self) -> usize {
+        match self {
+            CifarKind::Cifar10 => 1,
+            CifarKind::Cifar100 => 2,
+        }
+    }
+}
+/// Label of a record of CIFAR
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct CifarLabel {
+    /// Coarse label (CIFAR-100 only)
+    pub coarse:Option<u8>,
+    /// Label of CIFAR-10 or fine label of CIFAR-100
+    pub fine:u8,
+}
+/// Implementation of an iterator that reads records one by one from a binary batch file of CIFAR
+pub struct CifarReader {
+    reader:BufReader<File>,
+    name:String,
+    kind:CifarKind,
+    len:usize,
+    index:usize,
+}
+impl CifarReader {
+    /// Open a binary batch file
+    /// # Arguments
+    /// * `file` - Path of the file (e.g. data_batch_1.bin)
+    /// * `kind` - Kind of the file
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn open<P: AsRef<Path>>(file:P,kind:CifarKind) -> Result<CifarReader,ConfigReadError> {
+        let name = file.as_ref().display().to_string();
+
+        let file = File::open(file)?;
+
+        let size = file.metadata()?.len() as usize;
+        let record_size = kind.label_size() + CIFAR_IMAGE_SIZE;
+
+        if size % record_size != 0 {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The size of the file {} ({} bytes) is not a multiple of the record size ({} bytes). The file is truncated or is not a {:?} file.",
+                name,size,record_size,kind
+            )));
+        }
+
+        Ok(CifarReader {
+            reader:BufReader::new(file),
+            name:name,
+            kind:kind,
+            len:size / record_size,
+            index:0
+        })
+    }
+
+    /// get the number of records
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Read all remaining records
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read_all(self) -> Result<(VecImages<u8,3,32,32>,Vec<CifarLabel>),ConfigReadError> {
+        let mut images = VecImages::with_capacity(self.len - self.index);
+        let mut labels = Vec::with_capacity(self.len - self.index);
+
+        for r in self {
+            let (i,l) = r?;
+
+            images.push(i);
+            labels.push(l);
+        }
+
+        Ok((images,labels))
+    }
+}
+impl Iterator for CifarReader {
+    type Item = Result<(Images<u8,3,32,32>,CifarLabel),ConfigReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.len {
+            None
+        } else {
+            self.index += 1;
+
+            let mut label = [0u8; 2];
+            let mut images = Images::new();
+
+            let label_size = self.kind.label_size();
+
+            Some(read_record(&mut self.reader,&mut label[..label_size],&self.name).and_then(|_| {
+                read_record(&mut self.reader,images.as_raw_mut_slice(),&self.name)
+            }).map(|_| {
+                let label = match self.kind {
+                    CifarKind::Cifar10 => CifarLabel { coarse: None, fine: label[0] },
+                    CifarKind::Cifar100 => CifarLabel { coarse: Some(label[0]), fine: label[1] },
+                };
+
+                (images,label)
+            }))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len - self.index, Some(self.len - self.index))
+    }
+}
+/// Read the images and labels of binary batch files of CIFAR-10
+/// # Arguments
+/// * `files` - Paths of the files (e.g. data_batch_1.bin ... data_batch_5.bin)
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`ConfigReadError`]
+pub fn load_cifar10<P: AsRef<Path>>(files:&[P]) -> Result<(VecImages<u8,3,32,32>,Vec<u8>),ConfigReadError> {
+    let mut images = VecImages::new();
+    let mut labels = Vec::new();
+
+    for f in files.iter() {
+        let (i,l) = CifarReader::open(f,CifarKind::Cifar10)?.read_all()?;
+
+        images.extend(i.iter());
+        labels.extend(l.into_iter().map(|l| l.fine));
+    }
+
+    Ok((images,labels))
+}
+/// Read the images and labels of a binary file of CIFAR-100
+/// # Arguments
+/// * `file` - Path of the file (e.g. train.bin)
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`ConfigReadError`]
+pub fn load_cifar100<P: AsRef<Path>>(file:P) -> Result<(VecImages<u8,3,32,32>,Vec<CifarLabel>),ConfigReadError> {
+    CifarReader::open(file,CifarKind::Cifar100)?.read_all()
+}
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use nncombinator::mem::AsRawSlice;
+
+    use crate::dataset::cifar::{load_cifar10, load_cifar100, CifarKind, CifarLabel, CifarReader, CIFAR_IMAGE_SIZE};
+
+    fn write_records(name:&str,labels:&[&[u8]]) -> PathBuf {
+        let file = std::env::temp_dir().join(format!("nncombinator-convolution-{}-{}",name,std::process::id()));
+
+        let mut bytes = Vec::new();
+
+        for (i,l) in labels.iter().enumerate() {
+            bytes.extend_from_slice(l);
+            bytes.extend((0..CIFAR_IMAGE_SIZE).map(|p| ((p + i * 7) % 256) as u8));
+        }
+
+        std::fs::write(&file,bytes).unwrap();
+
+        file
+    }
+
+    fn pixels(i:usize) -> Vec<u8> {
+        (0..CIFAR_IMAGE_SIZE).map(|p| ((p + i * 7) % 256) as u8).collect()
+    }
+
+    #[test]
+    fn test_cifar10_records() {
+        let file = write_records("cifar10",&[&[3],&[9]]);
+
+        let reader = CifarReader::open(&file,CifarKind::Cifar10);
+        let loaded = load_cifar10(&[&file]);
+
+        std::fs::remove_file(&file).unwrap();
+
+        let reader = reader.unwrap();
+
+        assert_eq!(reader.len(),2);
+
+        let (images,labels) = reader.read_all().unwrap();
+
+        assert_eq!(labels,vec![CifarLabel { coarse:None, fine:3 },CifarLabel { coarse:None, fine:9 }]);
+        assert_eq!(images.len(),2);
+        assert_eq!(images.as_raw_slice(),&[pixels(0),pixels(1)].concat()[..]);
+
+        let (images,labels) = loaded.unwrap();
+
+        assert_eq!(labels,vec![3,9]);
+        assert_eq!(images.as_raw_slice(),&[pixels(0),pixels(1)].concat()[..]);
+    }
+
+    #[test]
+    fn test_cifar100_records() {
+        let file = write_records("cifar100",&[&[1,42],&[19,99],&[0,7]]);
+
+        let loaded = load_cifar100(&file);
+
+        std::fs::remove_file(&file).unwrap();
+
+        let (images,labels) = loaded.unwrap();
+
+        assert_eq!(labels,vec![
+            CifarLabel { coarse:Some(1), fine:42 },
+            CifarLabel { coarse:Some(19), fine:99 },
+            CifarLabel { coarse:Some(0), fine:7 }
+        ]);
+        assert_eq!(images.as_raw_slice(),&[pixels(0),pixels(1),pixels(2)].concat()[..]);
+    }
+
+    #[test]
+    fn test_size_not_multiple_of_record_size() {
+        let file = write_records("cifar-kind-mismatch",&[&[1,42],&[19,99]]);
+
+        let reader = CifarReader::open(&file,CifarKind::Cifar10);
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(reader.is_err());
+
+        let file = write_records("cifar-truncated",&[&[3]]);
+
+        std::fs::write(&file,&std::fs::read(&file).unwrap()[..CIFAR_IMAGE_SIZE]).unwrap();
+
+        let reader = CifarReader::open(&file,CifarKind::Cifar10);
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(reader.is_err());
+    }
+}
@@ -0,0 +1,333 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use nncombinator::error::ConfigReadError;
+use nncombinator::mem::AsRawMutSlice;
+
+use crate::collection::{Images, VecImages};
+use crate::dataset::{read_record, read_u32_be};
+
+/// Magic number of IDX files of images
+const IDX_IMAGES_MAGIC:u32 = 0x00000803;
+/// Magic number of IDX files of labels
+const IDX_LABELS_MAGIC:u32 = 0x00000801;
+/// Size of the header of IDX files of images (magic number, count, rows and columns)
+const IDX_IMAGES_HEADER_SIZE:usize = 16;
+/// Size of the header of IDX files of labels (magic number and count)
+const IDX_LABELS_HEADER_SIZE:usize = 8;
+
+/// Verify that the size of an IDX file matches the count recorded in its header
+fn verify_idx_size(name:&str,size:usize,header_size:usize,len:usize,record_size:usize) -> Result<(),ConfigReadError> {
+    let expected = len.checked_mul(record_size).and_then(|n| n.checked_add(header_size));
+
+    if expected != Some(size) {
+        return Err(ConfigReadError::InvalidState(format!(
+            "The size of the file {} ({} bytes) does not match the {} records of {} bytes recorded in the header. The file is truncated or the header is corrupted.",
+            name,size,len,record_size
+        )));
+    }
+
+    Ok(())
+}
+
+/// Implementation of an iterator that reads images one by one from an IDX file of MNIST
+pub struct MnistImagesReader<const H:usize,const W:usize> {
+    reader:BufReader<File>,
+    name:String,
+    len:usize,
+    index:usize,
+}
+impl<const H:usize,const W:usize> MnistImagesReader<H,W> {
+    /// Open an IDX file of images
+    /// # Arguments
+    /// * `file` - Path of the file (e.g. train-images-idx3-ubyte)
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn open<P: AsRef<Path>>(file:P) -> Result<MnistImagesReader<H,W>,ConfigReadError> {
+        let name = file.as_ref().display().to_string();
+
+        let file = File::open(file)?;
+
+        let size = file.metadata()?.len() as usize;
+
+        let mut reader = BufReader::new(file);
+
+        let magic = read_u32_be(&mut reader,&name)?;
+
+        if magic != IDX_IMAGES_MAGIC {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The file {} is not an IDX file of images. (magic number {:#010x})",name,magic
+            )));
+        }
+
+        let len = read_u32_be(&mut reader,&name)? as usize;
+        let rows = read_u32_be(&mut reader,&name)? as usize;
+        let cols = read_u32_be(&mut reader,&name)? as usize;
+
+        if rows != H || cols != W {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The size of the images in the file {} does not match. (expected {}x{}, actual {}x{})",name,H,W,rows,cols
+            )));
+        }
+
+        verify_idx_size(&name,size,IDX_IMAGES_HEADER_SIZE,len,H * W)?;
+
+        Ok(MnistImagesReader {
+            reader:reader,
+            name:name,
+            len:len,
+            index:0
+        })
+    }
+
+    /// get the number of images recorded in the header
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Read all remaining images
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read_all(self) -> Result<VecImages<u8,1,H,W>,ConfigReadError> {
+        let mut images = VecImages::with_capacity(self.len - self.index);
+
+        for i in self {
+            images.push(i?);
+        }
+
+        Ok(images)
+    }
+}
+impl<const H:usize,const W:usize> Iterator for MnistImagesReader<H,W> {
+    type Item = Result<Images<u8,1,H,W>,ConfigReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.len {
+            None
+        } else {
+            self.index += 1;
+
+            let mut images = Images::new();
+
+            Some(read_record(&mut self.reader,images.as_raw_mut_slice(),&self.name).map(|_| images))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len - self.index, Some(self.len - self.index))
+    }
+}
+/// Implementation of an iterator that reads labels one by one from an IDX file of MNIST
+pub struct MnistLabelsReader {
+    reader:BufReader<File>,
+    name:String,
+    len:usize,
+    index:usize,
+}
+impl MnistLabelsReader {
+    /// Open an IDX file of labels
+    /// # Arguments
+    /// * `file` - Path of the file (e.g. train-labels-idx1-ubyte)
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn open<P: AsRef<Path>>(file:P) -> Result<MnistLabelsReader,ConfigReadError> {
+        let name = file.as_ref().display().to_string();
+
+        let file = File::open(file)?;
+
+        let size = file.metadata()?.len() as usize;
+
+        let mut reader = BufReader::new(file);
+
+        let magic = read_u32_be(&mut reader,&name)?;
+
+        if magic != IDX_LABELS_MAGIC {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The file {} is not an IDX file of labels. (magic number {:#010x})",name,magic
+            )));
+        }
+
+        let len = read_u32_be(&mut reader,&name)? as usize;
+
+        verify_idx_size(&name,size,IDX_LABELS_HEADER_SIZE,len,1)?;
+
+        Ok(MnistLabelsReader {
+            reader:reader,
+            name:name,
+            len:len,
+            index:0
+        })
+    }
+
+    /// get the number of labels recorded in the header
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Read all remaining labels
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read_all(mut self) -> Result<Vec<u8>,ConfigReadError> {
+        let mut labels = vec![0u8; self.len - self.index];
+
+        read_record(&mut self.reader,&mut labels,&self.name)?;
+
+        self.index = self.len;
+
+        Ok(labels)
+    }
+}
+impl Iterator for MnistLabelsReader {
+    type Item = Result<u8,ConfigReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.len {
+            None
+        } else {
+            self.index += 1;
+
+            let mut label = [0u8; 1];
+
+            Some(read_record(&mut self.reader,&mut label,&self.name).map(|_| label[0]))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len - self.index, Some(self.len - self.index))
+    }
+}
+/// Implementation of an iterator that reads pairs of an image and its label from IDX files of MNIST
+pub struct MnistReader<const H:usize,const W:usize> {
+    images:MnistImagesReader<H,W>,
+    labels:MnistLabelsReader,
+}
+impl<const H:usize,const W:usize> MnistReader<H,W> {
+    /// Open IDX files of images and labels
+    /// # Arguments
+    /// * `images` - Path of the file of images
+    /// * `labels` - Path of the file of labels
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn open<P: AsRef<Path>,Q: AsRef<Path>>(images:P,labels:Q) -> Result<MnistReader<H,W>,ConfigReadError> {
+        let images = MnistImagesReader::open(images)?;
+        let labels = MnistLabelsReader::open(labels)?;
+
+        if images.len() != labels.len() {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The number of images ({}) and the number of labels ({}) do not match.",images.len(),labels.len()
+            )));
+        }
+
+        Ok(MnistReader {
+            images:images,
+            labels:labels
+        })
+    }
+
+    /// get the number of pairs
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Read all remaining pairs
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read_all(self) -> Result<(VecImages<u8,1,H,W>,Vec<u8>),ConfigReadError> {
+        Ok((self.images.read_all()?,self.labels.read_all()?))
+    }
+}
+impl<const H:usize,const W:usize> Iterator for MnistReader<H,W> {
+    type Item = Result<(Images<u8,1,H,W>,u8),ConfigReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match (self.images.next(),self.labels.next()) {
+            (Some(i),Some(l)) => Some(i.and_then(|i| l.map(|l| (i,l)))),
+            _ => None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.images.size_hint()
+    }
+}
+/// Read the images and labels of MNIST
+/// # Arguments
+/// * `images` - Path of the IDX file of images
+/// * `labels` - Path of the IDX file of labels
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`ConfigReadError`]
+pub fn load_mnist<P: AsRef<Path>,Q: AsRef<Path>>(images:P,labels:Q) -> Result<(VecImages<u8,1,28,28>,Vec<u8>),ConfigReadError> {
+    MnistReader::open(images,labels)?.read_all()
+}
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::dataset::mnist::{MnistImagesReader, MnistLabelsReader};
+
+    fn write_idx(name:&str,header:&[u32],data:&[u8]) -> PathBuf {
+        let file = std::env::temp_dir().join(format!("nncombinator-convolution-{}-{}",name,std::process::id()));
+
+        let mut bytes = header.iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<u8>>();
+
+        bytes.extend_from_slice(data);
+
+        std::fs::write(&file,bytes).unwrap();
+
+        file
+    }
+
+    #[test]
+    fn test_images_count_matching_file_size() {
+        let file = write_idx("images-ok",&[0x803,3,2,2],&[7u8; 12]);
+
+        let images = MnistImagesReader::<2,2>::open(&file).and_then(|r| r.read_all());
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(images.unwrap().len(),3);
+    }
+
+    #[test]
+    fn test_images_count_exceeding_file_size() {
+        let file = write_idx("images-truncated",&[0x803,u32::MAX,2,2],&[7u8; 12]);
+
+        let reader = MnistImagesReader::<2,2>::open(&file);
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(reader.is_err());
+    }
+
+    #[test]
+    fn test_labels_count_exceeding_file_size() {
+        let file = write_idx("labels-truncated",&[0x801,u32::MAX],&[1u8; 3]);
+
+        let reader = MnistLabelsReader::open(&file);
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(reader.is_err());
+    }
+}
@@ -0,0 +1,26 @@
+use std::io;
+use std::io::Read;
+use nncombinator::error::ConfigReadError;
+
+pub mod mnist;
+pub mod cifar;
+pub mod batch;
+
+/// Read exactly the size of the buffer, reporting a truncated file as an error
+fn read_record<R: Read>(reader:&mut R,buffer:&mut [u8],name:&str) -> Result<(),ConfigReadError> {
+    reader.read_exact(buffer).map_err(|e| {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            ConfigReadError::InvalidState(format!("The file {} is truncated.",name))
+        } else {
+            ConfigReadError::from(e)
+        }
+    })
+}
+/// Read a big-endian u32
+fn read_u32_be<R: Read>(reader:&mut R,name:&str) -> Result<u32,ConfigReadError> {
+    let mut buffer = [0u8; 4];
+
+    read_record(reader,&mut buffer,name)?;
+
+    Ok(u32::from_be_bytes(buffer))
+}
@@ -0,0 +1,1495 @@
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut, Range};
+use std::sync::Arc;
+use const_guards::guard;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use rayon::prelude::{IndexedParallelIterator, ParallelIterator, ParallelSliceMut};
+use nncombinator::arr::{Arr, Arr4};
+use nncombinator::error::{EvaluateError, SizeMismatchError, TrainingError};
+use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+use nncombinator::ope::UnitValue;
+
+use crate::collection::{Images, ImagesViewMut, VecImages};
+use crate::device::{ConvolutionWorkspace, DeviceConvolution, DeviceConvolutionInto, DeviceFusedConvolution, FusedActivation};
+use crate::device::simd::{self, SimdFloat};
+
+/// Order in which the terms of a convolution are summed
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Summation {
+    /// The input channels of a single sample are computed in parallel, and f32 and f64 use vectorized kernels
+    /// chosen by the features of the running CPU.
+    /// This is the fastest, but the order of summation is not specified, so results are not guaranteed to be reproducible.
+    Unordered,
+    /// Each value is summed by one thread in a fixed order, so the result is reproducible bit for bit
+    Sequential,
+    /// Like Sequential, but the terms are combined by a fixed pairwise tree, which reduces rounding errors
+    Pairwise,
+    /// Like Sequential, but with Kahan compensated summation, which reduces rounding errors
+    Kahan,
+}
+/// Default number of multiply-adds of a call below which the convolution runs sequentially
+pub const DEFAULT_SEQUENTIAL_CUTOFF:usize = 1 << 15;
+/// Configuration of DeviceCpuConfigured
+#[derive(Debug,Clone)]
+pub struct DeviceCpuConfig {
+    /// Order in which the terms of a convolution are summed
+    pub summation:Summation,
+    /// Thread pool on which the convolution runs, rayon's global pool if None
+    pub pool:Option<Arc<ThreadPool>>,
+    /// Number of multiply-adds of a call below which the convolution runs sequentially on the calling thread
+    pub sequential_cutoff:usize,
+}
+impl DeviceCpuConfig {
+    /// Create a configuration whose results are reproducible bit for bit
+    pub fn deterministic() -> DeviceCpuConfig {
+        DeviceCpuConfig {
+            summation:Summation::Sequential,
+            ..DeviceCpuConfig::default()
+        }
+    }
+
+    /// Set the order in which the terms of a convolution are summed
+    /// # Arguments
+    /// * `summation` - Order of summation
+    pub fn with_summation(mut self,summation:Summation) -> DeviceCpuConfig {
+        self.summation = summation;
+        self
+    }
+
+    /// Set the thread pool on which the convolution runs, such as a pool managed by the embedding application
+    /// # Arguments
+    /// * `pool` - Thread pool
+    pub fn with_thread_pool(mut self,pool:Arc<ThreadPool>) -> DeviceCpuConfig {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Create a dedicated thread pool on which the convolution runs
+    /// # Arguments
+    /// * `threads` - Number of threads, the number of logical CPUs if 0
+    /// * `pinning` - true to pin the i-th thread to the i-th logical CPU (only supported on Linux, ignored elsewhere)
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ThreadPoolBuildError`]
+    pub fn with_threads(self,threads:usize,pinning:bool) -> Result<DeviceCpuConfig,ThreadPoolBuildError> {
+        let builder = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("nncombinator-cnn-{}",i));
+
+        let builder = if pinning {
+            builder.start_handler(pin_current_thread)
+        } else {
+            builder
+        };
+
+        Ok(self.with_thread_pool(Arc::new(builder.build()?)))
+    }
+
+    /// Set the number of multiply-adds of a call below which the convolution runs sequentially
+    /// # Arguments
+    /// * `cutoff` - Number of multiply-adds, 0 to always run in parallel
+    pub fn with_sequential_cutoff(mut self,cutoff:usize) -> DeviceCpuConfig {
+        self.sequential_cutoff = cutoff;
+        self
+    }
+}
+impl Default for DeviceCpuConfig {
+    fn default() -> Self {
+        DeviceCpuConfig {
+            summation:Summation::Unordered,
+            pool:None,
+            sequential_cutoff:DEFAULT_SEQUENTIAL_CUTOFF,
+        }
+    }
+}
+/// Pin the current thread to the logical CPU of the specified index, modulo the number of logical CPUs
+#[cfg(target_os = "linux")]
+fn pin_current_thread(index:usize) {
+    let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+    unsafe {
+        let mut set = std::mem::zeroed::<libc::cpu_set_t>();
+
+        libc::CPU_ZERO(&mut set);
+        libc::CPU_SET(index % cpus,&mut set);
+
+        // Pinning is an optimization, so a failure leaves the thread unpinned
+        libc::sched_setaffinity(0,std::mem::size_of::<libc::cpu_set_t>(),&set);
+    }
+}
+/// Pin the current thread to the logical CPU of the specified index (not supported on this platform)
+#[cfg(not(target_os = "linux"))]
+fn pin_current_thread(_:usize) {
+}
+/// CPU device whose convolution behavior is chosen by DeviceCpuConfig
+#[derive(Debug)]
+pub struct DeviceCpuConfigured<U> where U: UnitValue<U> {
+    config:DeviceCpuConfig,
+    u:PhantomData<U>,
+}
+impl<U> DeviceCpuConfigured<U> where U: UnitValue<U> {
+    /// Create an instance of DeviceCpuConfigured
+    /// # Arguments
+    /// * `config` - Configuration of the device
+    pub fn new(config:DeviceCpuConfig) -> DeviceCpuConfigured<U> {
+        DeviceCpuConfigured {
+            config:config,
+            u:PhantomData::<U>
+        }
+    }
+
+    /// get the configuration of the device
+    pub fn config(&self) -> &DeviceCpuConfig {
+        &self.config
+    }
+
+    /// Returns true if a convolution of n samples of the specified shape is large enough to run in parallel
+    pub(crate) fn parallel(&self,shape:&ConvolutionShape,n:usize) -> bool {
+        shape.work(n) >= self.config.sequential_cutoff
+    }
+
+    /// Number of groups into which a batch of n samples is split, each computed with its own scratch buffer
+    pub(crate) fn batch_groups(&self,n:usize,parallel:bool) -> usize {
+        if !parallel {
+            return 1;
+        }
+
+        let threads = match self.config.pool {
+            Some(ref pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads()
+        };
+
+        threads.min(n).max(1)
+    }
+
+    /// Run f on the thread pool of the device
+    pub(crate) fn install<R,F>(&self,f:F) -> R where R: Send, F: FnOnce() -> R + Send {
+        match self.config.pool {
+            Some(ref pool) => pool.install(f),
+            None => f()
+        }
+    }
+}
+impl<U> Default for DeviceCpuConfigured<U> where U: UnitValue<U> {
+    fn default() -> Self {
+        DeviceCpuConfigured::new(DeviceCpuConfig::default())
+    }
+}
+impl<U> Clone for DeviceCpuConfigured<U> where U: UnitValue<U> {
+    fn clone(&self) -> Self {
+        DeviceCpuConfigured::new(self.config.clone())
+    }
+}
+/// Accumulator of a sum in the order specified by Summation
+///
+/// Each mode keeps only its own state, so the partial sums of the pairwise tree are not initialized for the other modes.
+enum Accumulator<U> where U: UnitValue<U> {
+    /// Plain running sum
+    Sequential(U),
+    /// Running sum and the compensation of the lost low-order bits
+    Kahan(U,U),
+    /// Binary counter of partial sums, where partials[i] is the sum of 2^i consecutive terms, and the number of terms
+    Pairwise([U; 64],u64),
+}
+impl<U> Accumulator<U> where U: UnitValue<U> {
+    #[inline]
+    fn new(summation:Summation) -> Accumulator<U> {
+        match summation {
+            Summation::Unordered | Summation::Sequential => Accumulator::Sequential(U::default()),
+            Summation::Kahan => Accumulator::Kahan(U::default(),U::default()),
+            Summation::Pairwise => Accumulator::Pairwise([U::default(); 64],0),
+        }
+    }
+
+    #[inline]
+    fn add(&mut self,v:U) {
+        match self {
+            Accumulator::Sequential(sum) => {
+                *sum = *sum + v;
+            },
+            Accumulator::Kahan(sum,compensation) => {
+                let y = v - *compensation;
+                let t = *sum + y;
+
+                *compensation = (t - *sum) - y;
+                *sum = t;
+            },
+            Accumulator::Pairwise(partials,count) => {
+                let mut v = v;
+                let mut level = 0;
+                let mut n = *count;
+
+                while n & 1 == 1 {
+                    v = partials[level] + v;
+                    n >>= 1;
+                    level += 1;
+                }
+
+                partials[level] = v;
+                *count += 1;
+            }
+        }
+    }
+
+    #[inline]
+    fn finish(&self) -> U {
+        match self {
+            Accumulator::Sequential(sum) | Accumulator::Kahan(sum,_) => *sum,
+            Accumulator::Pairwise(partials,count) => {
+                let mut acc = U::default();
+
+                for level in 0..64 {
+                    if (*count >> level) & 1 == 1 {
+                        acc = partials[level] + acc;
+                    }
+                }
+
+                acc
+            }
+        }
+    }
+}
+/// Shape of a convolution
+#[derive(Debug,Clone,Copy)]
+pub(crate) struct ConvolutionShape {
+    pub(crate) c:usize,
+    pub(crate) k:usize,
+    pub(crate) h:usize,
+    pub(crate) w:usize,
+    pub(crate) fh:usize,
+    pub(crate) fw:usize,
+    pub(crate) pad:usize,
+    pub(crate) s:usize,
+    pub(crate) oh:usize,
+    pub(crate) ow:usize,
+}
+impl ConvolutionShape {
+    pub(crate) fn new(c:usize,k:usize,h:usize,w:usize,fh:usize,fw:usize,pad:usize,s:usize) -> ConvolutionShape {
+        ConvolutionShape {
+            c:c,
+            k:k,
+            h:h,
+            w:w,
+            fh:fh,
+            fw:fw,
+            pad:pad,
+            s:s,
+            oh:(h + 2 * pad - fh) / s + 1,
+            ow:(w + 2 * pad - fw) / s + 1,
+        }
+    }
+
+    /// Number of multiply-adds of a convolution of n samples
+    pub(crate) fn work(&self,n:usize) -> usize {
+        n.saturating_mul(self.k * self.c * self.oh * self.ow * self.fh * self.fw)
+    }
+
+    /// Range of filter offsets whose input position is inside the image for the output position o
+    #[inline]
+    fn valid_range(o:usize,s:usize,pad:usize,f:usize,n:usize) -> (usize,usize) {
+        let start = pad.saturating_sub(o * s).min(f);
+        let end = (n + pad).saturating_sub(o * s).min(f);
+
+        (start,end.max(start))
+    }
+}
+/// Call f with the index and contents of each chunk of data, in parallel if parallel is true
+fn for_each_chunk<U,F>(data:&mut [U],chunk:usize,parallel:bool,f:F) where U: Send, F: Fn(usize,&mut [U]) + Send + Sync {
+    if parallel {
+        data.par_chunks_mut(chunk).enumerate().for_each(|(i,c)| f(i,c));
+    } else {
+        data.chunks_mut(chunk).enumerate().for_each(|(i,c)| f(i,c));
+    }
+}
+/// Add the products of one channel of the input window and the filter at the output position (oy,ox)
+#[inline]
+fn accumulate_window<U>(acc:&mut Accumulator<U>,input:&[U],kernel:&[U],shape:&ConvolutionShape,oy:usize,ox:usize)
+    where U: UnitValue<U> {
+    let (fy0,fy1) = ConvolutionShape::valid_range(oy,shape.s,shape.pad,shape.fh,shape.h);
+    let (fx0,fx1) = ConvolutionShape::valid_range(ox,shape.s,shape.pad,shape.fw,shape.w);
+
+    for fy in fy0..fy1 {
+        let y = oy * shape.s + fy - shape.pad;
+        let row = &input[y * shape.w..(y + 1) * shape.w];
+        let k = &kernel[fy * shape.fw..(fy + 1) * shape.fw];
+
+        for fx in fx0..fx1 {
+            acc.add(row[ox * shape.s + fx - shape.pad] * k[fx]);
+        }
+    }
+}
+/// Compute the feature map of one output channel from the specified input channels
+fn forward_plane<U>(input:&[U],kernel:&[U],output:&mut [U],shape:&ConvolutionShape,channels:Range<usize>,summation:Summation)
+    where U: UnitValue<U> {
+    let hw = shape.h * shape.w;
+    let ff = shape.fh * shape.fw;
+
+    for oy in 0..shape.oh {
+        for ox in 0..shape.ow {
+            let mut acc = Accumulator::new(summation);
+
+            for c in channels.clone() {
+                accumulate_window(&mut acc,&input[c * hw..(c + 1) * hw],&kernel[c * ff..(c + 1) * ff],shape,oy,ox);
+            }
+
+            output[oy * shape.ow + ox] = acc.finish();
+        }
+    }
+}
+/// Number of scratch elements needed by forward_sample
+pub(crate) fn forward_scratch_len(shape:&ConvolutionShape,summation:Summation) -> usize {
+    match summation {
+        Summation::Unordered => {
+            let plane = shape.oh * shape.ow;
+
+            (shape.k * shape.c * plane).max(shape.c * shape.fh * shape.fw * plane)
+        },
+        _ => 0
+    }
+}
+/// Number of scratch elements needed by backward_sample
+pub(crate) fn backward_scratch_len(shape:&ConvolutionShape,summation:Summation) -> usize {
+    match summation {
+        Summation::Unordered => {
+            let cff = shape.c * shape.fh * shape.fw;
+
+            cff * shape.k + cff * shape.oh * shape.ow
+        },
+        _ => 0
+    }
+}
+/// Number of scratch elements needed by weight_gradient
+pub(crate) fn weight_gradient_scratch_len(shape:&ConvolutionShape,summation:Summation) -> usize {
+    match summation {
+        Summation::Unordered => shape.c * shape.fh * shape.fw * shape.oh * shape.ow,
+        _ => 0
+    }
+}
+/// Returns true if U has vectorized kernels
+fn is_simd_float<U: 'static>() -> bool {
+    simd::cast_slice::<U,f32>(&[]).is_some() || simd::cast_slice::<U,f64>(&[]).is_some()
+}
+/// Expand the input windows into a (C * FH * FW) x (OH * OW) matrix, with 0 at the padding
+fn im2col<U>(input:&[U],col:&mut [U],shape:&ConvolutionShape,parallel:bool) where U: UnitValue<U> {
+    let hw = shape.h * shape.w;
+    let plane = shape.oh * shape.ow;
+
+    for_each_chunk(col,plane,parallel,|j,row| {
+        let c = j / (shape.fh * shape.fw);
+        let fy = j / shape.fw % shape.fh;
+        let fx = j % shape.fw;
+
+        let input = &input[c * hw..(c + 1) * hw];
+
+        for oy in 0..shape.oh {
+            let y = oy * shape.s + fy;
+
+            for ox in 0..shape.ow {
+                let x = ox * shape.s + fx;
+
+                row[oy * shape.ow + ox] = if y < shape.pad || y - shape.pad >= shape.h || x < shape.pad || x - shape.pad >= shape.w {
+                    U::default()
+                } else {
+                    input[(y - shape.pad) * shape.w + x - shape.pad]
+                };
+            }
+        }
+    });
+}
+/// Matrix product with the vectorized kernels if U is T, returns false otherwise
+fn gemm_as<U,T>(m:usize,n:usize,k:usize,a:&[U],b:&[U],c:&mut [U],parallel:bool) -> bool where U: 'static, T: SimdFloat + Default {
+    match (simd::cast_slice::<U,T>(a),simd::cast_slice::<U,T>(b),simd::cast_slice_mut::<U,T>(c)) {
+        (Some(a),Some(b),Some(c)) if parallel => {
+            simd::gemm(m,n,k,a,b,c);
+            true
+        },
+        (Some(a),Some(b),Some(c)) => {
+            simd::gemm_sequential(m,n,k,a,b,c);
+            true
+        },
+        _ => false
+    }
+}
+/// Row i of the matrix product with the vectorized kernels if U is T, returns false otherwise
+fn gemm_row_as<U,T>(i:usize,n:usize,k:usize,a:&[U],b:&[U],row:&mut [U]) -> bool where U: 'static, T: SimdFloat + Default {
+    match (simd::cast_slice::<U,T>(a),simd::cast_slice::<U,T>(b),simd::cast_slice_mut::<U,T>(row)) {
+        (Some(a),Some(b),Some(row)) => {
+            simd::gemm_row(simd::detect(),i,n,k,a,b,row);
+            true
+        },
+        _ => false
+    }
+}
+/// Dot product with the vectorized kernels if U is T, None otherwise
+fn dot_as<U,T>(a:&[U],b:&[U]) -> Option<U> where U: Copy + 'static, T: SimdFloat {
+    match (simd::cast_slice::<U,T>(a),simd::cast_slice::<U,T>(b)) {
+        (Some(a),Some(b)) => simd::cast_slice::<T,U>(&[T::dot(a,b)]).map(|r| r[0]),
+        _ => None
+    }
+}
+/// Add the columns of a (C * FH * FW) x (OH * OW) matrix back to the input positions they were taken from, the inverse of im2col
+fn col2im<U>(col:&[U],output:&mut [U],shape:&ConvolutionShape,parallel:bool) where U: UnitValue<U> {
+    let ff = shape.fh * shape.fw;
+    let plane = shape.oh * shape.ow;
+
+    for_each_chunk(output,shape.h * shape.w,parallel,|c,out| {
+        for v in out.iter_mut() {
+            *v = U::default();
+        }
+
+        for fy in 0..shape.fh {
+            for fx in 0..shape.fw {
+                let row = &col[(c * ff + fy * shape.fw + fx) * plane..(c * ff + fy * shape.fw + fx + 1) * plane];
+
+                for oy in 0..shape.oh {
+                    let y = oy * shape.s + fy;
+
+                    if y < shape.pad || y - shape.pad >= shape.h {
+                        continue;
+                    }
+
+                    for ox in 0..shape.ow {
+                        let x = ox * shape.s + fx;
+
+                        if x < shape.pad || x - shape.pad >= shape.w {
+                            continue;
+                        }
+
+                        let o = &mut out[(y - shape.pad) * shape.w + x - shape.pad];
+
+                        *o = *o + row[oy * shape.ow + ox];
+                    }
+                }
+            }
+        }
+    });
+}
+/// Summation of the accumulators of the scalar paths, where Summation::Unordered adds in sequence
+fn sample_summation(summation:Summation) -> Summation {
+    match summation {
+        Summation::Unordered => Summation::Sequential,
+        s => s
+    }
+}
+/// Forward propagation of one sample
+///
+/// With Summation::Unordered, f32 and f64 expand the input windows into the scratch buffer of forward_scratch_len elements
+/// and compute the output as a vectorized matrix product.
+/// Other types compute the contribution of each input channel in parallel into the scratch buffer before the channels are summed.
+pub(crate) fn forward_sample<U>(input:&[U],kernel:&[U],output:&mut [U],shape:&ConvolutionShape,summation:Summation,scratch:&mut [U],parallel:bool)
+    where U: UnitValue<U> + 'static {
+    let cff = shape.c * shape.fh * shape.fw;
+    let plane = shape.oh * shape.ow;
+
+    match summation {
+        Summation::Unordered if is_simd_float::<U>() => {
+            let col = &mut scratch[..cff * plane];
+
+            im2col(input,col,shape,parallel);
+
+            let col = &*col;
+
+            if !gemm_as::<U,f32>(shape.k,plane,cff,kernel,col,output,parallel) {
+                gemm_as::<U,f64>(shape.k,plane,cff,kernel,col,output,parallel);
+            }
+        },
+        Summation::Unordered => {
+            let partials = &mut scratch[..shape.k * shape.c * plane];
+
+            for_each_chunk(partials,plane,parallel,|kc,partial| {
+                let k = kc / shape.c;
+                let c = kc % shape.c;
+
+                forward_plane(input,&kernel[k * cff..(k + 1) * cff],partial,shape,c..c + 1,Summation::Sequential);
+            });
+
+            let partials = &*partials;
+
+            for_each_chunk(output,plane,parallel,|k,out| {
+                let partials = &partials[k * shape.c * plane..(k + 1) * shape.c * plane];
+
+                for (i,o) in out.iter_mut().enumerate() {
+                    *o = (0..shape.c).fold(U::default(),|acc,c| acc + partials[c * plane + i]);
+                }
+            });
+        },
+        _ => {
+            for_each_chunk(output,plane,parallel,|k,out| {
+                forward_plane(input,&kernel[k * cff..(k + 1) * cff],out,shape,0..shape.c,summation);
+            });
+        }
+    }
+}
+/// Error back propagation of one sample, summing the contributions of each input position in a fixed order
+///
+/// With Summation::Unordered, f32 and f64 compute the gradient of the expanded input windows as the vectorized matrix product
+/// of the transposed kernel and the loss in the scratch buffer of backward_scratch_len elements, and add it back to the input positions.
+pub(crate) fn backward_sample<U>(loss:&[U],kernel:&[U],output:&mut [U],shape:&ConvolutionShape,summation:Summation,scratch:&mut [U],parallel:bool)
+    where U: UnitValue<U> + 'static {
+    let hw = shape.h * shape.w;
+    let ff = shape.fh * shape.fw;
+    let cff = shape.c * ff;
+    let plane = shape.oh * shape.ow;
+
+    if summation == Summation::Unordered && is_simd_float::<U>() {
+        let (transposed,col) = scratch[..cff * shape.k + cff * plane].split_at_mut(cff * shape.k);
+
+        for_each_chunk(transposed,shape.k,parallel,|j,row| {
+            for (k,v) in row.iter_mut().enumerate() {
+                *v = kernel[k * cff + j];
+            }
+        });
+
+        let transposed = &*transposed;
+
+        if !gemm_as::<U,f32>(cff,plane,shape.k,transposed,loss,col,parallel) {
+            gemm_as::<U,f64>(cff,plane,shape.k,transposed,loss,col,parallel);
+        }
+
+        col2im(col,output,shape,parallel);
+
+        return;
+    }
+
+    for_each_chunk(output,hw,parallel,|c,out| {
+        for y in 0..shape.h {
+            for x in 0..shape.w {
+                let mut acc = Accumulator::new(sample_summation(summation));
+
+                for k in 0..shape.k {
+                    let l = &loss[k * plane..(k + 1) * plane];
+                    let f = &kernel[k * cff + c * ff..k * cff + (c + 1) * ff];
+
+                    for fy in 0..shape.fh {
+                        let py = y + shape.pad;
+
+                        if py < fy || (py - fy) % shape.s != 0 || (py - fy) / shape.s >= shape.oh {
+                            continue;
+                        }
+
+                        let oy = (py - fy) / shape.s;
+
+                        for fx in 0..shape.fw {
+                            let px = x + shape.pad;
+
+                            if px < fx || (px - fx) % shape.s != 0 || (px - fx) / shape.s >= shape.ow {
+                                continue;
+                            }
+
+                            let ox = (px - fx) / shape.s;
+
+                            acc.add(l[oy * shape.ow + ox] * f[fy * shape.fw + fx]);
+                        }
+                    }
+                }
+
+                out[y * shape.w + x] = acc.finish();
+            }
+        }
+    });
+}
+/// Gradient of the kernel summed over the samples, each weight summed in a fixed order
+///
+/// With Summation::Unordered, f32 and f64 expand the input windows of each sample into the scratch buffer of weight_gradient_scratch_len elements
+/// and add the vectorized dot products of each channel of the loss and each row of the expanded windows.
+pub(crate) fn weight_gradient<U>(loss:&[U],input:&[U],n:usize,gradient:&mut [U],shape:&ConvolutionShape,summation:Summation,scratch:&mut [U],parallel:bool)
+    where U: UnitValue<U> + 'static {
+    let hw = shape.h * shape.w;
+    let ff = shape.fh * shape.fw;
+    let chw = shape.c * hw;
+    let plane = shape.oh * shape.ow;
+    let kplane = shape.k * plane;
+
+    if summation == Summation::Unordered && is_simd_float::<U>() {
+        let cff = shape.c * ff;
+        let col = &mut scratch[..cff * plane];
+
+        for g in gradient.iter_mut() {
+            *g = U::default();
+        }
+
+        for i in 0..n {
+            im2col(&input[i * chw..(i + 1) * chw],col,shape,parallel);
+
+            let col = &*col;
+            let loss = &loss[i * kplane..(i + 1) * kplane];
+
+            for_each_chunk(gradient,cff,parallel,|k,g| {
+                let l = &loss[k * plane..(k + 1) * plane];
+
+                for (j,g) in g.iter_mut().enumerate() {
+                    let r = &col[j * plane..(j + 1) * plane];
+
+                    let d = dot_as::<U,f32>(l,r).or_else(|| dot_as::<U,f64>(l,r)).expect("U is f32 or f64.");
+
+                    *g = *g + d;
+                }
+            });
+        }
+
+        return;
+    }
+
+    let summation = sample_summation(summation);
+
+    for_each_chunk(gradient,ff,parallel,|kc,g| {
+        let k = kc / shape.c;
+        let c = kc % shape.c;
+
+        for fy in 0..shape.fh {
+            for fx in 0..shape.fw {
+                let mut acc = Accumulator::new(summation);
+
+                for i in 0..n {
+                    let l = &loss[i * kplane + k * plane..i * kplane + (k + 1) * plane];
+                    let x = &input[i * chw + c * hw..i * chw + (c + 1) * hw];
+
+                    for oy in 0..shape.oh {
+                        let y = oy * shape.s + fy;
+
+                        if y < shape.pad || y - shape.pad >= shape.h {
+                            continue;
+                        }
+
+                        for ox in 0..shape.ow {
+                            let xx = ox * shape.s + fx;
+
+                            if xx < shape.pad || xx - shape.pad >= shape.w {
+                                continue;
+                            }
+
+                            acc.add(l[oy * shape.ow + ox] * x[(y - shape.pad) * shape.w + xx - shape.pad]);
+                        }
+                    }
+                }
+
+                g[fy * shape.fw + fx] = acc.finish();
+            }
+        }
+    });
+}
+#[guard({ H + 2 * PAD >= FH && (H + 2 * PAD - FH) % S == 0 && W + 2 * PAD >= FW && (W + 2 * PAD - FW) % S == 0 })]
+impl<U,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH: usize,const FW: usize,const PAD:usize,const S:usize> DeviceConvolution<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S> for DeviceCpuConfigured<U>
+    where U: UnitValue<U> + 'static {
+    fn forward_convolution<const CI: usize>(&self, input: &Images<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>)
+        -> Result<Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>, EvaluateError> {
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let mut output = Images::new();
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let scratch = workspace.buffer(forward_scratch_len(&shape,self.config.summation));
+
+        let parallel = self.parallel(&shape,1);
+
+        self.install(|| forward_sample(input.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,self.config.summation,scratch,parallel));
+
+        Ok(output)
+    }
+
+    fn backward_convolution(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>, kernel: &Arr4<U,K,C,FH,FW>)
+        -> Result<Images<U, C, H, W>, TrainingError> {
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let mut output = Images::new();
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let scratch = workspace.buffer(backward_scratch_len(&shape,self.config.summation));
+
+        let parallel = self.parallel(&shape,1);
+
+        self.install(|| backward_sample(loss.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,self.config.summation,scratch,parallel));
+
+        Ok(output)
+    }
+
+    fn backward_weight_gradient_convolution(&self,loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                            input: &Images<U, C, H, W>)
+        -> Result<Arr4<U, K, C, FH, FW>, TrainingError> {
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let mut gradient = Arr4::new();
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let scratch = workspace.buffer(weight_gradient_scratch_len(&shape,self.config.summation));
+
+        let parallel = self.parallel(&shape,1);
+
+        self.install(|| weight_gradient(loss.as_raw_slice(),input.as_raw_slice(),1,gradient.as_raw_mut_slice(),&shape,self.config.summation,scratch,parallel));
+
+        Ok(gradient)
+    }
+
+    fn batch_forward_convolution<const CI: usize>(&self, input: &VecImages<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>)
+        -> Result<VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>, EvaluateError> {
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let mut output = VecImages::with_size(input.len());
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let parallel = self.parallel(&shape,input.len());
+        let groups = self.batch_groups(input.len(),parallel);
+
+        let scratch = workspace.buffer(batch_scratch_len(forward_scratch_len(&shape,self.config.summation),groups));
+
+        self.install(|| batch_forward(input.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,self.config.summation,scratch,groups,parallel));
+
+        Ok(output)
+    }
+
+    fn batch_backward_convolution(&self,
+                                  loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                  kernel: &Arr4<U,K,C,FH,FW>)
+        -> Result<VecImages<U, C, H, W>, TrainingError> {
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let mut output = VecImages::with_size(loss.len());
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let parallel = self.parallel(&shape,loss.len());
+        let groups = self.batch_groups(loss.len(),parallel);
+
+        let scratch = workspace.buffer(batch_scratch_len(backward_scratch_len(&shape,self.config.summation),groups));
+
+        self.install(|| batch_backward(loss.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,self.config.summation,scratch,groups,parallel));
+
+        Ok(output)
+    }
+
+    fn batch_backward_weight_gradient_convolution(&self,
+                                                  loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                                  input: &VecImages<U, C, H, W>)
+        -> Result<Arr4<U, K, C, FH, FW>, TrainingError> {
+        if loss.len() != input.len() {
+            return Err(TrainingError::from(SizeMismatchError(loss.len(),input.len())));
+        }
+
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let mut gradient = Arr4::new();
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let scratch = workspace.buffer(weight_gradient_scratch_len(&shape,self.config.summation));
+
+        let parallel = self.parallel(&shape,input.len());
+
+        self.install(|| weight_gradient(loss.as_raw_slice(),input.as_raw_slice(),input.len(),gradient.as_raw_mut_slice(),&shape,self.config.summation,scratch,parallel));
+
+        Ok(gradient)
+    }
+}
+/// Number of scratch elements needed by a batch split into the specified number of groups, where each sample needs len elements
+pub(crate) fn batch_scratch_len(len:usize,groups:usize) -> usize {
+    len.max(1) * groups
+}
+/// Call f with the index of the first sample, the outputs of a group of consecutive samples and the scratch buffer of the group
+///
+/// The samples are split into at most groups groups of sample elements each, computed in parallel if parallel is true,
+/// and each group gets its own len elements of scratch, which holds batch_scratch_len(len,groups) elements.
+fn for_each_group<U,F>(output:&mut [U],sample:usize,groups:usize,scratch:&mut [U],len:usize,parallel:bool,f:F)
+    where U: Send, F: Fn(usize,&mut [U],&mut [U]) + Send + Sync {
+    if sample == 0 || output.is_empty() {
+        return;
+    }
+
+    let n = output.len() / sample;
+    let per = ((n + groups - 1) / groups).max(1);
+    let len = len.max(1);
+
+    if parallel {
+        output.par_chunks_mut(per * sample).zip(scratch.par_chunks_mut(len)).enumerate().for_each(|(g,(out,scratch))| f(g * per,out,scratch));
+    } else {
+        output.chunks_mut(per * sample).zip(scratch.chunks_mut(len)).enumerate().for_each(|(g,(out,scratch))| f(g * per,out,scratch));
+    }
+}
+/// Call f with the index of the first sample, the pairs of outputs of a group of consecutive samples and the scratch buffer of the group,
+/// splitting the samples as for_each_group does
+fn for_each_group_pair<U,F>(a:&mut [U],b:&mut [U],sample:usize,groups:usize,scratch:&mut [U],len:usize,parallel:bool,f:F)
+    where U: Send, F: Fn(usize,&mut [U],&mut [U],&mut [U]) + Send + Sync {
+    if sample == 0 || a.is_empty() {
+        return;
+    }
+
+    let n = a.len() / sample;
+    let per = ((n + groups - 1) / groups).max(1);
+    let len = len.max(1);
+
+    if parallel {
+        a.par_chunks_mut(per * sample).zip(b.par_chunks_mut(per * sample)).zip(scratch.par_chunks_mut(len)).enumerate()
+            .for_each(|(g,((a,b),scratch))| f(g * per,a,b,scratch));
+    } else {
+        a.chunks_mut(per * sample).zip(b.chunks_mut(per * sample)).zip(scratch.chunks_mut(len)).enumerate()
+            .for_each(|(g,((a,b),scratch))| f(g * per,a,b,scratch));
+    }
+}
+/// Forward propagation of each sample of a batch, the groups of samples in parallel
+///
+/// The scratch buffer holds batch_scratch_len(forward_scratch_len(shape,summation),groups) elements,
+/// so that Summation::Unordered reaches the vectorized matrix product for every sample.
+pub(crate) fn batch_forward<U>(input:&[U],kernel:&[U],output:&mut [U],shape:&ConvolutionShape,summation:Summation,
+                               scratch:&mut [U],groups:usize,parallel:bool)
+    where U: UnitValue<U> + 'static {
+    let chw = shape.c * shape.h * shape.w;
+    let kplane = shape.k * shape.oh * shape.ow;
+
+    for_each_group(output,kplane,groups,scratch,forward_scratch_len(shape,summation),parallel,|first,out,scratch| {
+        for (j,out) in out.chunks_mut(kplane).enumerate() {
+            let i = first + j;
+
+            forward_sample(&input[i * chw..(i + 1) * chw],kernel,out,shape,summation,scratch,parallel);
+        }
+    });
+}
+/// Error back propagation of each sample of a batch, the groups of samples in parallel
+///
+/// The scratch buffer holds batch_scratch_len(backward_scratch_len(shape,summation),groups) elements.
+fn batch_backward<U>(loss:&[U],kernel:&[U],output:&mut [U],shape:&ConvolutionShape,summation:Summation,
+                     scratch:&mut [U],groups:usize,parallel:bool)
+    where U: UnitValue<U> + 'static {
+    let chw = shape.c * shape.h * shape.w;
+    let kplane = shape.k * shape.oh * shape.ow;
+
+    for_each_group(output,chw,groups,scratch,backward_scratch_len(shape,summation),parallel,|first,out,scratch| {
+        for (j,out) in out.chunks_mut(chw).enumerate() {
+            let i = first + j;
+
+            backward_sample(&loss[i * kplane..(i + 1) * kplane],kernel,out,shape,summation,scratch,parallel);
+        }
+    });
+}
+#[guard({ H + 2 * PAD >= FH && (H + 2 * PAD - FH) % S == 0 && W + 2 * PAD >= FW && (W + 2 * PAD - FW) % S == 0 })]
+impl<U,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH: usize,const FW: usize,const PAD:usize,const S:usize> DeviceConvolutionInto<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S> for DeviceCpuConfigured<U>
+    where U: UnitValue<U> + 'static {
+    fn forward_convolution_into(&self, input: &Images<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>,
+                                output: &mut ImagesViewMut<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), EvaluateError> {
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let scratch = workspace.buffer(forward_scratch_len(&shape,self.config.summation));
+
+        let parallel = self.parallel(&shape,1);
+
+        self.install(|| forward_sample(input.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,self.config.summation,scratch,parallel));
+
+        Ok(())
+    }
+
+    fn backward_convolution_into(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                 kernel: &Arr4<U,K,C,FH,FW>, output: &mut ImagesViewMut<U, C, H, W>, workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let scratch = workspace.buffer(backward_scratch_len(&shape,self.config.summation));
+
+        let parallel = self.parallel(&shape,1);
+
+        self.install(|| backward_sample(loss.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,self.config.summation,scratch,parallel));
+
+        Ok(())
+    }
+
+    fn backward_weight_gradient_convolution_into(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                                 input: &Images<U, C, H, W>, output: &mut Arr4<U, K, C, FH, FW>, workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let scratch = workspace.buffer(weight_gradient_scratch_len(&shape,self.config.summation));
+
+        let parallel = self.parallel(&shape,1);
+
+        self.install(|| weight_gradient(loss.as_raw_slice(),input.as_raw_slice(),1,output.as_raw_mut_slice(),&shape,self.config.summation,scratch,parallel));
+
+        Ok(())
+    }
+
+    fn batch_forward_convolution_into(&self, input: &VecImages<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>,
+                                      output: &mut VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                      workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), EvaluateError> {
+        if input.len() != output.len() {
+            return Err(EvaluateError::from(SizeMismatchError(input.len(),output.len())));
+        }
+
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let parallel = self.parallel(&shape,input.len());
+        let groups = self.batch_groups(input.len(),parallel);
+
+        let scratch = workspace.buffer(batch_scratch_len(forward_scratch_len(&shape,self.config.summation),groups));
+
+        self.install(|| batch_forward(input.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,self.config.summation,scratch,groups,parallel));
+
+        Ok(())
+    }
+
+    fn batch_backward_convolution_into(&self, loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                       kernel: &Arr4<U,K,C,FH,FW>, output: &mut VecImages<U, C, H, W>, workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        if loss.len() != output.len() {
+            return Err(TrainingError::from(SizeMismatchError(loss.len(),output.len())));
+        }
+
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let parallel = self.parallel(&shape,loss.len());
+        let groups = self.batch_groups(loss.len(),parallel);
+
+        let scratch = workspace.buffer(batch_scratch_len(backward_scratch_len(&shape,self.config.summation),groups));
+
+        self.install(|| batch_backward(loss.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,self.config.summation,scratch,groups,parallel));
+
+        Ok(())
+    }
+
+    fn batch_backward_weight_gradient_convolution_into(&self, loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                                       input: &VecImages<U, C, H, W>, output: &mut Arr4<U, K, C, FH, FW>,
+                                                       workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        if loss.len() != input.len() {
+            return Err(TrainingError::from(SizeMismatchError(loss.len(),input.len())));
+        }
+
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let scratch = workspace.buffer(weight_gradient_scratch_len(&shape,self.config.summation));
+
+        let parallel = self.parallel(&shape,input.len());
+
+        self.install(|| weight_gradient(loss.as_raw_slice(),input.as_raw_slice(),input.len(),output.as_raw_mut_slice(),&shape,self.config.summation,scratch,parallel));
+
+        Ok(())
+    }
+}
+/// Call f with the index and contents of each pair of chunks of a and b, in parallel if parallel is true
+fn for_each_chunk_pair<U,F>(a:&mut [U],b:&mut [U],chunk:usize,parallel:bool,f:F) where U: Send, F: Fn(usize,&mut [U],&mut [U]) + Send + Sync {
+    if parallel {
+        a.par_chunks_mut(chunk).zip(b.par_chunks_mut(chunk)).enumerate().for_each(|(i,(a,b))| f(i,a,b));
+    } else {
+        a.chunks_mut(chunk).zip(b.chunks_mut(chunk)).enumerate().for_each(|(i,(a,b))| f(i,a,b));
+    }
+}
+/// Add the bias to the values before activation of one output channel and write the values after activation
+#[inline]
+fn fused_plane<U,A>(u:&mut [U],output:&mut [U],bias:U,activation:&A) where U: UnitValue<U>, A: FusedActivation<U> {
+    for (u,o) in u.iter_mut().zip(output.iter_mut()) {
+        *u = *u + bias;
+        *o = activation.apply_element(*u);
+    }
+}
+/// Forward propagation of one sample fused with the bias and the activation function
+///
+/// The bias and the activation function are applied to each output channel right after it is computed, while it is still in cache,
+/// writing the value before activation to u and the value after activation to output.
+/// The scratch buffer is used as in forward_sample.
+fn forward_fused_sample<U,A>(input:&[U],kernel:&[U],bias:&[U],activation:&A,u:&mut [U],output:&mut [U],
+                             shape:&ConvolutionShape,summation:Summation,scratch:&mut [U],parallel:bool)
+    where U: UnitValue<U> + 'static, A: FusedActivation<U> + Sync {
+    let cff = shape.c * shape.fh * shape.fw;
+    let plane = shape.oh * shape.ow;
+
+    match summation {
+        Summation::Unordered if is_simd_float::<U>() => {
+            let col = &mut scratch[..cff * plane];
+
+            im2col(input,col,shape,parallel);
+
+            let col = &*col;
+
+            for_each_chunk_pair(u,output,plane,parallel,|k,u,out| {
+                if !gemm_row_as::<U,f32>(k,plane,cff,kernel,col,u) {
+                    gemm_row_as::<U,f64>(k,plane,cff,kernel,col,u);
+                }
+
+                fused_plane(u,out,bias[k],activation);
+            });
+        },
+        Summation::Unordered => {
+            let partials = &mut scratch[..shape.k * shape.c * plane];
+
+            for_each_chunk(partials,plane,parallel,|kc,partial| {
+                let k = kc / shape.c;
+                let c = kc % shape.c;
+
+                forward_plane(input,&kernel[k * cff..(k + 1) * cff],partial,shape,c..c + 1,Summation::Sequential);
+            });
+
+            let partials = &*partials;
+
+            for_each_chunk_pair(u,output,plane,parallel,|k,u,out| {
+                let partials = &partials[k * shape.c * plane..(k + 1) * shape.c * plane];
+
+                for (i,v) in u.iter_mut().enumerate() {
+                    *v = (0..shape.c).fold(U::default(),|acc,c| acc + partials[c * plane + i]);
+                }
+
+                fused_plane(u,out,bias[k],activation);
+            });
+        },
+        _ => {
+            for_each_chunk_pair(u,output,plane,parallel,|k,u,out| {
+                forward_plane(input,&kernel[k * cff..(k + 1) * cff],u,shape,0..shape.c,summation);
+
+                fused_plane(u,out,bias[k],activation);
+            });
+        }
+    }
+}
+/// Fused forward propagation of each sample of a batch, the groups of samples in parallel
+///
+/// The scratch buffer holds batch_scratch_len(forward_scratch_len(shape,summation),groups) elements.
+fn batch_forward_fused<U,A>(input:&[U],kernel:&[U],bias:&[U],activation:&A,u:&mut [U],output:&mut [U],
+                            shape:&ConvolutionShape,summation:Summation,scratch:&mut [U],groups:usize,parallel:bool)
+    where U: UnitValue<U> + 'static, A: FusedActivation<U> + Sync {
+    let chw = shape.c * shape.h * shape.w;
+    let kplane = shape.k * shape.oh * shape.ow;
+
+    for_each_group_pair(u,output,kplane,groups,scratch,forward_scratch_len(shape,summation),parallel,|first,u,out,scratch| {
+        for (j,(u,out)) in u.chunks_mut(kplane).zip(out.chunks_mut(kplane)).enumerate() {
+            let i = first + j;
+
+            forward_fused_sample(&input[i * chw..(i + 1) * chw],kernel,bias,activation,u,out,shape,summation,scratch,parallel);
+        }
+    });
+}
+/// Multiply the loss by the derivative of the activation function at the value before activation
+fn fused_delta<U,A>(loss:&[U],u:&[U],delta:&mut [U],plane:usize,activation:&A,parallel:bool)
+    where U: UnitValue<U>, A: FusedActivation<U> + Sync {
+    for_each_chunk(delta,plane,parallel,|i,delta| {
+        let loss = &loss[i * plane..(i + 1) * plane];
+        let u = &u[i * plane..(i + 1) * plane];
+
+        for ((d,&l),&u) in delta.iter_mut().zip(loss.iter()).zip(u.iter()) {
+            *d = l * activation.derive_element(u);
+        }
+    });
+}
+/// Gradient of the bias summed over the samples, each channel summed in a fixed order
+fn bias_gradient<U>(delta:&[U],n:usize,gradient:&mut [U],shape:&ConvolutionShape,summation:Summation,parallel:bool)
+    where U: UnitValue<U> {
+    let plane = shape.oh * shape.ow;
+
+    let summation = sample_summation(summation);
+
+    for_each_chunk(gradient,1,parallel,|k,g| {
+        let mut acc = Accumulator::new(summation);
+
+        for i in 0..n {
+            for &d in &delta[(i * shape.k + k) * plane..(i * shape.k + k + 1) * plane] {
+                acc.add(d);
+            }
+        }
+
+        g[0] = acc.finish();
+    });
+}
+#[guard({ H + 2 * PAD >= FH && (H + 2 * PAD - FH) % S == 0 && W + 2 * PAD >= FW && (W + 2 * PAD - FW) % S == 0 })]
+impl<U,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH: usize,const FW: usize,const PAD:usize,const S:usize> DeviceFusedConvolution<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S> for DeviceCpuConfigured<U>
+    where U: UnitValue<U> + 'static {
+    fn forward_fused_convolution<A: FusedActivation<U> + Sync>(&self, input: &Images<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>, bias: &Arr<U,K>, activation: &A)
+        -> Result<(Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                   Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>), EvaluateError> {
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let mut u = Images::new();
+        let mut output = Images::new();
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let scratch = workspace.buffer(forward_scratch_len(&shape,self.config.summation));
+
+        let parallel = self.parallel(&shape,1);
+
+        self.install(|| forward_fused_sample(input.as_raw_slice(),kernel.as_raw_slice(),bias.deref(),activation,
+                                             u.as_raw_mut_slice(),output.as_raw_mut_slice(),&shape,self.config.summation,scratch,parallel));
+
+        Ok((output,u))
+    }
+
+    fn backward_fused_convolution<A: FusedActivation<U> + Sync>(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                  u: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                  input: &Images<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>, activation: &A)
+        -> Result<(Images<U, C, H, W>, Arr4<U, K, C, FH, FW>, Arr<U, K>), TrainingError> {
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+        let plane = shape.oh * shape.ow;
+
+        let mut delta = vec![U::default(); K * plane];
+        let mut output = Images::new();
+        let mut kernel_gradient = Arr4::new();
+        let mut bias_gradient_values = Arr::new();
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let parallel = self.parallel(&shape,1);
+        let summation = self.config.summation;
+
+        let scratch = workspace.buffer(backward_scratch_len(&shape,summation).max(weight_gradient_scratch_len(&shape,summation)));
+
+        self.install(|| {
+            fused_delta(loss.as_raw_slice(),u.as_raw_slice(),&mut delta,plane,activation,parallel);
+            backward_sample(&delta,kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,summation,scratch,parallel);
+            weight_gradient(&delta,input.as_raw_slice(),1,kernel_gradient.as_raw_mut_slice(),&shape,summation,scratch,parallel);
+            bias_gradient(&delta,1,bias_gradient_values.deref_mut(),&shape,summation,parallel);
+        });
+
+        Ok((output,kernel_gradient,bias_gradient_values))
+    }
+
+    fn batch_forward_fused_convolution<A: FusedActivation<U> + Sync>(&self, input: &VecImages<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>, bias: &Arr<U,K>, activation: &A)
+        -> Result<(VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                   VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>), EvaluateError> {
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+
+        let mut u = VecImages::with_size(input.len());
+        let mut output = VecImages::with_size(input.len());
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let parallel = self.parallel(&shape,input.len());
+        let groups = self.batch_groups(input.len(),parallel);
+
+        let scratch = workspace.buffer(batch_scratch_len(forward_scratch_len(&shape,self.config.summation),groups));
+
+        self.install(|| batch_forward_fused(input.as_raw_slice(),kernel.as_raw_slice(),bias.deref(),activation,
+                                            u.as_raw_mut_slice(),output.as_raw_mut_slice(),&shape,self.config.summation,scratch,groups,parallel));
+
+        Ok((output,u))
+    }
+
+    fn batch_backward_fused_convolution<A: FusedActivation<U> + Sync>(&self, loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                        u: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                        input: &VecImages<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>, activation: &A)
+        -> Result<(VecImages<U, C, H, W>, Arr4<U, K, C, FH, FW>, Arr<U, K>), TrainingError> {
+        if loss.len() != u.len() {
+            return Err(TrainingError::from(SizeMismatchError(loss.len(),u.len())));
+        }
+
+        if loss.len() != input.len() {
+            return Err(TrainingError::from(SizeMismatchError(loss.len(),input.len())));
+        }
+
+        let shape = ConvolutionShape::new(C,K,H,W,FH,FW,PAD,S);
+        let plane = shape.oh * shape.ow;
+
+        let mut delta = vec![U::default(); loss.len() * K * plane];
+        let mut output = VecImages::with_size(loss.len());
+        let mut kernel_gradient = Arr4::new();
+        let mut bias_gradient_values = Arr::new();
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let parallel = self.parallel(&shape,loss.len());
+        let groups = self.batch_groups(loss.len(),parallel);
+        let summation = self.config.summation;
+
+        let scratch = workspace.buffer(batch_scratch_len(backward_scratch_len(&shape,summation),groups).max(weight_gradient_scratch_len(&shape,summation)));
+
+        self.install(|| {
+            fused_delta(loss.as_raw_slice(),u.as_raw_slice(),&mut delta,plane,activation,parallel);
+            batch_backward(&delta,kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,summation,scratch,groups,parallel);
+            weight_gradient(&delta,input.as_raw_slice(),input.len(),kernel_gradient.as_raw_mut_slice(),&shape,summation,scratch,parallel);
+            bias_gradient(&delta,input.len(),bias_gradient_values.deref_mut(),&shape,summation,parallel);
+        });
+
+        Ok((output,kernel_gradient,bias_gradient_values))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use nncombinator::activation::{ReLu, Sigmoid, Swish, Tanh};
+    use nncombinator::arr::{Arr, Arr4};
+    use nncombinator::device::DeviceCpu;
+    use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+    use nncombinator::ope::UnitValue;
+    use num_traits::{FromPrimitive, ToPrimitive};
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use crate::collection::{Images, VecImages};
+    use crate::device::{ConvolutionWorkspace, DeviceConvolution, DeviceConvolutionInto, DeviceFusedConvolution, FusedActivation, LeakyReLu};
+    use crate::device::cpu::{DeviceCpuConfig, DeviceCpuConfigured, Summation};
+    use crate::device::reference::{compare_with_reference, ConvolutionDifference, DeviceReference};
+
+    fn random(values:&mut [f32],rnd:&mut XorShiftRng) {
+        for v in values.iter_mut() {
+            *v = rnd.gen_range(-1.0..1.0);
+        }
+    }
+
+    fn run(summation:Summation,threads:usize) -> (Vec<u32>,Vec<u32>,Vec<u32>) {
+        let mut rnd = XorShiftRng::seed_from_u64(7);
+
+        let mut input = VecImages::<f32,3,9,9>::with_size(4);
+        let mut kernel = Arr4::<f32,4,3,3,3>::new();
+        let mut loss = VecImages::<f32,4,5,5>::with_size(4);
+
+        random(input.as_raw_mut_slice(),&mut rnd);
+        random(kernel.as_raw_mut_slice(),&mut rnd);
+        random(loss.as_raw_mut_slice(),&mut rnd);
+
+        let config = DeviceCpuConfig::deterministic()
+            .with_summation(summation)
+            .with_sequential_cutoff(0)
+            .with_threads(threads,false).unwrap();
+
+        let device = DeviceCpuConfigured::<f32>::new(config);
+
+        let output = DeviceConvolution::<f32,Arr4<f32,4,3,3,3>,3,4,9,9,3,3,1,2>::batch_forward_convolution::<3>(&device,&input,&kernel).unwrap();
+        let backward = DeviceConvolution::<f32,Arr4<f32,4,3,3,3>,3,4,9,9,3,3,1,2>::batch_backward_convolution(&device,&loss,&kernel).unwrap();
+        let gradient = DeviceConvolution::<f32,Arr4<f32,4,3,3,3>,3,4,9,9,3,3,1,2>::batch_backward_weight_gradient_convolution(&device,&loss,&input).unwrap();
+
+        let bits = |values:&[f32]| values.iter().map(|v| v.to_bits()).collect::<Vec<u32>>();
+
+        (bits(output.as_raw_slice()),bits(backward.as_raw_slice()),bits(gradient.as_raw_slice()))
+    }
+
+    #[test]
+    fn test_deterministic_summation_across_thread_counts() {
+        for summation in [Summation::Sequential,Summation::Pairwise,Summation::Kahan] {
+            let expected = run(summation,1);
+
+            for threads in [2,3,8] {
+                assert!(run(summation,threads) == expected,"{:?} differs with {} threads",summation,threads);
+            }
+        }
+    }
+
+    #[test]
+    fn test_summation_modes_agree() {
+        let to_f32 = |bits:&[u32]| bits.iter().map(|&b| f32::from_bits(b)).collect::<Vec<f32>>();
+
+        let (so,sb,sg) = run(Summation::Sequential,1);
+
+        for summation in [Summation::Pairwise,Summation::Kahan] {
+            let (o,b,g) = run(summation,1);
+
+            for (e,a) in [(&so,&o),(&sb,&b),(&sg,&g)] {
+                for (e,a) in to_f32(e).iter().zip(to_f32(a).iter()) {
+                    assert!((e - a).abs() <= 1e-4,"{:?}: {} != {}",summation,e,a);
+                }
+            }
+        }
+    }
+
+    fn assert_within(difference:ConvolutionDifference,tolerance:f64) {
+        assert!(difference.forward.max_absolute_difference <= tolerance,"forward: {:?}",difference.forward);
+        assert!(difference.backward.max_absolute_difference <= tolerance,"backward: {:?}",difference.backward);
+        assert!(difference.weight_gradient.max_absolute_difference <= tolerance,"weight gradient: {:?}",difference.weight_gradient);
+    }
+
+    #[test]
+    fn test_unordered_matches_reference_f32() {
+        for cutoff in [0,usize::MAX] {
+            let device = DeviceCpuConfigured::<f32>::new(DeviceCpuConfig::default().with_sequential_cutoff(cutoff));
+
+            assert_within(compare_with_reference::<f32,_,1,1,4,4,3,3,0,1>(&device,1).unwrap(),1e-4);
+            assert_within(compare_with_reference::<f32,_,2,3,5,5,3,3,1,1>(&device,2).unwrap(),1e-4);
+            assert_within(compare_with_reference::<f32,_,3,2,7,7,3,3,1,2>(&device,3).unwrap(),1e-4);
+            assert_within(compare_with_reference::<f32,_,2,2,8,6,2,4,0,2>(&device,4).unwrap(),1e-4);
+            assert_within(compare_with_reference::<f32,_,2,4,6,6,5,5,2,1>(&device,5).unwrap(),1e-4);
+            assert_within(compare_with_reference::<f32,_,8,8,16,16,3,3,1,1>(&device,6).unwrap(),1e-3);
+        }
+    }
+
+    #[test]
+    fn test_unordered_matches_reference_f64() {
+        for cutoff in [0,usize::MAX] {
+            let device = DeviceCpuConfigured::<f64>::new(DeviceCpuConfig::default().with_sequential_cutoff(cutoff));
+
+            assert_within(compare_with_reference::<f64,_,1,1,4,4,3,3,0,1>(&device,1).unwrap(),1e-10);
+            assert_within(compare_with_reference::<f64,_,2,3,5,5,3,3,1,1>(&device,2).unwrap(),1e-10);
+            assert_within(compare_with_reference::<f64,_,3,2,7,7,3,3,1,2>(&device,3).unwrap(),1e-10);
+            assert_within(compare_with_reference::<f64,_,2,2,8,6,2,4,0,2>(&device,4).unwrap(),1e-10);
+            assert_within(compare_with_reference::<f64,_,2,4,6,6,5,5,2,1>(&device,5).unwrap(),1e-10);
+            assert_within(compare_with_reference::<f64,_,8,8,16,16,3,3,1,1>(&device,6).unwrap(),1e-10);
+        }
+    }
+
+    type BatchKernel<U> = Arr4<U,4,3,3,3>;
+
+    /// Compare the batch convolutions computed with Summation::Unordered, split into groups with their own scratch buffers,
+    /// with the scalar computation of DeviceReference
+    fn assert_batch_unordered_matches_reference<U>(threads:usize,cutoff:usize,tolerance:f64)
+        where U: UnitValue<U> + FromPrimitive + ToPrimitive + 'static,
+              DeviceCpuConfigured<U>: DeviceConvolution<U,BatchKernel<U>,3,4,9,9,3,3,1,2> + DeviceConvolutionInto<U,BatchKernel<U>,3,4,9,9,3,3,1,2>,
+              DeviceReference<U>: DeviceConvolution<U,BatchKernel<U>,3,4,9,9,3,3,1,2> {
+        let mut rnd = XorShiftRng::seed_from_u64(17);
+
+        let mut random = |values:&mut [U]| {
+            for v in values.iter_mut() {
+                *v = <U as FromPrimitive>::from_f64(rnd.gen_range(-1.0..1.0)).unwrap();
+            }
+        };
+
+        let mut input = VecImages::<U,3,9,9>::with_size(5);
+        let mut kernel = BatchKernel::<U>::new();
+        let mut loss = VecImages::<U,4,5,5>::with_size(5);
+
+        random(input.as_raw_mut_slice());
+        random(kernel.as_raw_mut_slice());
+        random(loss.as_raw_mut_slice());
+
+        let config = DeviceCpuConfig::default()
+            .with_summation(Summation::Unordered)
+            .with_sequential_cutoff(cutoff)
+            .with_threads(threads,false).unwrap();
+
+        let device = DeviceCpuConfigured::<U>::new(config);
+        let reference = DeviceReference::<U>::new();
+
+        let assert_near = |expected:&[U],actual:&[U],what:&str| {
+            assert_eq!(expected.len(),actual.len());
+
+            for (i,(e,a)) in expected.iter().zip(actual.iter()).enumerate() {
+                let d = (<U as ToPrimitive>::to_f64(e).unwrap() - <U as ToPrimitive>::to_f64(a).unwrap()).abs();
+
+                assert!(d <= tolerance,"{} with {} threads and cutoff {} at {}: difference {}",what,threads,cutoff,i,d);
+            }
+        };
+
+        let expected = DeviceConvolution::<U,BatchKernel<U>,3,4,9,9,3,3,1,2>::batch_forward_convolution::<3>(&reference,&input,&kernel).unwrap();
+        let actual = DeviceConvolution::<U,BatchKernel<U>,3,4,9,9,3,3,1,2>::batch_forward_convolution::<3>(&device,&input,&kernel).unwrap();
+
+        assert_near(expected.as_raw_slice(),actual.as_raw_slice(),"forward");
+
+        let mut workspace = ConvolutionWorkspace::new();
+        let mut output = VecImages::<U,4,5,5>::with_size(5);
+
+        device.batch_forward_convolution_into(&input,&kernel,&mut output,&mut workspace).unwrap();
+
+        assert_near(expected.as_raw_slice(),output.as_raw_slice(),"forward into");
+
+        let expected = DeviceConvolution::<U,BatchKernel<U>,3,4,9,9,3,3,1,2>::batch_backward_convolution(&reference,&loss,&kernel).unwrap();
+        let actual = DeviceConvolution::<U,BatchKernel<U>,3,4,9,9,3,3,1,2>::batch_backward_convolution(&device,&loss,&kernel).unwrap();
+
+        assert_near(expected.as_raw_slice(),actual.as_raw_slice(),"backward");
+
+        let mut output = VecImages::<U,3,9,9>::with_size(5);
+
+        device.batch_backward_convolution_into(&loss,&kernel,&mut output,&mut workspace).unwrap();
+
+        assert_near(expected.as_raw_slice(),output.as_raw_slice(),"backward into");
+    }
+
+    #[test]
+    fn test_batch_unordered_matches_reference() {
+        for threads in [1,2,3,8] {
+            for cutoff in [0,usize::MAX] {
+                assert_batch_unordered_matches_reference::<f32>(threads,cutoff,1e-4);
+                assert_batch_unordered_matches_reference::<f64>(threads,cutoff,1e-10);
+            }
+        }
+    }
+
+    type FusedKernel = Arr4<f32,4,3,3,3>;
+
+    fn assert_close(expected:&[f32],actual:&[f32],what:&str) {
+        assert_eq!(expected.len(),actual.len());
+
+        for (i,(e,a)) in expected.iter().zip(actual.iter()).enumerate() {
+            assert!((e - a).abs() <= 1e-4 * e.abs().max(1.),"{} at {}: {} != {}",what,i,e,a);
+        }
+    }
+
+    /// Compare the fused convolution with the convolution, bias and activation computed separately on DeviceReference
+    fn assert_fused_matches_separate<D,A>(device:&D,activation:&A)
+        where D: DeviceFusedConvolution<f32,FusedKernel,3,4,7,7,3,3,1,2>, A: FusedActivation<f32> + Sync {
+        let mut rnd = XorShiftRng::seed_from_u64(11);
+
+        let mut input = VecImages::<f32,3,7,7>::with_size(3);
+        let mut kernel = FusedKernel::new();
+        let mut bias = Arr::<f32,4>::new();
+        let mut loss = VecImages::<f32,4,4,4>::with_size(3);
+
+        random(input.as_raw_mut_slice(),&mut rnd);
+        random(kernel.as_raw_mut_slice(),&mut rnd);
+        random(&mut bias,&mut rnd);
+        random(loss.as_raw_mut_slice(),&mut rnd);
+
+        let reference = DeviceReference::<f32>::new();
+
+        let conv = DeviceConvolution::<f32,FusedKernel,3,4,7,7,3,3,1,2>::batch_forward_convolution::<3>(&reference,&input,&kernel).unwrap();
+
+        let expected_u = conv.as_raw_slice().chunks(16).enumerate().flat_map(|(i,p)| {
+            let b = bias[i % 4];
+
+            p.iter().map(move |&v| v + b)
+        }).collect::<Vec<f32>>();
+        let expected_output = expected_u.iter().map(|&u| activation.apply_element(u)).collect::<Vec<f32>>();
+
+        let mut delta = VecImages::<f32,4,4,4>::with_size(3);
+
+        for ((d,&l),&u) in delta.as_raw_mut_slice().iter_mut().zip(loss.as_raw_slice().iter()).zip(expected_u.iter()) {
+            *d = l * activation.derive_element(u);
+        }
+
+        let expected_input = DeviceConvolution::<f32,FusedKernel,3,4,7,7,3,3,1,2>::batch_backward_convolution(&reference,&delta,&kernel).unwrap();
+        let expected_kernel = DeviceConvolution::<f32,FusedKernel,3,4,7,7,3,3,1,2>::batch_backward_weight_gradient_convolution(&reference,&delta,&input).unwrap();
+
+        let mut expected_bias = vec![0f32; 4];
+
+        for (i,p) in delta.as_raw_slice().chunks(16).enumerate() {
+            expected_bias[i % 4] += p.iter().sum::<f32>();
+        }
+
+        let (output,u) = device.batch_forward_fused_convolution(&input,&kernel,&bias,activation).unwrap();
+
+        assert_close(&expected_u,u.as_raw_slice(),"batch u");
+        assert_close(&expected_output,output.as_raw_slice(),"batch output");
+
+        let (input_loss,kernel_gradient,bias_gradient) = device.batch_backward_fused_convolution(&loss,&u,&input,&kernel,activation).unwrap();
+
+        assert_close(expected_input.as_raw_slice(),input_loss.as_raw_slice(),"batch input loss");
+        assert_close(expected_kernel.as_raw_slice(),kernel_gradient.as_raw_slice(),"batch kernel gradient");
+        assert_close(&expected_bias,&bias_gradient,"batch bias gradient");
+
+        let mut x = Images::<f32,3,7,7>::new();
+        let mut l = Images::<f32,4,4,4>::new();
+
+        x.as_raw_mut_slice().copy_from_slice(&input.as_raw_slice()[..3 * 7 * 7]);
+        l.as_raw_mut_slice().copy_from_slice(&loss.as_raw_slice()[..4 * 4 * 4]);
+
+        let (output,u) = device.forward_fused_convolution(&x,&kernel,&bias,activation).unwrap();
+
+        assert_close(&expected_u[..64],u.as_raw_slice(),"u");
+        assert_close(&expected_output[..64],output.as_raw_slice(),"output");
+
+        let mut d = Images::<f32,4,4,4>::new();
+
+        d.as_raw_mut_slice().copy_from_slice(&delta.as_raw_slice()[..64]);
+
+        let expected_input = DeviceConvolution::<f32,FusedKernel,3,4,7,7,3,3,1,2>::backward_convolution(&reference,&d,&kernel).unwrap();
+        let expected_kernel = DeviceConvolution::<f32,FusedKernel,3,4,7,7,3,3,1,2>::backward_weight_gradient_convolution(&reference,&d,&x).unwrap();
+        let expected_bias = d.as_raw_slice().chunks(16).map(|p| p.iter().sum::<f32>()).collect::<Vec<f32>>();
+
+        let (input_loss,kernel_gradient,bias_gradient) = device.backward_fused_convolution(&l,&u,&x,&kernel,activation).unwrap();
+
+        assert_close(expected_input.as_raw_slice(),input_loss.as_raw_slice(),"input loss");
+        assert_close(expected_kernel.as_raw_slice(),kernel_gradient.as_raw_slice(),"kernel gradient");
+        assert_close(&expected_bias,&bias_gradient,"bias gradient");
+    }
+
+    fn assert_fused_activations<D>(device:&D) where D: DeviceFusedConvolution<f32,FusedKernel,3,4,7,7,3,3,1,2> {
+        let cpu = DeviceCpu::<f32>::new().unwrap();
+
+        assert_fused_matches_separate(device,&ReLu::new(&cpu));
+        assert_fused_matches_separate(device,&Sigmoid::new(&cpu));
+        assert_fused_matches_separate(device,&Swish::new(&cpu));
+        assert_fused_matches_separate(device,&Tanh::new(&cpu));
+        assert_fused_matches_separate(device,&LeakyReLu::new(0.1));
+    }
+
+    #[test]
+    fn test_fused_matches_separate() {
+        assert_fused_activations(&DeviceCpu::<f32>::new().unwrap());
+
+        for summation in [Summation::Unordered,Summation::Sequential,Summation::Pairwise,Summation::Kahan] {
+            for cutoff in [0,usize::MAX] {
+                assert_fused_activations(&DeviceCpuConfigured::<f32>::new(
+                    DeviceCpuConfig::default().with_summation(summation).with_sequential_cutoff(cutoff)
+                ));
+            }
+        }
+    }
+}
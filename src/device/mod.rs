@@ -1,13 +1,20 @@
 use const_guards::guard;
-use rayon::prelude::{IntoParallelIterator, ParallelIterator, IndexedParallelIterator, IntoParallelRefIterator};
+use num_traits::Float;
 use nncombinator::arr::{Arr, Arr4};
-use nncombinator::device::DeviceCpu;
+use nncombinator::activation::{Identity, ReLu, Sigmoid, Swish, Tanh};
+use nncombinator::device::{Device, DeviceCpu};
 use nncombinator::error::{EvaluateError, TrainingError};
 use nncombinator::error::SizeMismatchError;
+use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
 use nncombinator::ope::UnitValue;
 
-use crate::collection::{Image, Images};
+use crate::collection::{DynImages, DynVecImages, Images, ImagesViewMut};
 use crate::collection::VecImages;
+use crate::device::cpu::{batch_forward, batch_scratch_len, forward_sample, forward_scratch_len, ConvolutionShape, DeviceCpuConfigured};
+
+pub mod cpu;
+pub mod reference;
+pub mod simd;
 
 /// Trait that defines the implementation of various calculation processes in the convolution layer
 pub trait DeviceConvolution<U,F,const C:usize,const K:usize,const H:usize,const W:usize,
@@ -71,7 +78,7 @@ pub trait DeviceConvolution<U,F,const C:usize,const K:usize,const H:usize,const
     /// * [`TrainingError`]
     fn batch_backward_convolution(&self,loss:&VecImages<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
                                   kernel:&F)
-       -> Result<VecImages<U,C,H,K>, TrainingError>;
+       -> Result<VecImages<U,C,H,W>, TrainingError>;
     /// Calculate the gradient of the weights in batch
     /// # Arguments
     /// * `loss` - loss
@@ -84,81 +91,801 @@ pub trait DeviceConvolution<U,F,const C:usize,const K:usize,const H:usize,const
     fn batch_backward_weight_gradient_convolution(&self,
                                 loss: &VecImages<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
                                 input: &VecImages<U,C,H,W>)
-        -> Result<Arr4<U,K,C,H,W>, TrainingError>;
+        -> Result<Arr4<U,K,C,FH,FW>, TrainingError>;
+}
+/// Reusable scratch memory for convolution calculations
+///
+/// The memory grows to the largest size requested and is kept, so repeated calculations of the same shape do not allocate.
+#[derive(Debug)]
+pub struct ConvolutionWorkspace<U> where U: UnitValue<U> {
+    buffer:Vec<U>,
+}
+impl<U> ConvolutionWorkspace<U> where U: UnitValue<U> {
+    /// Create an empty instance of ConvolutionWorkspace
+    pub fn new() -> ConvolutionWorkspace<U> {
+        ConvolutionWorkspace {
+            buffer:Vec::new()
+        }
+    }
+
+    /// Create an instance of ConvolutionWorkspace with the specified number of elements allocated in advance
+    /// # Arguments
+    /// * `len` - Number of elements
+    pub fn with_len(len:usize) -> ConvolutionWorkspace<U> {
+        let mut workspace = ConvolutionWorkspace::new();
+
+        workspace.buffer(len);
+
+        workspace
+    }
+
+    /// get the number of elements allocated
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns true if no memory is allocated
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Obtain a scratch buffer of the specified number of elements, growing the memory only if it is too small
+    pub(crate) fn buffer(&mut self,len:usize) -> &mut [U] {
+        if self.buffer.len() < len {
+            self.buffer.resize(len,U::default());
+        }
+
+        &mut self.buffer[..len]
+    }
+}
+/// Trait that defines the calculations of the convolution layer writing into buffers provided by the caller
+pub trait DeviceConvolutionInto<U,F,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH: usize,const FW: usize,const PAD:usize,const S:usize>
+    where U: UnitValue<U> {
+    /// Forward propagation calculation
+    /// # Arguments
+    /// * `input` - Input values from upper layers
+    /// * `kernel` - filter weights
+    /// * `output` - Buffer to which the result is written
+    /// * `workspace` - Scratch memory
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`EvaluateError`]
+    fn forward_convolution_into(&self, input:&Images<U,C,H,W>, kernel:&F,
+                                output:&mut ImagesViewMut<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                workspace:&mut ConvolutionWorkspace<U>)
+        -> Result<(), EvaluateError>;
+    /// Error back propagation calculation
+    /// # Arguments
+    /// * `loss` - loss
+    /// * `kernel` - filter weights
+    /// * `output` - Buffer to which the result is written
+    /// * `workspace` - Scratch memory
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`TrainingError`]
+    fn backward_convolution_into(&self, loss:&Images<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                 kernel:&F, output:&mut ImagesViewMut<U,C,H,W>, workspace:&mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError>;
+    /// Calculate the gradient of the weights
+    /// # Arguments
+    /// * `loss` - loss
+    /// * `input` - Input values from upper layers
+    /// * `output` - Buffer to which the result is written
+    /// * `workspace` - Scratch memory
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`TrainingError`]
+    fn backward_weight_gradient_convolution_into(&self,
+                                loss: &Images<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                input: &Images<U,C,H,W>, output:&mut Arr4<U,K,C,FH,FW>, workspace:&mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError>;
+    /// Forward propagation calculation in batch
+    /// # Arguments
+    /// * `input` - Input values from upper layers
+    /// * `kernel` - filter weights
+    /// * `output` - Buffer to which the result is written, with as many elements as the input
+    /// * `workspace` - Scratch memory
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`EvaluateError`]
+    fn batch_forward_convolution_into(&self, input:&VecImages<U,C,H,W>, kernel:&F,
+                                      output:&mut VecImages<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                      workspace:&mut ConvolutionWorkspace<U>)
+        -> Result<(), EvaluateError>;
+    /// Error back propagation calculation in batch
+    /// # Arguments
+    /// * `loss` - loss
+    /// * `kernel` - filter weights
+    /// * `output` - Buffer to which the result is written, with as many elements as the loss
+    /// * `workspace` - Scratch memory
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`TrainingError`]
+    fn batch_backward_convolution_into(&self,loss:&VecImages<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                       kernel:&F, output:&mut VecImages<U,C,H,W>, workspace:&mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError>;
+    /// Calculate the gradient of the weights in batch
+    /// # Arguments
+    /// * `loss` - loss
+    /// * `input` - Input values from upper layers
+    /// * `output` - Buffer to which the result is written
+    /// * `workspace` - Scratch memory
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`TrainingError`]
+    fn batch_backward_weight_gradient_convolution_into(&self,
+                                loss: &VecImages<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                input: &VecImages<U,C,H,W>, output:&mut Arr4<U,K,C,FH,FW>, workspace:&mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError>;
+}
+/// Trait defining an activation function applied element by element in the epilogue of a fused convolution
+///
+/// It is implemented for the activation functions of nncombinator, so a fused convolution takes the same activation objects as the rest of the network.
+pub trait FusedActivation<U> where U: UnitValue<U> {
+    /// Apply the activation function
+    /// # Arguments
+    /// * `u` - Value before activation
+    fn apply_element(&self,u:U) -> U;
+
+    /// Derivative of the activation function
+    /// # Arguments
+    /// * `u` - Value before activation
+    fn derive_element(&self,u:U) -> U;
+}
+/// 1 / (1 + e^-u)
+fn sigmoid<U>(u:U) -> U where U: UnitValue<U> + Float {
+    <U as Float>::one() / (<U as Float>::one() + <U as Float>::exp(-u))
 }
+impl<U,D> FusedActivation<U> for Identity<U,D> where U: UnitValue<U> + Float, D: Device<U> {
+    fn apply_element(&self, u: U) -> U {
+        u
+    }
+
+    fn derive_element(&self, _: U) -> U {
+        <U as Float>::one()
+    }
+}
+impl<U,D> FusedActivation<U> for ReLu<U,D> where U: UnitValue<U> + Float, D: Device<U> {
+    fn apply_element(&self, u: U) -> U {
+        if u > <U as Float>::zero() { u } else { <U as Float>::zero() }
+    }
+
+    fn derive_element(&self, u: U) -> U {
+        if u > <U as Float>::zero() { <U as Float>::one() } else { <U as Float>::zero() }
+    }
+}
+impl<U,D> FusedActivation<U> for Sigmoid<U,D> where U: UnitValue<U> + Float, D: Device<U> {
+    fn apply_element(&self, u: U) -> U {
+        sigmoid(u)
+    }
+
+    fn derive_element(&self, u: U) -> U {
+        let s = sigmoid(u);
+
+        s * (<U as Float>::one() - s)
+    }
+}
+impl<U,D> FusedActivation<U> for Swish<U,D> where U: UnitValue<U> + Float, D: Device<U> {
+    fn apply_element(&self, u: U) -> U {
+        u * sigmoid(u)
+    }
+
+    fn derive_element(&self, u: U) -> U {
+        let s = sigmoid(u);
+
+        s + u * s * (<U as Float>::one() - s)
+    }
+}
+impl<U,D> FusedActivation<U> for Tanh<U,D> where U: UnitValue<U> + Float, D: Device<U> {
+    fn apply_element(&self, u: U) -> U {
+        <U as Float>::tanh(u)
+    }
+
+    fn derive_element(&self, u: U) -> U {
+        let t = <U as Float>::tanh(u);
+
+        <U as Float>::one() - t * t
+    }
+}
+/// Leaky ReLU, which nncombinator does not provide, for use in a fused convolution
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct LeakyReLu<U> where U: UnitValue<U> {
+    slope:U,
+}
+impl<U> LeakyReLu<U> where U: UnitValue<U> {
+    /// Create an instance of LeakyReLu
+    /// # Arguments
+    /// * `slope` - Slope for negative values
+    pub fn new(slope:U) -> LeakyReLu<U> {
+        LeakyReLu {
+            slope:slope
+        }
+    }
+}
+impl<U> FusedActivation<U> for LeakyReLu<U> where U: UnitValue<U> + Float {
+    fn apply_element(&self, u: U) -> U {
+        if u > <U as Float>::zero() { u } else { self.slope * u }
+    }
+
+    fn derive_element(&self, u: U) -> U {
+        if u > <U as Float>::zero() { <U as Float>::one() } else { self.slope }
+    }
+}
+/// Trait defining a convolution fused with the addition of a bias per output channel and an activation function
+pub trait DeviceFusedConvolution<U,F,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH: usize,const FW: usize,const PAD:usize,const S:usize>
+    where U: UnitValue<U> {
+    /// Forward propagation calculation
+    ///
+    /// Returns the output after activation and the value before activation, which is needed by backward_fused_convolution.
+    /// # Arguments
+    /// * `input` - Input values from upper layers
+    /// * `kernel` - filter weights
+    /// * `bias` - bias
+    /// * `activation` - Activation function
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`EvaluateError`]
+    fn forward_fused_convolution<A: FusedActivation<U> + Sync>(&self, input:&Images<U,C,H,W>, kernel:&F, bias:&Arr<U,K>, activation:&A)
+        -> Result<(Images<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                   Images<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>), EvaluateError>;
+    /// Error back propagation calculation
+    ///
+    /// Returns the loss of the input, the gradient of the kernel and the gradient of the bias.
+    /// # Arguments
+    /// * `loss` - loss of the output after activation
+    /// * `u` - Value before activation returned by forward_fused_convolution
+    /// * `input` - Input values from upper layers
+    /// * `kernel` - filter weights
+    /// * `activation` - Activation function
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`TrainingError`]
+    fn backward_fused_convolution<A: FusedActivation<U> + Sync>(&self, loss:&Images<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                  u:&Images<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                  input:&Images<U,C,H,W>, kernel:&F, activation:&A)
+        -> Result<(Images<U,C,H,W>,Arr4<U,K,C,FH,FW>,Arr<U,K>), TrainingError>;
+    /// Forward propagation calculation in batch
+    ///
+    /// Returns the output after activation and the value before activation, which is needed by batch_backward_fused_convolution.
+    /// # Arguments
+    /// * `input` - Input values from upper layers
+    /// * `kernel` - filter weights
+    /// * `bias` - bias
+    /// * `activation` - Activation function
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`EvaluateError`]
+    fn batch_forward_fused_convolution<A: FusedActivation<U> + Sync>(&self, input:&VecImages<U,C,H,W>, kernel:&F, bias:&Arr<U,K>, activation:&A)
+        -> Result<(VecImages<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                   VecImages<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>), EvaluateError>;
+    /// Error back propagation calculation in batch
+    ///
+    /// Returns the loss of the input, the gradient of the kernel and the gradient of the bias, both summed over the samples.
+    /// # Arguments
+    /// * `loss` - loss of the output after activation
+    /// * `u` - Value before activation returned by batch_forward_fused_convolution
+    /// * `input` - Input values from upper layers
+    /// * `kernel` - filter weights
+    /// * `activation` - Activation function
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`TrainingError`]
+    fn batch_backward_fused_convolution<A: FusedActivation<U> + Sync>(&self, loss:&VecImages<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                        u:&VecImages<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                        input:&VecImages<U,C,H,W>, kernel:&F, activation:&A)
+        -> Result<(VecImages<U,C,H,W>,Arr4<U,K,C,FH,FW>,Arr<U,K>), TrainingError>;
+}
+/// DeviceCpu computes the convolution with the kernels of DeviceCpuConfigured in its default configuration
 #[guard({ H + 2 * PAD >= FH && (H + 2 * PAD - FH) % S == 0 && W + 2 * PAD >= FW && (W + 2 * PAD - FW) % S == 0 })]
 impl<U,const C:usize,const K:usize,const H:usize,const W:usize,
-    const FH: usize,const FW: usize,const PAD:usize,const S:usize> DeviceConvolution<U,Arr4<U,K,C,H,W>,C,K,H,W,FH,FW,PAD,S> for DeviceCpu<U>
-    where U: UnitValue<U> {
-    fn forward_convolution<const CI: usize>(&self, input: &Images<U, C, H, W>, kernel: &Arr4<U,K,C,H,W>)
+    const FH: usize,const FW: usize,const PAD:usize,const S:usize> DeviceConvolution<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S> for DeviceCpu<U>
+    where U: UnitValue<U> + 'static {
+    fn forward_convolution<const CI: usize>(&self, input: &Images<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>)
         -> Result<Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>, EvaluateError> {
-        Ok(kernel.par_iter().map(|k| {
-            k.par_iter().zip(input.par_iter()).map(|(k,i)| {
-                (0..(H + PAD * 2 - FH)).into_par_iter().step_by(S).map(|sy| {
-                    (0..(W + PAD * 2 - FW)).into_par_iter().step_by(S).map(|sx| {
-                        k.iter().enumerate()
-                            .skip_while(|(oy,_)| sy + oy < PAD)
-                            .take_while(|(oy,_)| sy + oy < H).zip(i.clone().skip(sy - PAD))
-                            .map(|((_,k), i)| {
-                                k.iter().enumerate()
-                                    .skip_while(|(ox,_)| sx + ox < PAD)
-                                    .take_while(|(ox,_)| sx + ox < W).zip(i.iter().clone().skip(sx - PAD))
-                                    .map(|((_,&k),&i)| i * k)
-                                    .fold(U::default(), |acc, p| acc + p)
-                            }).fold(U::default(), |acc, p| acc + p)
-                        }).collect::<Vec<U>>()
-                }).collect::<Vec<Vec<U>>>()
-            }).fold(|| Ok(Image::new()),|acc,img| {
-                acc.and_then(|acc| acc.par_iter().zip(img.par_iter()).map(|(acc,row)| {
-                    acc.par_iter().zip(row.par_iter()).map(|(&acc,&pixel)| acc + pixel).collect::<Vec<U>>().try_into()
-                }).collect::<Result<Vec<Arr<U,{ ( W + 2 * PAD - FW ) / S + 1 }>>,SizeMismatchError>>()?.try_into())
-            }).reduce(|| Ok(Image::new()),|acc,img| {
-                acc.and_then(|acc| img.and_then(|img| {
-                    acc.par_iter().zip(img.par_iter()).map(|(acc,row)| {
-                        acc.par_iter().zip(row.par_iter()).map(|(&acc,&pixel)| acc + pixel).collect::<Vec<U>>().try_into()
-                    }).collect::<Result<Vec<Arr<U,{ ( W + 2 * PAD - FW ) / S + 1 }>>,SizeMismatchError>>()?.try_into()
-                }))
-            })
-        }).collect::<Result<Vec<Image<U,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>>,SizeMismatchError>>()?.try_into()?)
-    }
-
-    fn backward_convolution(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>, kernel: &Arr4<U,K,C,H,W>)
+        DeviceCpuConfigured::<U>::default().forward_convolution::<CI>(input,kernel)
+    }
+
+    fn backward_convolution(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>, kernel: &Arr4<U,K,C,FH,FW>)
         -> Result<Images<U, C, H, W>, TrainingError> {
-        Ok(kernel.par_iter().map(|k| {
-            (0..H).into_par_iter().map(|sy| {
-                (0..W).into_par_iter().map(|sx| {
-                    loss.par_iter().zip(k.par_iter()).map(|(l, k)| {
-                        let l = l[((sy + PAD) / S, (sx + PAD) / S)];
-
-                        k.iter().skip((sy + PAD) % S).step_by(S).map(|k| {
-                            k.iter().skip((sx + PAD) % S).step_by(S).map(|&w| l * w).fold(U::default(), |acc, l| {
-                                acc + l
-                            })
-                        }).fold(U::default(), |acc, l| acc + l)
-                    }).reduce(|| U::default(), | acc, l | acc + l)
-                }).collect::<Vec<U>>().try_into()
-            }).collect::<Result<Vec<Arr<U,W>>,SizeMismatchError>>()?.try_into()
-        }).collect::<Result<Vec<Image<U,H,W>>,SizeMismatchError>>()?.try_into()?)
+        DeviceCpuConfigured::<U>::default().backward_convolution(loss,kernel)
     }
+
     fn backward_weight_gradient_convolution(&self,loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
                                             input: &Images<U, C, H, W>)
         -> Result<Arr4<U, K, C, FH, FW>, TrainingError> {
-        todo!()
+        DeviceCpuConfigured::<U>::default().backward_weight_gradient_convolution(loss,input)
     }
-    fn batch_forward_convolution<const CI: usize>(&self, input: &VecImages<U, C, H, W>, kernel: &Arr4<U,K,C,H,W>)
+
+    fn batch_forward_convolution<const CI: usize>(&self, input: &VecImages<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>)
         -> Result<VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>, EvaluateError> {
-        todo!()
+        DeviceCpuConfigured::<U>::default().batch_forward_convolution::<CI>(input,kernel)
     }
+
     fn batch_backward_convolution(&self,
                                   loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
-                                  kernel: &Arr4<U,K,C,H,W>)
-        -> Result<VecImages<U, C, H, K>, TrainingError> {
-        todo!()
+                                  kernel: &Arr4<U,K,C,FH,FW>)
+        -> Result<VecImages<U, C, H, W>, TrainingError> {
+        DeviceCpuConfigured::<U>::default().batch_backward_convolution(loss,kernel)
     }
+
     fn batch_backward_weight_gradient_convolution(&self,
                                                   loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
                                                   input: &VecImages<U, C, H, W>)
-        -> Result<Arr4<U, K, C, H, W>, TrainingError> {
-        todo!()
+        -> Result<Arr4<U, K, C, FH, FW>, TrainingError> {
+        DeviceCpuConfigured::<U>::default().batch_backward_weight_gradient_convolution(loss,input)
+    }
+}
+/// DeviceCpu writes into the buffers of the caller with the kernels of DeviceCpuConfigured in its default configuration
+#[guard({ H + 2 * PAD >= FH && (H + 2 * PAD - FH) % S == 0 && W + 2 * PAD >= FW && (W + 2 * PAD - FW) % S == 0 })]
+impl<U,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH: usize,const FW: usize,const PAD:usize,const S:usize> DeviceConvolutionInto<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S> for DeviceCpu<U>
+    where U: UnitValue<U> + 'static {
+    fn forward_convolution_into(&self, input: &Images<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>,
+                                output: &mut ImagesViewMut<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), EvaluateError> {
+        DeviceCpuConfigured::<U>::default().forward_convolution_into(input,kernel,output,workspace)
+    }
+
+    fn backward_convolution_into(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                 kernel: &Arr4<U,K,C,FH,FW>, output: &mut ImagesViewMut<U, C, H, W>, workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        DeviceCpuConfigured::<U>::default().backward_convolution_into(loss,kernel,output,workspace)
+    }
+
+    fn backward_weight_gradient_convolution_into(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                                 input: &Images<U, C, H, W>, output: &mut Arr4<U, K, C, FH, FW>, workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        DeviceCpuConfigured::<U>::default().backward_weight_gradient_convolution_into(loss,input,output,workspace)
+    }
+
+    fn batch_forward_convolution_into(&self, input: &VecImages<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>,
+                                      output: &mut VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                      workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), EvaluateError> {
+        DeviceCpuConfigured::<U>::default().batch_forward_convolution_into(input,kernel,output,workspace)
+    }
+
+    fn batch_backward_convolution_into(&self, loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                       kernel: &Arr4<U,K,C,FH,FW>, output: &mut VecImages<U, C, H, W>, workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        DeviceCpuConfigured::<U>::default().batch_backward_convolution_into(loss,kernel,output,workspace)
+    }
+
+    fn batch_backward_weight_gradient_convolution_into(&self, loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                                       input: &VecImages<U, C, H, W>, output: &mut Arr4<U, K, C, FH, FW>,
+                                                       workspace: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        DeviceCpuConfigured::<U>::default().batch_backward_weight_gradient_convolution_into(loss,input,output,workspace)
+    }
+}
+/// DeviceCpu computes the fused convolution with the kernels of DeviceCpuConfigured in its default configuration
+#[guard({ H + 2 * PAD >= FH && (H + 2 * PAD - FH) % S == 0 && W + 2 * PAD >= FW && (W + 2 * PAD - FW) % S == 0 })]
+impl<U,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH: usize,const FW: usize,const PAD:usize,const S:usize> DeviceFusedConvolution<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S> for DeviceCpu<U>
+    where U: UnitValue<U> + 'static {
+    fn forward_fused_convolution<A: FusedActivation<U> + Sync>(&self, input: &Images<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>, bias: &Arr<U,K>, activation: &A)
+        -> Result<(Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                   Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>), EvaluateError> {
+        DeviceCpuConfigured::<U>::default().forward_fused_convolution(input,kernel,bias,activation)
+    }
+
+    fn backward_fused_convolution<A: FusedActivation<U> + Sync>(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                  u: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                  input: &Images<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>, activation: &A)
+        -> Result<(Images<U, C, H, W>, Arr4<U, K, C, FH, FW>, Arr<U, K>), TrainingError> {
+        DeviceCpuConfigured::<U>::default().backward_fused_convolution(loss,u,input,kernel,activation)
+    }
+
+    fn batch_forward_fused_convolution<A: FusedActivation<U> + Sync>(&self, input: &VecImages<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>, bias: &Arr<U,K>, activation: &A)
+        -> Result<(VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                   VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>), EvaluateError> {
+        DeviceCpuConfigured::<U>::default().batch_forward_fused_convolution(input,kernel,bias,activation)
+    }
+
+    fn batch_backward_fused_convolution<A: FusedActivation<U> + Sync>(&self, loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                        u: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                        input: &VecImages<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>, activation: &A)
+        -> Result<(VecImages<U, C, H, W>, Arr4<U, K, C, FH, FW>, Arr<U, K>), TrainingError> {
+        DeviceCpuConfigured::<U>::default().batch_backward_fused_convolution(loss,u,input,kernel,activation)
+    }
+}
+/// Trait that defines the convolution calculation for inputs whose height and width are determined at runtime
+pub trait DeviceDynConvolution<U,F,const C:usize,const K:usize,const FH:usize,const FW:usize,const PAD:usize,const S:usize>
+    where U: UnitValue<U> {
+    /// Forward propagation calculation
+    /// # Arguments
+    /// * `input` - Input values from upper layers
+    /// * `kernel` - filter weights
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`EvaluateError`]
+    fn forward_convolution_dyn(&self, input:&DynImages<U>, kernel:&F) -> Result<DynImages<U>, EvaluateError>;
+    /// Forward propagation calculation in batch
+    /// # Arguments
+    /// * `input` - Input values from upper layers
+    /// * `kernel` - filter weights
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`EvaluateError`]
+    fn batch_forward_convolution_dyn(&self, input:&DynVecImages<U>, kernel:&F) -> Result<DynVecImages<U>, EvaluateError>;
+    /// Forward propagation calculation writing into a buffer provided by the caller
+    /// # Arguments
+    /// * `input` - Input values from upper layers
+    /// * `kernel` - filter weights
+    /// * `output` - Buffer to which the result is written, with the shape of the output for the input
+    /// * `workspace` - Scratch memory
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`EvaluateError`]
+    fn forward_convolution_dyn_into(&self, input:&DynImages<U>, kernel:&F, output:&mut DynImages<U>,
+                                    workspace:&mut ConvolutionWorkspace<U>) -> Result<(), EvaluateError>;
+    /// Forward propagation calculation in batch writing into a buffer provided by the caller
+    /// # Arguments
+    /// * `input` - Input values from upper layers
+    /// * `kernel` - filter weights
+    /// * `output` - Buffer to which the result is written, with as many elements as the input and the shape of the output for the input
+    /// * `workspace` - Scratch memory
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`EvaluateError`]
+    fn batch_forward_convolution_dyn_into(&self, input:&DynVecImages<U>, kernel:&F, output:&mut DynVecImages<U>,
+                                          workspace:&mut ConvolutionWorkspace<U>) -> Result<(), EvaluateError>;
+}
+impl<U,const C:usize,const K:usize,const FH:usize,const FW:usize,const PAD:usize,const S:usize>
+    DeviceDynConvolution<U,Arr4<U,K,C,FH,FW>,C,K,FH,FW,PAD,S> for DeviceCpu<U>
+    where U: UnitValue<U> + 'static {
+    fn forward_convolution_dyn(&self, input: &DynImages<U>, kernel: &Arr4<U,K,C,FH,FW>) -> Result<DynImages<U>, EvaluateError> {
+        let (h,w) = dyn_convolution_output_size::<C,FH,FW,PAD,S>(input.channels(),input.height(),input.width())?;
+
+        let mut output = DynImages::new(K,h,w);
+
+        self.forward_convolution_dyn_into(input,kernel,&mut output,&mut ConvolutionWorkspace::new())?;
+
+        Ok(output)
+    }
+
+    fn batch_forward_convolution_dyn(&self, input: &DynVecImages<U>, kernel: &Arr4<U,K,C,FH,FW>) -> Result<DynVecImages<U>, EvaluateError> {
+        let (h,w) = dyn_convolution_output_size::<C,FH,FW,PAD,S>(input.channels(),input.height(),input.width())?;
+
+        let mut output = DynVecImages::with_size(input.len(),K,h,w);
+
+        self.batch_forward_convolution_dyn_into(input,kernel,&mut output,&mut ConvolutionWorkspace::new())?;
+
+        Ok(output)
+    }
+
+    fn forward_convolution_dyn_into(&self, input: &DynImages<U>, kernel: &Arr4<U,K,C,FH,FW>, output: &mut DynImages<U>,
+                                    workspace: &mut ConvolutionWorkspace<U>) -> Result<(), EvaluateError> {
+        let (h,w) = dyn_convolution_output_size::<C,FH,FW,PAD,S>(input.channels(),input.height(),input.width())?;
+
+        verify_dyn_output_size(output.channels(),output.height(),output.width(),K,h,w)?;
+
+        let shape = ConvolutionShape::new(C,K,input.height(),input.width(),FH,FW,PAD,S);
+
+        let device = DeviceCpuConfigured::<U>::default();
+        let summation = device.config().summation;
+
+        let scratch = workspace.buffer(forward_scratch_len(&shape,summation));
+
+        let parallel = device.parallel(&shape,1);
+
+        forward_sample(input.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,summation,scratch,parallel);
+
+        Ok(())
     }
-}
\ No newline at end of file
+
+    fn batch_forward_convolution_dyn_into(&self, input: &DynVecImages<U>, kernel: &Arr4<U,K,C,FH,FW>, output: &mut DynVecImages<U>,
+                                          workspace: &mut ConvolutionWorkspace<U>) -> Result<(), EvaluateError> {
+        let (h,w) = dyn_convolution_output_size::<C,FH,FW,PAD,S>(input.channels(),input.height(),input.width())?;
+
+        if input.len() != output.len() {
+            return Err(EvaluateError::from(SizeMismatchError(input.len(),output.len())));
+        }
+
+        verify_dyn_output_size(output.channels(),output.height(),output.width(),K,h,w)?;
+
+        let shape = ConvolutionShape::new(C,K,input.height(),input.width(),FH,FW,PAD,S);
+
+        let device = DeviceCpuConfigured::<U>::default();
+        let summation = device.config().summation;
+
+        let parallel = device.parallel(&shape,input.len());
+        let groups = device.batch_groups(input.len(),parallel);
+
+        let scratch = workspace.buffer(batch_scratch_len(forward_scratch_len(&shape,summation),groups));
+
+        // The output is at least 1x1 whenever the input passes the size check, so only K == 0 or an empty batch
+        // leave no elements, which would be split into chunks of size 0 and panic
+        if output.len() > 0 && K * h * w > 0 {
+            batch_forward(input.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),&shape,summation,scratch,groups,parallel);
+        }
+
+        Ok(())
+    }
+}
+/// Calculate the height and width of the output of the convolution for a runtime input size
+///
+/// A stride of 0 is reported as a mismatch against the minimum stride of 1
+fn dyn_convolution_output_size<const C:usize,const FH:usize,const FW:usize,const PAD:usize,const S:usize>(c:usize,h:usize,w:usize)
+    -> Result<(usize,usize),SizeMismatchError> {
+    if S == 0 {
+        Err(SizeMismatchError(S,1))
+    } else if c != C {
+        Err(SizeMismatchError(c,C))
+    } else if h + 2 * PAD < FH {
+        Err(SizeMismatchError(h + 2 * PAD,FH))
+    } else if w + 2 * PAD < FW {
+        Err(SizeMismatchError(w + 2 * PAD,FW))
+    } else {
+        Ok(((h + 2 * PAD - FH) / S + 1, (w + 2 * PAD - FW) / S + 1))
+    }
+}
+/// Verify that the shape of a buffer provided by the caller matches the shape of the output
+fn verify_dyn_output_size(c:usize,h:usize,w:usize,k:usize,oh:usize,ow:usize) -> Result<(),SizeMismatchError> {
+    if c != k {
+        Err(SizeMismatchError(c,k))
+    } else if h != oh {
+        Err(SizeMismatchError(h,oh))
+    } else if w != ow {
+        Err(SizeMismatchError(w,ow))
+    } else {
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use rayon::{ThreadPool, ThreadPoolBuilder};
+    use nncombinator::arr::Arr4;
+    use nncombinator::device::DeviceCpu;
+
+    use crate::collection::{DynImages, DynVecImages, Images, VecImages};
+    use crate::device::{ConvolutionWorkspace, DeviceConvolutionInto, DeviceDynConvolution};
+    use crate::device::cpu::{DeviceCpuConfig, DeviceCpuConfigured};
+    use crate::device::reference::{compare_with_reference, ConvolutionDifference};
+
+    /// Global allocator that counts the allocations made by the threads marked as counted
+    ///
+    /// The workers of the pool returned by counted_pool are always marked,
+    /// so the allocations made by rayon's workers are counted as well as those of the calling thread.
+    struct CountingAllocator;
+
+    static ALLOCATIONS:AtomicUsize = AtomicUsize::new(0);
+
+    /// Held while allocations are counted, since the counter is shared by all the tests of this binary
+    static COUNTING:Mutex<()> = Mutex::new(());
+
+    thread_local! {
+        static COUNTED:Cell<bool> = const { Cell::new(false) };
+    }
+
+    fn count() {
+        if COUNTED.try_with(|c| c.get()).unwrap_or(false) {
+            ALLOCATIONS.fetch_add(1,Ordering::SeqCst);
+        }
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count();
+
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr,layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count();
+
+            System.realloc(ptr,layout,new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR:CountingAllocator = CountingAllocator;
+
+    /// Thread pool whose workers are marked as counted, shared by the tests so that no worker outlives its pool while counting
+    fn counted_pool() -> Arc<ThreadPool> {
+        static POOL:OnceLock<Arc<ThreadPool>> = OnceLock::new();
+
+        POOL.get_or_init(|| {
+            Arc::new(ThreadPoolBuilder::new()
+                .num_threads(4)
+                .start_handler(|_| COUNTED.with(|c| c.set(true)))
+                .build()
+                .unwrap())
+        }).clone()
+    }
+
+    /// Run f once to grow the buffers, then `steps` more times on the counted pool and return the number of allocations of those steps
+    ///
+    /// Rayon may allocate a block of its injector queue when the steps are handed to the pool,
+    /// so a caller should allow fewer allocations than steps rather than require none.
+    fn allocations_of_steps<F>(steps:usize,mut f:F) -> usize where F: FnMut() + Send {
+        let _counting = COUNTING.lock().unwrap_or_else(|e| e.into_inner());
+        let pool = counted_pool();
+
+        pool.install(|| f());
+
+        COUNTED.with(|c| c.set(true));
+
+        let before = ALLOCATIONS.load(Ordering::SeqCst);
+
+        pool.install(|| {
+            for _ in 0..steps {
+                f();
+            }
+        });
+
+        let after = ALLOCATIONS.load(Ordering::SeqCst);
+
+        COUNTED.with(|c| c.set(false));
+
+        after - before
+    }
+
+    const STEPS:usize = 4;
+
+    type Kernel = Arr4<f32,4,3,3,3>;
+
+    /// Run one training step of forward, backward and weight gradient into preallocated buffers
+    fn step<D>(device:&D,input:&VecImages<f32,3,8,8>,kernel:&Kernel,loss:&VecImages<f32,4,8,8>,
+               output:&mut VecImages<f32,4,8,8>,delta:&mut VecImages<f32,3,8,8>,gradient:&mut Kernel,
+               x:&Images<f32,3,8,8>,single:&mut Images<f32,4,8,8>,workspace:&mut ConvolutionWorkspace<f32>)
+        where D: DeviceConvolutionInto<f32,Kernel,3,4,8,8,3,3,1,1> {
+        device.batch_forward_convolution_into(input,kernel,output,workspace).unwrap();
+        device.batch_backward_convolution_into(loss,kernel,delta,workspace).unwrap();
+        device.batch_backward_weight_gradient_convolution_into(loss,input,gradient,workspace).unwrap();
+
+        device.forward_convolution_into(x,kernel,&mut single.view_mut(),workspace).unwrap();
+    }
+
+    fn assert_no_allocation_per_step<D>(device:&D) where D: DeviceConvolutionInto<f32,Kernel,3,4,8,8,3,3,1,1> + Sync {
+        let input = VecImages::with_size(4);
+        let kernel = Kernel::new();
+        let loss = VecImages::with_size(4);
+        let x = Images::new();
+
+        let mut output = VecImages::with_size(4);
+        let mut delta = VecImages::with_size(4);
+        let mut gradient = Kernel::new();
+        let mut single = Images::new();
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let allocations = allocations_of_steps(STEPS,|| {
+            step(device,&input,&kernel,&loss,&mut output,&mut delta,&mut gradient,&x,&mut single,&mut workspace);
+        });
+
+        assert!(allocations < STEPS,"{} allocations in {} steps",allocations,STEPS);
+    }
+
+    fn assert_within(difference:ConvolutionDifference,tolerance:f64) {
+        assert!(difference.forward.max_absolute_difference <= tolerance,"forward: {:?}",difference.forward);
+        assert!(difference.backward.max_absolute_difference <= tolerance,"backward: {:?}",difference.backward);
+        assert!(difference.weight_gradient.max_absolute_difference <= tolerance,"weight gradient: {:?}",difference.weight_gradient);
+    }
+
+    #[test]
+    fn test_device_cpu_matches_reference_f32() {
+        let device = DeviceCpu::<f32>::new().unwrap();
+
+        assert_within(compare_with_reference::<f32,_,1,1,4,4,3,3,0,1>(&device,1).unwrap(),1e-4);
+        assert_within(compare_with_reference::<f32,_,2,3,5,5,3,3,1,1>(&device,2).unwrap(),1e-4);
+        assert_within(compare_with_reference::<f32,_,3,2,7,7,3,3,1,2>(&device,3).unwrap(),1e-4);
+        assert_within(compare_with_reference::<f32,_,2,2,8,6,2,4,0,2>(&device,4).unwrap(),1e-4);
+        assert_within(compare_with_reference::<f32,_,2,4,6,6,5,5,2,1>(&device,5).unwrap(),1e-4);
+        assert_within(compare_with_reference::<f32,_,8,8,16,16,3,3,1,1>(&device,6).unwrap(),1e-3);
+    }
+
+    #[test]
+    fn test_device_cpu_matches_reference_f64() {
+        let device = DeviceCpu::<f64>::new().unwrap();
+
+        assert_within(compare_with_reference::<f64,_,1,1,4,4,3,3,0,1>(&device,1).unwrap(),1e-10);
+        assert_within(compare_with_reference::<f64,_,2,3,5,5,3,3,1,1>(&device,2).unwrap(),1e-10);
+        assert_within(compare_with_reference::<f64,_,3,2,7,7,3,3,1,2>(&device,3).unwrap(),1e-10);
+        assert_within(compare_with_reference::<f64,_,2,2,8,6,2,4,0,2>(&device,4).unwrap(),1e-10);
+        assert_within(compare_with_reference::<f64,_,8,8,16,16,3,3,1,1>(&device,6).unwrap(),1e-10);
+    }
+
+    #[test]
+    fn test_no_allocation_per_step() {
+        assert_no_allocation_per_step(&DeviceCpu::<f32>::new().unwrap());
+        assert_no_allocation_per_step(&DeviceCpuConfigured::<f32>::new(DeviceCpuConfig::default()));
+        assert_no_allocation_per_step(&DeviceCpuConfigured::<f32>::new(DeviceCpuConfig::deterministic()));
+    }
+
+    #[test]
+    fn test_no_allocation_per_parallel_step() {
+        // The shapes of the steps are below the default cutoff, so the cutoff is lowered to run them on the workers
+        assert_no_allocation_per_step(&DeviceCpuConfigured::<f32>::new(
+            DeviceCpuConfig::default().with_thread_pool(counted_pool()).with_sequential_cutoff(0)
+        ));
+        assert_no_allocation_per_step(&DeviceCpuConfigured::<f32>::new(
+            DeviceCpuConfig::deterministic().with_thread_pool(counted_pool()).with_sequential_cutoff(0)
+        ));
+        assert_no_allocation_per_step(&DeviceCpuConfigured::<f32>::new(
+            DeviceCpuConfig::default().with_sequential_cutoff(0)
+        ));
+    }
+
+    #[test]
+    fn test_no_allocation_per_dyn_step() {
+        let device = DeviceCpu::<f32>::new().unwrap();
+        let kernel = Kernel::new();
+
+        let input = DynImages::new(3,10,6);
+        let batch = DynVecImages::with_size(2,3,10,6);
+
+        let mut output = DynImages::new(4,10,6);
+        let mut batch_output = DynVecImages::with_size(2,4,10,6);
+        let mut workspace = ConvolutionWorkspace::new();
+
+        let allocations = allocations_of_steps(STEPS,|| {
+            DeviceDynConvolution::<f32,Kernel,3,4,3,3,1,1>::forward_convolution_dyn_into(&device,&input,&kernel,&mut output,&mut workspace).unwrap();
+            DeviceDynConvolution::<f32,Kernel,3,4,3,3,1,1>::batch_forward_convolution_dyn_into(&device,&batch,&kernel,&mut batch_output,&mut workspace).unwrap();
+        });
+
+        assert!(allocations < STEPS,"{} allocations in {} steps",allocations,STEPS);
+    }
+
+    #[test]
+    fn test_dyn_into_rejects_output_of_wrong_shape() {
+        let device = DeviceCpu::<f32>::new().unwrap();
+
+        let mut output = DynImages::new(4,9,6);
+
+        assert!(DeviceDynConvolution::<f32,Kernel,3,4,3,3,1,1>::forward_convolution_dyn_into(
+            &device,&DynImages::new(3,10,6),&Kernel::new(),&mut output,&mut ConvolutionWorkspace::new()
+        ).is_err());
+    }
+
+    #[test]
+    fn test_dyn_rejects_zero_stride() {
+        let device = DeviceCpu::<f32>::new().unwrap();
+        let kernel = Kernel::new();
+
+        assert!(DeviceDynConvolution::<f32,Kernel,3,4,3,3,1,0>::forward_convolution_dyn(
+            &device,&DynImages::new(3,10,6),&kernel
+        ).is_err());
+        assert!(DeviceDynConvolution::<f32,Kernel,3,4,3,3,1,0>::batch_forward_convolution_dyn(
+            &device,&DynVecImages::with_size(2,3,10,6),&kernel
+        ).is_err());
+    }
+}
@@ -0,0 +1,416 @@
+use std::marker::PhantomData;
+use const_guards::guard;
+use num_traits::{FromPrimitive, ToPrimitive};
+use nncombinator::arr::Arr4;
+use nncombinator::error::{EvaluateError, SizeMismatchError, TrainingError};
+use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+use nncombinator::ope::UnitValue;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use crate::collection::{Images, ImagesViewMut, VecImages};
+use crate::device::{ConvolutionWorkspace, DeviceConvolution, DeviceConvolutionInto};
+use crate::gradient_check::GradientCheckError;
+
+/// Single-threaded device that computes with plain loops, used as the reference for other devices
+#[derive(Debug)]
+pub struct DeviceReference<U> where U: UnitValue<U> {
+    u:PhantomData<U>,
+}
+impl<U> DeviceReference<U> where U: UnitValue<U> {
+    /// Create an instance of DeviceReference
+    pub fn new() -> DeviceReference<U> {
+        DeviceReference {
+            u:PhantomData::<U>
+        }
+    }
+}
+impl<U> Clone for DeviceReference<U> where U: UnitValue<U> {
+    fn clone(&self) -> Self {
+        DeviceReference::new()
+    }
+}
+/// Convolve one sample given as raw slices
+fn forward_slice<U>(input:&[U],kernel:&[U],output:&mut [U],c:usize,k:usize,h:usize,w:usize,
+                    fh:usize,fw:usize,pad:usize,s:usize) where U: UnitValue<U> {
+    let oh = (h + 2 * pad - fh) / s + 1;
+    let ow = (w + 2 * pad - fw) / s + 1;
+
+    for ko in 0..k {
+        for oy in 0..oh {
+            for ox in 0..ow {
+                let mut acc = U::default();
+
+                for ci in 0..c {
+                    for fy in 0..fh {
+                        for fx in 0..fw {
+                            let y = oy * s + fy;
+                            let x = ox * s + fx;
+
+                            if y < pad || x < pad || y - pad >= h || x - pad >= w {
+                                continue;
+                            }
+
+                            acc = acc + input[(ci * h + y - pad) * w + x - pad] * kernel[((ko * c + ci) * fh + fy) * fw + fx];
+                        }
+                    }
+                }
+
+                output[(ko * oh + oy) * ow + ox] = acc;
+            }
+        }
+    }
+}
+/// Propagate the loss of one sample back to the input
+fn backward_slice<U>(loss:&[U],kernel:&[U],output:&mut [U],c:usize,k:usize,h:usize,w:usize,
+                     fh:usize,fw:usize,pad:usize,s:usize) where U: UnitValue<U> {
+    let oh = (h + 2 * pad - fh) / s + 1;
+    let ow = (w + 2 * pad - fw) / s + 1;
+
+    for v in output.iter_mut() {
+        *v = U::default();
+    }
+
+    for ko in 0..k {
+        for oy in 0..oh {
+            for ox in 0..ow {
+                let l = loss[(ko * oh + oy) * ow + ox];
+
+                for ci in 0..c {
+                    for fy in 0..fh {
+                        for fx in 0..fw {
+                            let y = oy * s + fy;
+                            let x = ox * s + fx;
+
+                            if y < pad || x < pad || y - pad >= h || x - pad >= w {
+                                continue;
+                            }
+
+                            let i = (ci * h + y - pad) * w + x - pad;
+
+                            output[i] = output[i] + l * kernel[((ko * c + ci) * fh + fy) * fw + fx];
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+/// Accumulate the gradient of the kernel for one sample
+fn weight_gradient_slice<U>(loss:&[U],input:&[U],gradient:&mut [U],c:usize,k:usize,h:usize,w:usize,
+                            fh:usize,fw:usize,pad:usize,s:usize) where U: UnitValue<U> {
+    let oh = (h + 2 * pad - fh) / s + 1;
+    let ow = (w + 2 * pad - fw) / s + 1;
+
+    for ko in 0..k {
+        for ci in 0..c {
+            for fy in 0..fh {
+                for fx in 0..fw {
+                    let mut acc = U::default();
+
+                    for oy in 0..oh {
+                        for ox in 0..ow {
+                            let y = oy * s + fy;
+                            let x = ox * s + fx;
+
+                            if y < pad || x < pad || y - pad >= h || x - pad >= w {
+                                continue;
+                            }
+
+                            acc = acc + loss[(ko * oh + oy) * ow + ox] * input[(ci * h + y - pad) * w + x - pad];
+                        }
+                    }
+
+                    let i = ((ko * c + ci) * fh + fy) * fw + fx;
+
+                    gradient[i] = gradient[i] + acc;
+                }
+            }
+        }
+    }
+}
+#[guard({ H + 2 * PAD >= FH && (H + 2 * PAD - FH) % S == 0 && W + 2 * PAD >= FW && (W + 2 * PAD - FW) % S == 0 })]
+impl<U,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH: usize,const FW: usize,const PAD:usize,const S:usize> DeviceConvolution<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S> for DeviceReference<U>
+    where U: UnitValue<U> {
+    fn forward_convolution<const CI: usize>(&self, input: &Images<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>)
+        -> Result<Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>, EvaluateError> {
+        let mut output = Images::new();
+
+        forward_slice(input.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+
+        Ok(output)
+    }
+
+    fn backward_convolution(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>, kernel: &Arr4<U,K,C,FH,FW>)
+        -> Result<Images<U, C, H, W>, TrainingError> {
+        let mut output = Images::new();
+
+        backward_slice(loss.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+
+        Ok(output)
+    }
+
+    fn backward_weight_gradient_convolution(&self,loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                            input: &Images<U, C, H, W>)
+        -> Result<Arr4<U, K, C, FH, FW>, TrainingError> {
+        let mut gradient = Arr4::new();
+
+        weight_gradient_slice(loss.as_raw_slice(),input.as_raw_slice(),gradient.as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+
+        Ok(gradient)
+    }
+
+    fn batch_forward_convolution<const CI: usize>(&self, input: &VecImages<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>)
+        -> Result<VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>, EvaluateError> {
+        let mut output = VecImages::with_size(input.len());
+
+        for i in 0..input.len() {
+            forward_slice(input[i].as_raw_slice(),kernel.as_raw_slice(),output[i].as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+        }
+
+        Ok(output)
+    }
+
+    fn batch_backward_convolution(&self,
+                                  loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                  kernel: &Arr4<U,K,C,FH,FW>)
+        -> Result<VecImages<U, C, H, W>, TrainingError> {
+        let mut output = VecImages::with_size(loss.len());
+
+        for i in 0..loss.len() {
+            backward_slice(loss[i].as_raw_slice(),kernel.as_raw_slice(),output[i].as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+        }
+
+        Ok(output)
+    }
+
+    fn batch_backward_weight_gradient_convolution(&self,
+                                                  loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                                  input: &VecImages<U, C, H, W>)
+        -> Result<Arr4<U, K, C, FH, FW>, TrainingError> {
+        if loss.len() != input.len() {
+            return Err(TrainingError::from(SizeMismatchError(loss.len(),input.len())));
+        }
+
+        let mut gradient = Arr4::new();
+
+        for i in 0..input.len() {
+            weight_gradient_slice(loss[i].as_raw_slice(),input[i].as_raw_slice(),gradient.as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+        }
+
+        Ok(gradient)
+    }
+}
+#[guard({ H + 2 * PAD >= FH && (H + 2 * PAD - FH) % S == 0 && W + 2 * PAD >= FW && (W + 2 * PAD - FW) % S == 0 })]
+impl<U,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH: usize,const FW: usize,const PAD:usize,const S:usize> DeviceConvolutionInto<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S> for DeviceReference<U>
+    where U: UnitValue<U> {
+    fn forward_convolution_into(&self, input: &Images<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>,
+                                output: &mut ImagesViewMut<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                _: &mut ConvolutionWorkspace<U>)
+        -> Result<(), EvaluateError> {
+        forward_slice(input.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+
+        Ok(())
+    }
+
+    fn backward_convolution_into(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                 kernel: &Arr4<U,K,C,FH,FW>, output: &mut ImagesViewMut<U, C, H, W>, _: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        backward_slice(loss.as_raw_slice(),kernel.as_raw_slice(),output.as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+
+        Ok(())
+    }
+
+    fn backward_weight_gradient_convolution_into(&self, loss: &Images<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                                 input: &Images<U, C, H, W>, output: &mut Arr4<U, K, C, FH, FW>, _: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        for v in output.as_raw_mut_slice().iter_mut() {
+            *v = U::default();
+        }
+
+        weight_gradient_slice(loss.as_raw_slice(),input.as_raw_slice(),output.as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+
+        Ok(())
+    }
+
+    fn batch_forward_convolution_into(&self, input: &VecImages<U, C, H, W>, kernel: &Arr4<U,K,C,FH,FW>,
+                                      output: &mut VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                      _: &mut ConvolutionWorkspace<U>)
+        -> Result<(), EvaluateError> {
+        if input.len() != output.len() {
+            return Err(EvaluateError::from(SizeMismatchError(input.len(),output.len())));
+        }
+
+        for i in 0..input.len() {
+            forward_slice(input[i].as_raw_slice(),kernel.as_raw_slice(),output[i].as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+        }
+
+        Ok(())
+    }
+
+    fn batch_backward_convolution_into(&self, loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                       kernel: &Arr4<U,K,C,FH,FW>, output: &mut VecImages<U, C, H, W>, _: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        if loss.len() != output.len() {
+            return Err(TrainingError::from(SizeMismatchError(loss.len(),output.len())));
+        }
+
+        for i in 0..loss.len() {
+            backward_slice(loss[i].as_raw_slice(),kernel.as_raw_slice(),output[i].as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+        }
+
+        Ok(())
+    }
+
+    fn batch_backward_weight_gradient_convolution_into(&self, loss: &VecImages<U, K, { ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,
+                                                       input: &VecImages<U, C, H, W>, output: &mut Arr4<U, K, C, FH, FW>,
+                                                       _: &mut ConvolutionWorkspace<U>)
+        -> Result<(), TrainingError> {
+        if loss.len() != input.len() {
+            return Err(TrainingError::from(SizeMismatchError(loss.len(),input.len())));
+        }
+
+        for v in output.as_raw_mut_slice().iter_mut() {
+            *v = U::default();
+        }
+
+        for i in 0..input.len() {
+            weight_gradient_slice(loss[i].as_raw_slice(),input[i].as_raw_slice(),output.as_raw_mut_slice(),C,K,H,W,FH,FW,PAD,S);
+        }
+
+        Ok(())
+    }
+}
+/// Difference between the values computed by the reference device and by another device
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct DifferenceReport {
+    /// Number of compared values
+    pub count:usize,
+    /// Maximum absolute difference
+    pub max_absolute_difference:f64,
+    /// Maximum relative difference
+    pub max_relative_difference:f64,
+    /// Position of the value with the maximum absolute difference in the raw slice
+    pub worst_index:usize,
+    /// Value of the reference device at the worst position
+    pub expected:f64,
+    /// Value of the compared device at the worst position
+    pub actual:f64,
+}
+impl DifferenceReport {
+    /// Compare values element by element
+    /// # Arguments
+    /// * `expected` - Values computed by the reference device
+    /// * `actual` - Values computed by the compared device
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`SizeMismatchError`]
+    pub fn compare<U>(expected:&[U],actual:&[U]) -> Result<DifferenceReport,SizeMismatchError> where U: UnitValue<U> + ToPrimitive {
+        if expected.len() != actual.len() {
+            return Err(SizeMismatchError(expected.len(),actual.len()));
+        }
+
+        let mut report = DifferenceReport {
+            count:expected.len(),
+            max_absolute_difference:0.,
+            max_relative_difference:0.,
+            worst_index:0,
+            expected:0.,
+            actual:0.
+        };
+
+        for (i,(e,a)) in expected.iter().zip(actual.iter()).enumerate() {
+            let e = <U as ToPrimitive>::to_f64(e).expect("Error in type conversion to f64.");
+            let a = <U as ToPrimitive>::to_f64(a).expect("Error in type conversion to f64.");
+
+            let absolute = (e - a).abs();
+            let relative = absolute / e.abs().max(a.abs()).max(f64::MIN_POSITIVE);
+
+            if absolute > report.max_absolute_difference || absolute.is_nan() {
+                report.max_absolute_difference = absolute;
+                report.worst_index = i;
+                report.expected = e;
+                report.actual = a;
+            }
+
+            if absolute > 0. {
+                report.max_relative_difference = report.max_relative_difference.max(relative);
+            }
+        }
+
+        Ok(report)
+    }
+}
+/// Differences of each computation of a convolution
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct ConvolutionDifference {
+    /// Difference of forward_convolution
+    pub forward:DifferenceReport,
+    /// Difference of backward_convolution
+    pub backward:DifferenceReport,
+    /// Difference of backward_weight_gradient_convolution
+    pub weight_gradient:DifferenceReport,
+}
+/// Run a device and DeviceReference on random input, kernel and loss drawn from [-1,1) and compare the results
+/// # Arguments
+/// * `device` - Device to be compared
+/// * `seed` - Seed of the random number generator
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`GradientCheckError`]
+pub fn compare_with_reference<U,D,const C:usize,const K:usize,const H:usize,const W:usize,
+                              const FH:usize,const FW:usize,const PAD:usize,const S:usize>(device:&D,seed:u64)
+    -> Result<ConvolutionDifference,GradientCheckError>
+    where U: UnitValue<U> + FromPrimitive + ToPrimitive,
+          D: DeviceConvolution<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S>,
+          DeviceReference<U>: DeviceConvolution<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S>,
+          [(); ( H + 2 * PAD - FH ) / S + 1]:,
+          [(); ( W + 2 * PAD - FW ) / S + 1]: {
+    let mut rnd = XorShiftRng::seed_from_u64(seed);
+
+    let mut random = |values:&mut [U]| {
+        for v in values.iter_mut() {
+            *v = <U as FromPrimitive>::from_f64(rnd.gen_range(-1.0..1.0)).expect("Error in type conversion from f64.");
+        }
+    };
+
+    let mut input = Images::new();
+    let mut kernel = Arr4::new();
+    let mut loss = Images::new();
+
+    random(input.as_raw_mut_slice());
+    random(kernel.as_raw_mut_slice());
+    random(loss.as_raw_mut_slice());
+
+    let reference = DeviceReference::<U>::new();
+
+    let expected = reference.forward_convolution::<C>(&input,&kernel)?;
+    let actual = device.forward_convolution::<C>(&input,&kernel)?;
+
+    let forward = DifferenceReport::compare(expected.as_raw_slice(),actual.as_raw_slice())
+        .map_err(|e| GradientCheckError::EvaluateError(EvaluateError::from(e)))?;
+
+    let expected = reference.backward_convolution(&loss,&kernel)?;
+    let actual = device.backward_convolution(&loss,&kernel)?;
+
+    let backward = DifferenceReport::compare(expected.as_raw_slice(),actual.as_raw_slice())
+        .map_err(|e| GradientCheckError::TrainingError(TrainingError::from(e)))?;
+
+    let expected = reference.backward_weight_gradient_convolution(&loss,&input)?;
+    let actual = device.backward_weight_gradient_convolution(&loss,&input)?;
+
+    let weight_gradient = DifferenceReport::compare(expected.as_raw_slice(),actual.as_raw_slice())
+        .map_err(|e| GradientCheckError::TrainingError(TrainingError::from(e)))?;
+
+    Ok(ConvolutionDifference {
+        forward:forward,
+        backward:backward,
+        weight_gradient:weight_gradient
+    })
+}
@@ -0,0 +1,505 @@
+use std::any::TypeId;
+use std::slice;
+use std::sync::OnceLock;
+use rayon::prelude::{IndexedParallelIterator, ParallelIterator, ParallelSliceMut};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// Instruction set used by the vectorized kernels
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+pub enum SimdLevel {
+    /// Portable scalar code
+    Scalar,
+    /// SSE2 (128 bit)
+    Sse2,
+    /// AVX2 with FMA (256 bit)
+    Avx2Fma,
+}
+impl SimdLevel {
+    /// Returns true if the instruction set is available on the running CPU
+    pub fn is_available(&self) -> bool {
+        match self {
+            SimdLevel::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Sse2 => is_x86_feature_detected!("sse2"),
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx2Fma => is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma"),
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
+
+    /// get the instruction sets available on the running CPU
+    pub fn available() -> Vec<SimdLevel> {
+        [SimdLevel::Scalar,SimdLevel::Sse2,SimdLevel::Avx2Fma].iter().cloned().filter(|l| l.is_available()).collect()
+    }
+}
+/// get the best instruction set available on the running CPU, detected once
+pub fn detect() -> SimdLevel {
+    static LEVEL:OnceLock<SimdLevel> = OnceLock::new();
+
+    *LEVEL.get_or_init(|| {
+        if SimdLevel::Avx2Fma.is_available() {
+            SimdLevel::Avx2Fma
+        } else if SimdLevel::Sse2.is_available() {
+            SimdLevel::Sse2
+        } else {
+            SimdLevel::Scalar
+        }
+    })
+}
+/// Trait defining the floating point types that have vectorized kernels
+pub trait SimdFloat: Copy + Send + Sync + 'static {
+    /// Dot product of a and b with the specified instruction set
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instruction set is not available on the running CPU
+    fn dot_with(level:SimdLevel,a:&[Self],b:&[Self]) -> Self;
+
+    /// y += alpha * x with the specified instruction set
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instruction set is not available on the running CPU
+    fn axpy_with(level:SimdLevel,alpha:Self,x:&[Self],y:&mut [Self]);
+
+    /// Dot product of a and b with the best instruction set available
+    fn dot(a:&[Self],b:&[Self]) -> Self {
+        Self::dot_with(detect(),a,b)
+    }
+
+    /// y += alpha * x with the best instruction set available
+    fn axpy(alpha:Self,x:&[Self],y:&mut [Self]) {
+        Self::axpy_with(detect(),alpha,x,y)
+    }
+}
+fn verify_level(level:SimdLevel) {
+    if !level.is_available() {
+        panic!("The instruction set {:?} is not available on this CPU.",level);
+    }
+}
+impl SimdFloat for f32 {
+    fn dot_with(level:SimdLevel,a:&[f32],b:&[f32]) -> f32 {
+        verify_level(level);
+
+        match level {
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx2Fma => unsafe { dot_f32_avx2(a,b) },
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Sse2 => unsafe { dot_f32_sse2(a,b) },
+            _ => a.iter().zip(b.iter()).fold(0.,|acc,(&a,&b)| acc + a * b)
+        }
+    }
+
+    fn axpy_with(level:SimdLevel,alpha:f32,x:&[f32],y:&mut [f32]) {
+        verify_level(level);
+
+        match level {
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx2Fma => unsafe { axpy_f32_avx2(alpha,x,y) },
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Sse2 => unsafe { axpy_f32_sse2(alpha,x,y) },
+            _ => {
+                for (y,&x) in y.iter_mut().zip(x.iter()) {
+                    *y += alpha * x;
+                }
+            }
+        }
+    }
+}
+impl SimdFloat for f64 {
+    fn dot_with(level:SimdLevel,a:&[f64],b:&[f64]) -> f64 {
+        verify_level(level);
+
+        match level {
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx2Fma => unsafe { dot_f64_avx2(a,b) },
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Sse2 => unsafe { dot_f64_sse2(a,b) },
+            _ => a.iter().zip(b.iter()).fold(0.,|acc,(&a,&b)| acc + a * b)
+        }
+    }
+
+    fn axpy_with(level:SimdLevel,alpha:f64,x:&[f64],y:&mut [f64]) {
+        verify_level(level);
+
+        match level {
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx2Fma => unsafe { axpy_f64_avx2(alpha,x,y) },
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Sse2 => unsafe { axpy_f64_sse2(alpha,x,y) },
+            _ => {
+                for (y,&x) in y.iter_mut().zip(x.iter()) {
+                    *y += alpha * x;
+                }
+            }
+        }
+    }
+}
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn dot_f32_avx2(a:&[f32],b:&[f32]) -> f32 {
+    let n = a.len().min(b.len());
+    let (pa,pb) = (a.as_ptr(),b.as_ptr());
+
+    let mut acc0 = _mm256_setzero_ps();
+    let mut acc1 = _mm256_setzero_ps();
+    let mut i = 0;
+
+    while i + 16 <= n {
+        acc0 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(i)),_mm256_loadu_ps(pb.add(i)),acc0);
+        acc1 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(i + 8)),_mm256_loadu_ps(pb.add(i + 8)),acc1);
+        i += 16;
+    }
+
+    while i + 8 <= n {
+        acc0 = _mm256_fmadd_ps(_mm256_loadu_ps(pa.add(i)),_mm256_loadu_ps(pb.add(i)),acc0);
+        i += 8;
+    }
+
+    let mut lanes = [0f32; 8];
+
+    _mm256_storeu_ps(lanes.as_mut_ptr(),_mm256_add_ps(acc0,acc1));
+
+    let mut sum = lanes.iter().sum::<f32>();
+
+    while i < n {
+        sum += a[i] * b[i];
+        i += 1;
+    }
+
+    sum
+}
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn dot_f32_sse2(a:&[f32],b:&[f32]) -> f32 {
+    let n = a.len().min(b.len());
+    let (pa,pb) = (a.as_ptr(),b.as_ptr());
+
+    let mut acc = _mm_setzero_ps();
+    let mut i = 0;
+
+    while i + 4 <= n {
+        acc = _mm_add_ps(acc,_mm_mul_ps(_mm_loadu_ps(pa.add(i)),_mm_loadu_ps(pb.add(i))));
+        i += 4;
+    }
+
+    let mut lanes = [0f32; 4];
+
+    _mm_storeu_ps(lanes.as_mut_ptr(),acc);
+
+    let mut sum = lanes.iter().sum::<f32>();
+
+    while i < n {
+        sum += a[i] * b[i];
+        i += 1;
+    }
+
+    sum
+}
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn dot_f64_avx2(a:&[f64],b:&[f64]) -> f64 {
+    let n = a.len().min(b.len());
+    let (pa,pb) = (a.as_ptr(),b.as_ptr());
+
+    let mut acc0 = _mm256_setzero_pd();
+    let mut acc1 = _mm256_setzero_pd();
+    let mut i = 0;
+
+    while i + 8 <= n {
+        acc0 = _mm256_fmadd_pd(_mm256_loadu_pd(pa.add(i)),_mm256_loadu_pd(pb.add(i)),acc0);
+        acc1 = _mm256_fmadd_pd(_mm256_loadu_pd(pa.add(i + 4)),_mm256_loadu_pd(pb.add(i + 4)),acc1);
+        i += 8;
+    }
+
+    while i + 4 <= n {
+        acc0 = _mm256_fmadd_pd(_mm256_loadu_pd(pa.add(i)),_mm256_loadu_pd(pb.add(i)),acc0);
+        i += 4;
+    }
+
+    let mut lanes = [0f64; 4];
+
+    _mm256_storeu_pd(lanes.as_mut_ptr(),_mm256_add_pd(acc0,acc1));
+
+    let mut sum = lanes.iter().sum::<f64>();
+
+    while i < n {
+        sum += a[i] * b[i];
+        i += 1;
+    }
+
+    sum
+}
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn dot_f64_sse2(a:&[f64],b:&[f64]) -> f64 {
+    let n = a.len().min(b.len());
+    let (pa,pb) = (a.as_ptr(),b.as_ptr());
+
+    let mut acc = _mm_setzero_pd();
+    let mut i = 0;
+
+    while i + 2 <= n {
+        acc = _mm_add_pd(acc,_mm_mul_pd(_mm_loadu_pd(pa.add(i)),_mm_loadu_pd(pb.add(i))));
+        i += 2;
+    }
+
+    let mut lanes = [0f64; 2];
+
+    _mm_storeu_pd(lanes.as_mut_ptr(),acc);
+
+    let mut sum = lanes[0] + lanes[1];
+
+    while i < n {
+        sum += a[i] * b[i];
+        i += 1;
+    }
+
+    sum
+}
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn axpy_f32_avx2(alpha:f32,x:&[f32],y:&mut [f32]) {
+    let n = x.len().min(y.len());
+    let (px,py) = (x.as_ptr(),y.as_mut_ptr());
+
+    let va = _mm256_set1_ps(alpha);
+    let mut i = 0;
+
+    while i + 8 <= n {
+        _mm256_storeu_ps(py.add(i),_mm256_fmadd_ps(va,_mm256_loadu_ps(px.add(i)),_mm256_loadu_ps(py.add(i))));
+        i += 8;
+    }
+
+    while i < n {
+        y[i] += alpha * x[i];
+        i += 1;
+    }
+}
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn axpy_f32_sse2(alpha:f32,x:&[f32],y:&mut [f32]) {
+    let n = x.len().min(y.len());
+    let (px,py) = (x.as_ptr(),y.as_mut_ptr());
+
+    let va = _mm_set1_ps(alpha);
+    let mut i = 0;
+
+    while i + 4 <= n {
+        _mm_storeu_ps(py.add(i),_mm_add_ps(_mm_mul_ps(va,_mm_loadu_ps(px.add(i))),_mm_loadu_ps(py.add(i))));
+        i += 4;
+    }
+
+    while i < n {
+        y[i] += alpha * x[i];
+        i += 1;
+    }
+}
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn axpy_f64_avx2(alpha:f64,x:&[f64],y:&mut [f64]) {
+    let n = x.len().min(y.len());
+    let (px,py) = (x.as_ptr(),y.as_mut_ptr());
+
+    let va = _mm256_set1_pd(alpha);
+    let mut i = 0;
+
+    while i + 4 <= n {
+        _mm256_storeu_pd(py.add(i),_mm256_fmadd_pd(va,_mm256_loadu_pd(px.add(i)),_mm256_loadu_pd(py.add(i))));
+        i += 4;
+    }
+
+    while i < n {
+        y[i] += alpha * x[i];
+        i += 1;
+    }
+}
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn axpy_f64_sse2(alpha:f64,x:&[f64],y:&mut [f64]) {
+    let n = x.len().min(y.len());
+    let (px,py) = (x.as_ptr(),y.as_mut_ptr());
+
+    let va = _mm_set1_pd(alpha);
+    let mut i = 0;
+
+    while i + 2 <= n {
+        _mm_storeu_pd(py.add(i),_mm_add_pd(_mm_mul_pd(va,_mm_loadu_pd(px.add(i))),_mm_loadu_pd(py.add(i))));
+        i += 2;
+    }
+
+    while i < n {
+        y[i] += alpha * x[i];
+        i += 1;
+    }
+}
+/// Matrix product c = a * b, where a is m x k, b is k x n and c is m x n, all row-major
+///
+/// The rows of c are computed in parallel with the specified instruction set.
+/// # Arguments
+/// * `level` - Instruction set
+/// * `m` - Number of rows of a and c
+/// * `n` - Number of columns of b and c
+/// * `k` - Number of columns of a and rows of b
+/// * `a` - Left matrix
+/// * `b` - Right matrix
+/// * `c` - Matrix to which the result is written
+///
+/// # Panics
+///
+/// Panics if the instruction set is not available on the running CPU or a slice is too short
+pub fn gemm_with<T: SimdFloat + Default>(level:SimdLevel,m:usize,n:usize,k:usize,a:&[T],b:&[T],c:&mut [T]) {
+    verify_level(level);
+
+    if a.len() < m * k || b.len() < k * n || c.len() < m * n {
+        panic!("The size of the matrices does not match. (m = {}, n = {}, k = {})",m,n,k);
+    }
+
+    if n == 0 {
+        return;
+    }
+
+    c[..m * n].par_chunks_mut(n).enumerate().for_each(|(i,row)| gemm_row(level,i,n,k,a,b,row));
+}
+/// Matrix product c = a * b with the best instruction set available
+///
+/// # Panics
+///
+/// Panics if a slice is too short
+pub fn gemm<T: SimdFloat + Default>(m:usize,n:usize,k:usize,a:&[T],b:&[T],c:&mut [T]) {
+    gemm_with(detect(),m,n,k,a,b,c)
+}
+/// Matrix product c = a * b with the best instruction set available, computed on the calling thread
+///
+/// # Panics
+///
+/// Panics if a slice is too short
+pub fn gemm_sequential<T: SimdFloat + Default>(m:usize,n:usize,k:usize,a:&[T],b:&[T],c:&mut [T]) {
+    let level = detect();
+
+    if a.len() < m * k || b.len() < k * n || c.len() < m * n {
+        panic!("The size of the matrices does not match. (m = {}, n = {}, k = {})",m,n,k);
+    }
+
+    if n == 0 {
+        return;
+    }
+
+    c[..m * n].chunks_mut(n).enumerate().for_each(|(i,row)| gemm_row(level,i,n,k,a,b,row));
+}
+/// Compute the row i of c = a * b
+#[inline]
+pub(crate) fn gemm_row<T: SimdFloat + Default>(level:SimdLevel,i:usize,n:usize,k:usize,a:&[T],b:&[T],row:&mut [T]) {
+    for v in row.iter_mut() {
+        *v = T::default();
+    }
+
+    for p in 0..k {
+        T::axpy_with(level,a[i * k + p],&b[p * n..(p + 1) * n],row);
+    }
+}
+/// Reinterpret a slice of U as a slice of T if U and T are the same type
+pub(crate) fn cast_slice<U: 'static,T: 'static>(s:&[U]) -> Option<&[T]> {
+    if TypeId::of::<U>() == TypeId::of::<T>() {
+        // The types are identical, so the layout is identical
+        Some(unsafe { slice::from_raw_parts(s.as_ptr() as *const T,s.len()) })
+    } else {
+        None
+    }
+}
+/// Reinterpret a mutable slice of U as a mutable slice of T if U and T are the same type
+pub(crate) fn cast_slice_mut<U: 'static,T: 'static>(s:&mut [U]) -> Option<&mut [T]> {
+    if TypeId::of::<U>() == TypeId::of::<T>() {
+        // The types are identical, so the layout is identical
+        Some(unsafe { slice::from_raw_parts_mut(s.as_mut_ptr() as *mut T,s.len()) })
+    } else {
+        None
+    }
+}
+#[cfg(test)]
+mod tests {
+    use crate::device::simd::{gemm_with, SimdFloat, SimdLevel};
+
+    /// Deterministic pseudo-random values in [-1,1)
+    fn values<T: From<f32>>(len:usize,seed:u32) -> Vec<T> {
+        let mut state = seed;
+
+        (0..len).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+
+            T::from((state as f32 / u32::MAX as f32) * 2. - 1.)
+        }).collect()
+    }
+
+    /// Difference relative to the magnitude, compared absolutely below 1
+    fn relative<T: Into<f64>>(a:T,b:T) -> f64 {
+        let (a,b):(f64,f64) = (a.into(),b.into());
+
+        (a - b).abs() / a.abs().max(b.abs()).max(1.)
+    }
+
+    /// Compare dot_with, axpy_with and gemm_with of every available instruction set with the scalar code
+    ///
+    /// The lengths cover the vectorized loops as well as the remainders handled by scalar code.
+    /// Results are not bit-identical because the order of summation and fused multiply-add differ between instruction sets.
+    fn assert_paths_match_scalar<T: SimdFloat + Default + Into<f64> + From<f32>>(tolerance:f64) {
+        let a = values::<T>(37 * 29,0x12345678);
+        let b = values::<T>(29 * 19,0x9abcdef0);
+        let alpha = T::from(0.75);
+
+        let mut expected_gemm = vec![T::default(); 37 * 19];
+
+        gemm_with(SimdLevel::Scalar,37,19,29,&a,&b,&mut expected_gemm);
+
+        for level in SimdLevel::available() {
+            for len in 0..b.len().min(67) {
+                let expected = T::dot_with(SimdLevel::Scalar,&a[..len],&b[..len]);
+                let actual = T::dot_with(level,&a[..len],&b[..len]);
+
+                assert!(relative(expected,actual) <= tolerance,"dot of {} values with {:?}",len,level);
+
+                let mut expected = b[..len].to_vec();
+                let mut actual = b[..len].to_vec();
+
+                T::axpy_with(SimdLevel::Scalar,alpha,&a[..len],&mut expected);
+                T::axpy_with(level,alpha,&a[..len],&mut actual);
+
+                for (&e,&a) in expected.iter().zip(actual.iter()) {
+                    assert!(relative(e,a) <= tolerance,"axpy of {} values with {:?}",len,level);
+                }
+            }
+
+            let len = a.len().min(b.len());
+
+            assert!(relative(T::dot_with(SimdLevel::Scalar,&a[..len],&b[..len]),T::dot_with(level,&a[..len],&b[..len])) <= tolerance * 10.,
+                    "dot of {} values with {:?}",len,level);
+
+            let mut actual = vec![T::default(); 37 * 19];
+
+            gemm_with(level,37,19,29,&a,&b,&mut actual);
+
+            for (&e,&a) in expected_gemm.iter().zip(actual.iter()) {
+                assert!(relative(e,a) <= tolerance,"gemm with {:?}",level);
+            }
+        }
+    }
+
+    #[test]
+    fn test_f32_paths_match_scalar() {
+        assert_paths_match_scalar::<f32>(1e-5);
+    }
+
+    #[test]
+    fn test_f64_paths_match_scalar() {
+        assert_paths_match_scalar::<f64>(1e-12);
+    }
+
+    #[test]
+    fn test_scalar_is_always_available() {
+        assert!(SimdLevel::available().contains(&SimdLevel::Scalar));
+    }
+}
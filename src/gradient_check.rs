@@ -0,0 +1,329 @@
+use std::error::Error;
+use std::fmt;
+use num_traits::{FromPrimitive, ToPrimitive};
+use nncombinator::arr::Arr4;
+use nncombinator::error::{EvaluateError, TrainingError};
+use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+use nncombinator::ope::UnitValue;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use crate::collection::Images;
+use crate::device::DeviceConvolution;
+
+/// Error raised while checking a convolution implementation
+#[derive(Debug)]
+pub enum GradientCheckError {
+    /// Error of forward propagation
+    EvaluateError(EvaluateError),
+    /// Error of back propagation
+    TrainingError(TrainingError),
+}
+impl fmt::Display for GradientCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GradientCheckError::EvaluateError(e) => write!(f, "An error occurred in forward propagation. ({})", e),
+            GradientCheckError::TrainingError(e) => write!(f, "An error occurred in back propagation. ({})", e),
+        }
+    }
+}
+impl Error for GradientCheckError {}
+impl From<EvaluateError> for GradientCheckError {
+    fn from(err: EvaluateError) -> GradientCheckError {
+        GradientCheckError::EvaluateError(err)
+    }
+}
+impl From<TrainingError> for GradientCheckError {
+    fn from(err: TrainingError) -> GradientCheckError {
+        GradientCheckError::TrainingError(err)
+    }
+}
+/// Comparison of a numerical gradient and an analytic gradient
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct GradientComparison {
+    /// Maximum relative error
+    pub max_relative_error:f64,
+    /// Maximum absolute error
+    pub max_absolute_error:f64,
+    /// Position of the value with the maximum relative error in the raw slice
+    pub worst_index:usize,
+    /// Numerical gradient at the worst position
+    pub numerical:f64,
+    /// Analytic gradient at the worst position
+    pub analytic:f64,
+}
+impl GradientComparison {
+    fn new() -> GradientComparison {
+        GradientComparison {
+            max_relative_error:0.,
+            max_absolute_error:0.,
+            worst_index:0,
+            numerical:0.,
+            analytic:0.
+        }
+    }
+
+    /// Relative error |a - n| / max(|a|,|n|), with small values compared absolutely
+    fn add(&mut self,index:usize,numerical:f64,analytic:f64) {
+        let absolute = (numerical - analytic).abs();
+        let relative = absolute / numerical.abs().max(analytic.abs()).max(1e-8);
+        let relative = if numerical.abs().max(analytic.abs()) < 1e-6 { absolute } else { relative };
+
+        self.max_absolute_error = self.max_absolute_error.max(absolute);
+
+        if relative > self.max_relative_error || relative.is_nan() {
+            self.max_relative_error = relative;
+            self.worst_index = index;
+            self.numerical = numerical;
+            self.analytic = analytic;
+        }
+    }
+}
+impl fmt::Display for GradientComparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "max relative error {:e}, max absolute error {:e} (index {}: numerical {}, analytic {})",
+               self.max_relative_error, self.max_absolute_error, self.worst_index, self.numerical, self.analytic)
+    }
+}
+/// Result of checking the gradients of a convolution
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct GradientCheckReport {
+    /// Comparison of the gradient of the input (backward_convolution)
+    pub input:GradientComparison,
+    /// Comparison of the gradient of the kernel (backward_weight_gradient_convolution)
+    pub kernel:GradientComparison,
+}
+impl GradientCheckReport {
+    /// Returns true if the relative errors of both gradients are within the tolerance
+    /// # Arguments
+    /// * `tolerance` - Maximum allowed relative error
+    pub fn passed(&self,tolerance:f64) -> bool {
+        self.input.max_relative_error <= tolerance && self.kernel.max_relative_error <= tolerance
+    }
+}
+impl fmt::Display for GradientCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "input: {}, kernel: {}", self.input, self.kernel)
+    }
+}
+fn to_f64<U>(v:U) -> f64 where U: UnitValue<U> + ToPrimitive {
+    <U as ToPrimitive>::to_f64(&v).expect("Error in type conversion to f64.")
+}
+fn from_f64<U>(v:f64) -> U where U: UnitValue<U> + FromPrimitive {
+    <U as FromPrimitive>::from_f64(v).expect("Error in type conversion from f64.")
+}
+/// Scalar objective sum(forward(input,kernel) * loss), whose gradients are the back propagation of loss
+fn objective<U,D,const C:usize,const K:usize,const H:usize,const W:usize,
+             const FH:usize,const FW:usize,const PAD:usize,const S:usize>(
+    device:&D,input:&Images<U,C,H,W>,kernel:&Arr4<U,K,C,FH,FW>,
+    loss:&Images<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>)
+    -> Result<f64,GradientCheckError>
+    where U: UnitValue<U> + ToPrimitive,
+          D: DeviceConvolution<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S>,
+          [(); ( H + 2 * PAD - FH ) / S + 1]:,
+          [(); ( W + 2 * PAD - FW ) / S + 1]: {
+    let output = device.forward_convolution::<C>(input,kernel)?;
+
+    Ok(output.as_raw_slice().iter().zip(loss.as_raw_slice().iter()).map(|(&o,&l)| to_f64(o) * to_f64(l)).sum())
+}
+/// Compute the central finite difference of the objective for each value of a raw slice
+fn finite_difference<U,F>(values:&mut [U],epsilon:f64,mut f:F) -> Result<Vec<f64>,GradientCheckError>
+    where U: UnitValue<U> + FromPrimitive + ToPrimitive,
+          F: FnMut(&[U]) -> Result<f64,GradientCheckError> {
+    let mut gradient = Vec::with_capacity(values.len());
+
+    for i in 0..values.len() {
+        let original = values[i];
+
+        let plus:U = from_f64(to_f64(original) + epsilon);
+        let minus:U = from_f64(to_f64(original) - epsilon);
+
+        values[i] = plus;
+        let lp = f(values)?;
+
+        values[i] = minus;
+        let lm = f(values)?;
+
+        values[i] = original;
+
+        // The perturbation actually applied may differ from epsilon after rounding to U
+        gradient.push((lp - lm) / (to_f64(plus) - to_f64(minus)));
+    }
+
+    Ok(gradient)
+}
+/// Compare the gradients of a convolution implementation with central finite differences
+///
+/// The objective is sum(forward_convolution(input,kernel) * loss),
+/// so the analytic gradients are backward_convolution(loss,kernel) and backward_weight_gradient_convolution(loss,input).
+/// # Arguments
+/// * `device` - Convolution implementation to be checked
+/// * `input` - Input values
+/// * `kernel` - filter weights
+/// * `loss` - loss propagated back from the output
+/// * `epsilon` - Perturbation of the finite differences
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`GradientCheckError`]
+pub fn check_convolution_gradient<U,D,const C:usize,const K:usize,const H:usize,const W:usize,
+                                  const FH:usize,const FW:usize,const PAD:usize,const S:usize>(
+    device:&D,input:&Images<U,C,H,W>,kernel:&Arr4<U,K,C,FH,FW>,
+    loss:&Images<U,K,{ ( H + 2 * PAD - FH ) / S + 1 }, { ( W + 2 * PAD - FW ) / S + 1 }>,epsilon:f64)
+    -> Result<GradientCheckReport,GradientCheckError>
+    where U: UnitValue<U> + FromPrimitive + ToPrimitive,
+          D: DeviceConvolution<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S>,
+          [(); ( H + 2 * PAD - FH ) / S + 1]:,
+          [(); ( W + 2 * PAD - FW ) / S + 1]: {
+    let input_gradient = device.backward_convolution(loss,kernel)?;
+    let kernel_gradient = device.backward_weight_gradient_convolution(loss,input)?;
+
+    let mut x = input.clone();
+
+    let numerical = finite_difference(x.as_raw_mut_slice(),epsilon,|values| {
+        let mut x = Images::new();
+
+        x.as_raw_mut_slice().copy_from_slice(values);
+
+        objective(device,&x,kernel,loss)
+    })?;
+
+    let mut report = GradientCheckReport {
+        input:GradientComparison::new(),
+        kernel:GradientComparison::new()
+    };
+
+    for (i,(&n,&a)) in numerical.iter().zip(input_gradient.as_raw_slice().iter()).enumerate() {
+        report.input.add(i,n,to_f64(a));
+    }
+
+    let mut k = kernel.clone();
+
+    let numerical = finite_difference(k.as_raw_mut_slice(),epsilon,|values| {
+        let mut k = Arr4::new();
+
+        k.as_raw_mut_slice().copy_from_slice(values);
+
+        objective(device,input,&k,loss)
+    })?;
+
+    for (i,(&n,&a)) in numerical.iter().zip(kernel_gradient.as_raw_slice().iter()).enumerate() {
+        report.kernel.add(i,n,to_f64(a));
+    }
+
+    Ok(report)
+}
+/// Check the gradients of a convolution implementation with random input, kernel and loss drawn from [-1,1)
+/// # Arguments
+/// * `device` - Convolution implementation to be checked
+/// * `seed` - Seed of the random number generator
+/// * `epsilon` - Perturbation of the finite differences
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`GradientCheckError`]
+pub fn check_random_convolution_gradient<U,D,const C:usize,const K:usize,const H:usize,const W:usize,
+                                         const FH:usize,const FW:usize,const PAD:usize,const S:usize>(
+    device:&D,seed:u64,epsilon:f64) -> Result<GradientCheckReport,GradientCheckError>
+    where U: UnitValue<U> + FromPrimitive + ToPrimitive,
+          D: DeviceConvolution<U,Arr4<U,K,C,FH,FW>,C,K,H,W,FH,FW,PAD,S>,
+          [(); ( H + 2 * PAD - FH ) / S + 1]:,
+          [(); ( W + 2 * PAD - FW ) / S + 1]: {
+    let mut rnd = XorShiftRng::seed_from_u64(seed);
+
+    let mut input = Images::new();
+    let mut kernel = Arr4::new();
+    let mut loss = Images::new();
+
+    for v in input.as_raw_mut_slice().iter_mut() {
+        *v = from_f64(rnd.gen_range(-1.0..1.0));
+    }
+
+    for v in kernel.as_raw_mut_slice().iter_mut() {
+        *v = from_f64(rnd.gen_range(-1.0..1.0));
+    }
+
+    for v in loss.as_raw_mut_slice().iter_mut() {
+        *v = from_f64(rnd.gen_range(-1.0..1.0));
+    }
+
+    check_convolution_gradient(device,&input,&kernel,&loss,epsilon)
+}
+/// Check the gradients of a convolution implementation for each of the listed shapes
+/// with random values, and panic with the reports of the shapes whose relative error exceeds the tolerance.
+///
+/// Each shape is given as (C,K,H,W,FH,FW,PAD,S).
+///
+/// ```
+/// use nncombinator_cnn::check_convolution_gradients;
+/// use nncombinator_cnn::device::reference::DeviceReference;
+///
+/// check_convolution_gradients!(DeviceReference::<f64>::new(), f64, 1e-6, 1e-5;
+///     (1,1,5,5,3,3,0,1),
+///     (2,3,6,6,3,3,1,1),
+///     (3,2,7,7,3,3,1,2),
+/// );
+/// ```
+#[macro_export]
+macro_rules! check_convolution_gradients {
+    ($device:expr, $u:ty, $epsilon:expr, $tolerance:expr;
+     $(($c:literal,$k:literal,$h:literal,$w:literal,$fh:literal,$fw:literal,$pad:literal,$s:literal)),+ $(,)?) => {{
+        let device = $device;
+        let mut failures:Vec<String> = Vec::new();
+        let mut seed:u64 = 0;
+
+        $(
+            seed += 1;
+
+            let shape = format!("(C,K,H,W,FH,FW,PAD,S) = ({},{},{},{},{},{},{},{})",$c,$k,$h,$w,$fh,$fw,$pad,$s);
+
+            match $crate::gradient_check::check_random_convolution_gradient::<$u,_,$c,$k,$h,$w,$fh,$fw,$pad,$s>(&device,seed,$epsilon) {
+                Ok(report) if report.passed($tolerance) => (),
+                Ok(report) => failures.push(format!("{}: {}",shape,report)),
+                Err(e) => failures.push(format!("{}: {}",shape,e)),
+            }
+        )+
+
+        if !failures.is_empty() {
+            panic!("The gradient check failed.\n{}",failures.join("\n"));
+        }
+    }};
+}
+#[cfg(test)]
+mod tests {
+    use crate::device::cpu::{DeviceCpuConfig, DeviceCpuConfigured, Summation};
+    use crate::device::reference::DeviceReference;
+
+    #[test]
+    fn test_reference_gradients() {
+        check_convolution_gradients!(DeviceReference::<f64>::new(), f64, 1e-6, 1e-5;
+            (1,1,5,5,3,3,0,1),
+            (2,3,6,6,3,3,1,1),
+            (3,2,7,7,3,3,1,2),
+            (2,2,8,6,2,4,0,2),
+            (1,2,4,4,5,5,2,1),
+        );
+    }
+
+    #[test]
+    fn test_configured_gradients() {
+        check_convolution_gradients!(DeviceCpuConfigured::<f64>::new(DeviceCpuConfig::deterministic()), f64, 1e-6, 1e-5;
+            (1,1,5,5,3,3,0,1),
+            (2,3,6,6,3,3,1,1),
+            (3,2,7,7,3,3,1,2),
+            (2,2,8,6,2,4,0,2),
+        );
+
+        check_convolution_gradients!(DeviceCpuConfigured::<f64>::new(DeviceCpuConfig::default().with_sequential_cutoff(0)), f64, 1e-6, 1e-5;
+            (2,3,6,6,3,3,1,1),
+            (3,2,7,7,3,3,1,2),
+        );
+
+        check_convolution_gradients!(DeviceCpuConfigured::<f64>::new(DeviceCpuConfig::deterministic().with_summation(Summation::Kahan)), f64, 1e-6, 1e-5;
+            (2,3,6,6,3,3,1,1),
+            (3,2,7,7,3,3,1,2),
+        );
+    }
+}
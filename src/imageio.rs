@@ -0,0 +1,645 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use num_traits::ToPrimitive;
+use nncombinator::arr::Arr4;
+use nncombinator::error::{ConfigReadError, PersistenceError};
+use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+use nncombinator::ope::UnitValue;
+
+use crate::collection::Images;
+
+/// How to fit an image whose size differs from the const H/W
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Resize {
+    /// The size of the image must match, otherwise an error is returned
+    Exact,
+    /// Resize with nearest neighbor sampling
+    Nearest,
+    /// Resize with bilinear sampling
+    Bilinear,
+}
+/// File format of the image
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ImageFormat {
+    /// PGM (1 channel) or PPM (3 channels) in binary form
+    Pnm,
+    /// PNG
+    Png,
+}
+/// Image decoded from a file, with interleaved 8-bit channels
+struct Raster {
+    width:usize,
+    height:usize,
+    channels:usize,
+    data:Vec<u8>,
+}
+impl Raster {
+    /// Convert the number of channels to 1 (luminance) or 3 (replicating gray)
+    fn to_channels(self,channels:usize) -> Raster {
+        if self.channels == channels {
+            return self;
+        }
+
+        let data = self.data.chunks(self.channels).flat_map(|p| {
+            let (r,g,b) = if self.channels >= 3 {
+                (p[0] as f64,p[1] as f64,p[2] as f64)
+            } else {
+                (p[0] as f64,p[0] as f64,p[0] as f64)
+            };
+
+            if channels == 1 {
+                vec![(0.299 * r + 0.587 * g + 0.114 * b).round().min(255.) as u8]
+            } else {
+                vec![r as u8,g as u8,b as u8]
+            }
+        }).collect::<Vec<u8>>();
+
+        Raster {
+            width:self.width,
+            height:self.height,
+            channels:channels,
+            data:data
+        }
+    }
+
+    /// Sample a channel at the specified position of the resized image
+    fn sample(&self,c:usize,y:usize,x:usize,h:usize,w:usize,resize:Resize) -> u8 {
+        let sy = (y as f64 + 0.5) * self.height as f64 / h as f64 - 0.5;
+        let sx = (x as f64 + 0.5) * self.width as f64 / w as f64 - 0.5;
+
+        let pixel = |y:usize,x:usize| self.data[(y * self.width + x) * self.channels + c] as f64;
+
+        match resize {
+            Resize::Bilinear => {
+                let sy = sy.max(0.).min((self.height - 1) as f64);
+                let sx = sx.max(0.).min((self.width - 1) as f64);
+
+                let y0 = sy.floor() as usize;
+                let x0 = sx.floor() as usize;
+                let y1 = (y0 + 1).min(self.height - 1);
+                let x1 = (x0 + 1).min(self.width - 1);
+
+                let dy = sy - y0 as f64;
+                let dx = sx - x0 as f64;
+
+                let v = pixel(y0,x0) * (1. - dy) * (1. - dx) +
+                        pixel(y0,x1) * (1. - dy) * dx +
+                        pixel(y1,x0) * dy * (1. - dx) +
+                        pixel(y1,x1) * dy * dx;
+
+                v.round().max(0.).min(255.) as u8
+            },
+            _ => {
+                let y = (sy.round().max(0.) as usize).min(self.height - 1);
+                let x = (sx.round().max(0.) as usize).min(self.width - 1);
+
+                pixel(y,x) as u8
+            }
+        }
+    }
+
+    /// Convert to Images, resizing as specified
+    fn into_images<const C:usize,const H:usize,const W:usize>(self,resize:Resize,name:&str) -> Result<Images<u8,C,H,W>,ConfigReadError> {
+        verify_channels(C).map_err(ConfigReadError::InvalidState)?;
+
+        let raster = self.to_channels(C);
+
+        if resize == Resize::Exact && (raster.width != W || raster.height != H) {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The size of the image {} does not match. (expected {}x{}, actual {}x{})",name,W,H,raster.width,raster.height
+            )));
+        } else if raster.width == 0 || raster.height == 0 {
+            return Err(ConfigReadError::InvalidState(format!("The image {} is empty.",name)));
+        }
+
+        let mut images = Images::new();
+
+        if H == 0 || W == 0 {
+            return Ok(images);
+        }
+
+        for (c,image) in images.as_raw_mut_slice().chunks_mut(H * W).enumerate() {
+            for (y,row) in image.chunks_mut(W).enumerate() {
+                for (x,p) in row.iter_mut().enumerate() {
+                    *p = if raster.width == W && raster.height == H {
+                        raster.data[(y * W + x) * C + c]
+                    } else {
+                        raster.sample(c,y,x,H,W,resize)
+                    };
+                }
+            }
+        }
+
+        Ok(images)
+    }
+}
+/// Verify that the number of channels can be stored in an image file
+fn verify_channels(c:usize) -> Result<(),String> {
+    if c == 1 || c == 3 {
+        Ok(())
+    } else {
+        Err(format!("Images with {} channels cannot be stored in an image file. (1 or 3 channels are supported)",c))
+    }
+}
+/// Convert planar channels into interleaved pixels
+fn interleave(data:&[u8],c:usize,h:usize,w:usize) -> Vec<u8> {
+    let mut buffer = vec![0u8; c * h * w];
+
+    for ch in 0..c {
+        for i in 0..h * w {
+            buffer[i * c + ch] = data[ch * h * w + i];
+        }
+    }
+
+    buffer
+}
+/// Read the next token of the header of a Netpbm file, skipping comments
+fn pnm_token<R: Read>(reader:&mut R,name:&str) -> Result<String,ConfigReadError> {
+    let mut token = String::new();
+    let mut byte = [0u8; 1];
+    let mut comment = false;
+
+    loop {
+        if reader.read(&mut byte)? == 0 {
+            if token.is_empty() {
+                return Err(ConfigReadError::InvalidState(format!("The header of the file {} is truncated.",name)));
+            } else {
+                return Ok(token);
+            }
+        }
+
+        let c = byte[0] as char;
+
+        if comment {
+            comment = c != '\n';
+        } else if c == '#' {
+            comment = true;
+        } else if c.is_ascii_whitespace() {
+            if !token.is_empty() {
+                return Ok(token);
+            }
+        } else {
+            token.push(c);
+        }
+    }
+}
+fn pnm_number<R: Read>(reader:&mut R,name:&str) -> Result<usize,ConfigReadError> {
+    let token = pnm_token(reader,name)?;
+
+    token.parse::<usize>().map_err(|_| {
+        ConfigReadError::InvalidState(format!("Invalid number {} in the file {}.",token,name))
+    })
+}
+/// Decode a PGM or PPM file
+fn read_pnm_raster<P: AsRef<Path>>(file:P) -> Result<Raster,ConfigReadError> {
+    let name = file.as_ref().display().to_string();
+
+    let mut reader = BufReader::new(File::open(file)?);
+
+    let magic = pnm_token(&mut reader,&name)?;
+
+    let (channels,binary) = match magic.as_str() {
+        "P2" => (1,false),
+        "P3" => (3,false),
+        "P5" => (1,true),
+        "P6" => (3,true),
+        _ => {
+            return Err(ConfigReadError::InvalidState(format!("The file {} is not a PGM or PPM file. ({})",name,magic)));
+        }
+    };
+
+    let width = pnm_number(&mut reader,&name)?;
+    let height = pnm_number(&mut reader,&name)?;
+    let max = pnm_number(&mut reader,&name)?;
+
+    if max == 0 || max > 255 {
+        return Err(ConfigReadError::InvalidState(format!(
+            "The maximum value {} of the file {} is not supported. (1 to 255 are supported)",max,name
+        )));
+    }
+
+    let size = width.checked_mul(height).and_then(|s| s.checked_mul(channels)).ok_or_else(|| {
+        ConfigReadError::InvalidState(format!("The size {}x{} of the file {} is too large.",width,height,name))
+    })?;
+
+    // The size comes from the header, so the buffer grows with the data actually read instead of being allocated up front
+    let mut data = Vec::new();
+
+    if binary {
+        (&mut reader).take(size as u64).read_to_end(&mut data)?;
+
+        if data.len() < size {
+            return Err(ConfigReadError::InvalidState(format!("The file {} is truncated.",name)));
+        }
+    } else {
+        for _ in 0..size {
+            data.push(pnm_number(&mut reader,&name)?.min(max) as u8);
+        }
+    }
+
+    if max != 255 {
+        for p in data.iter_mut() {
+            *p = ((*p as usize * 255 + max / 2) / max) as u8;
+        }
+    }
+
+    Ok(Raster {
+        width:width,
+        height:height,
+        channels:channels,
+        data:data
+    })
+}
+/// Decode a PNG file
+fn read_png_raster<P: AsRef<Path>>(file:P) -> Result<Raster,ConfigReadError> {
+    let name = file.as_ref().display().to_string();
+
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(file)?));
+
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let mut reader = decoder.read_info().map_err(|e| {
+        ConfigReadError::InvalidState(format!("The file {} could not be decoded as PNG. ({})",name,e))
+    })?;
+
+    let mut buffer = vec![0u8; reader.output_buffer_size()];
+
+    let info = reader.next_frame(&mut buffer).map_err(|e| {
+        ConfigReadError::InvalidState(format!("The file {} could not be decoded as PNG. ({})",name,e))
+    })?;
+
+    buffer.truncate(info.buffer_size());
+
+    let (channels,alpha) = match info.color_type {
+        png::ColorType::Grayscale => (1,false),
+        png::ColorType::GrayscaleAlpha => (1,true),
+        png::ColorType::Rgb => (3,false),
+        png::ColorType::Rgba => (3,true),
+        png::ColorType::Indexed => {
+            return Err(ConfigReadError::InvalidState(format!("The palette of the file {} could not be expanded.",name)));
+        }
+    };
+
+    let data = if alpha {
+        buffer.chunks(channels + 1).flat_map(|p| p[..channels].iter().cloned()).collect()
+    } else {
+        buffer
+    };
+
+    Ok(Raster {
+        width:info.width as usize,
+        height:info.height as usize,
+        channels:channels,
+        data:data
+    })
+}
+/// Read Images from a PGM or PPM file
+/// # Arguments
+/// * `file` - Path of the file to be read
+/// * `resize` - How to fit an image whose size differs from H/W
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`ConfigReadError`]
+pub fn read_pnm<P: AsRef<Path>,const C:usize,const H:usize,const W:usize>(file:P,resize:Resize) -> Result<Images<u8,C,H,W>,ConfigReadError> {
+    let name = file.as_ref().display().to_string();
+
+    read_pnm_raster(file)?.into_images(resize,&name)
+}
+/// Read Images from a PNG file
+/// # Arguments
+/// * `file` - Path of the file to be read
+/// * `resize` - How to fit an image whose size differs from H/W
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`ConfigReadError`]
+pub fn read_png<P: AsRef<Path>,const C:usize,const H:usize,const W:usize>(file:P,resize:Resize) -> Result<Images<u8,C,H,W>,ConfigReadError> {
+    let name = file.as_ref().display().to_string();
+
+    read_png_raster(file)?.into_images(resize,&name)
+}
+/// Read Images from an image file, determining the format from the extension
+/// # Arguments
+/// * `file` - Path of the file to be read
+/// * `resize` - How to fit an image whose size differs from H/W
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`ConfigReadError`]
+pub fn read_image<P: AsRef<Path>,const C:usize,const H:usize,const W:usize>(file:P,resize:Resize) -> Result<Images<u8,C,H,W>,ConfigReadError> {
+    let png = file.as_ref().extension().map(|e| e.eq_ignore_ascii_case("png")).unwrap_or(false);
+
+    if png {
+        read_png(file,resize)
+    } else {
+        read_pnm(file,resize)
+    }
+}
+fn to_persistence_error(message:String) -> PersistenceError {
+    PersistenceError::IOError(io::Error::new(io::ErrorKind::InvalidInput,message))
+}
+/// Write interleaved 8-bit pixels to a file
+fn write_raster<P: AsRef<Path>>(file:P,format:ImageFormat,width:usize,height:usize,channels:usize,data:&[u8]) -> Result<(),PersistenceError> {
+    verify_channels(channels).map_err(to_persistence_error)?;
+
+    let mut writer = BufWriter::new(File::create(file)?);
+
+    match format {
+        ImageFormat::Pnm => {
+            write!(writer,"{}\n{} {}\n255\n",if channels == 1 { "P5" } else { "P6" },width,height)?;
+            writer.write_all(data)?;
+        },
+        ImageFormat::Png => {
+            let mut encoder = png::Encoder::new(&mut writer,width as u32,height as u32);
+
+            encoder.set_color(if channels == 1 { png::ColorType::Grayscale } else { png::ColorType::Rgb });
+            encoder.set_depth(png::BitDepth::Eight);
+
+            let mut png = encoder.write_header().map_err(|e| to_persistence_error(e.to_string()))?;
+
+            png.write_image_data(data).map_err(|e| to_persistence_error(e.to_string()))?;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+/// Write Images of 1 or 3 channels to an image file
+/// # Arguments
+/// * `file` - Path of the file to be written
+/// * `format` - File format
+/// * `images` - Images to be written
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`PersistenceError`]
+pub fn write_image<P: AsRef<Path>,const C:usize,const H:usize,const W:usize>(file:P,format:ImageFormat,images:&Images<u8,C,H,W>)
+    -> Result<(),PersistenceError> {
+    write_raster(file,format,W,H,C,&interleave(images.as_raw_slice(),C,H,W))
+}
+/// Scale the values of a tile to 0..255 by its minimum and maximum
+fn tile_to_u8<U>(values:&[U]) -> Vec<u8> where U: UnitValue<U> + ToPrimitive {
+    let values = values.iter().map(|v| <U as ToPrimitive>::to_f64(v).unwrap_or(0.)).collect::<Vec<f64>>();
+
+    let min = values.iter().cloned().fold(f64::INFINITY,f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY,f64::max);
+
+    values.iter().map(|&v| {
+        if max > min {
+            ((v - min) / (max - min) * 255.).round() as u8
+        } else {
+            0
+        }
+    }).collect()
+}
+/// Arrange tiles of the same size into a grayscale image with a margin of one pixel between them
+fn arrange_tiles(tiles:&[Vec<u8>],h:usize,w:usize,columns:usize) -> (usize,usize,Vec<u8>) {
+    let columns = columns.max(1).min(tiles.len().max(1));
+    let rows = (tiles.len() + columns - 1) / columns;
+
+    let width = columns * (w + 1) - 1;
+    let height = (rows * (h + 1)).max(1) - 1;
+
+    let mut buffer = vec![0u8; width * height];
+
+    for (i,tile) in tiles.iter().enumerate() {
+        let oy = (i / columns) * (h + 1);
+        let ox = (i % columns) * (w + 1);
+
+        for y in 0..h {
+            buffer[(oy + y) * width + ox..(oy + y) * width + ox + w].copy_from_slice(&tile[y * w..(y + 1) * w]);
+        }
+    }
+
+    (width,height,buffer)
+}
+/// Write each channel of a feature map as a grayscale tile, scaled by its minimum and maximum
+/// # Arguments
+/// * `file` - Path of the file to be written
+/// * `format` - File format
+/// * `images` - Feature map to be written
+/// * `columns` - Number of tiles per row
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`PersistenceError`]
+pub fn write_feature_map_tiles<U,P: AsRef<Path>,const C:usize,const H:usize,const W:usize>(file:P,format:ImageFormat,
+                                                                                           images:&Images<U,C,H,W>,columns:usize)
+    -> Result<(),PersistenceError> where U: UnitValue<U> + ToPrimitive {
+    if H == 0 || W == 0 {
+        return Err(to_persistence_error(format!("The feature map of size {}x{} is empty.",W,H)));
+    }
+
+    let tiles = images.as_raw_slice().chunks(H * W).map(tile_to_u8).collect::<Vec<Vec<u8>>>();
+
+    let (width,height,data) = arrange_tiles(&tiles,H,W,columns);
+
+    write_raster(file,format,width,height,1,&data)
+}
+/// Write a convolution kernel as grayscale tiles, one row per output channel and one column per input channel
+/// # Arguments
+/// * `file` - Path of the file to be written
+/// * `format` - File format
+/// * `kernel` - filter weights
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`PersistenceError`]
+pub fn write_kernel_tiles<U,P: AsRef<Path>,const K:usize,const C:usize,const FH:usize,const FW:usize>(file:P,format:ImageFormat,
+                                                                                                      kernel:&Arr4<U,K,C,FH,FW>)
+    -> Result<(),PersistenceError> where U: UnitValue<U> + ToPrimitive {
+    if FH == 0 || FW == 0 {
+        return Err(to_persistence_error(format!("The kernel of size {}x{} is empty.",FW,FH)));
+    }
+
+    let tiles = kernel.as_raw_slice().chunks(FH * FW).map(tile_to_u8).collect::<Vec<Vec<u8>>>();
+
+    let (width,height,data) = arrange_tiles(&tiles,FH,FW,C);
+
+    write_raster(file,format,width,height,1,&data)
+}
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use nncombinator::arr::Arr4;
+    use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+
+    use crate::collection::Images;
+    use crate::imageio::{read_image, read_png, read_pnm, write_feature_map_tiles, write_image, write_kernel_tiles, ImageFormat, Resize};
+
+    fn file(name:&str) -> PathBuf {
+        std::env::temp_dir().join(format!("nncombinator-convolution-{}-{}",name,std::process::id()))
+    }
+
+    fn image<const C:usize,const H:usize,const W:usize>() -> Images<u8,C,H,W> {
+        let mut image = Images::new();
+
+        for (i,p) in image.as_raw_mut_slice().iter_mut().enumerate() {
+            *p = (i * 37 % 256) as u8;
+        }
+
+        image
+    }
+
+    #[test]
+    fn test_pgm_round_trip() {
+        let file = file("round-trip.pgm");
+        let image = image::<1,3,5>();
+
+        write_image(&file,ImageFormat::Pnm,&image).unwrap();
+
+        let restored = read_image::<_,1,3,5>(&file,Resize::Exact);
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(restored.unwrap().as_raw_slice(),image.as_raw_slice());
+    }
+
+    #[test]
+    fn test_ppm_round_trip() {
+        let file = file("round-trip.ppm");
+        let image = image::<3,4,2>();
+
+        write_image(&file,ImageFormat::Pnm,&image).unwrap();
+
+        let restored = read_pnm::<_,3,4,2>(&file,Resize::Exact);
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(restored.unwrap().as_raw_slice(),image.as_raw_slice());
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let file = file("round-trip.png");
+        let gray = image::<1,4,6>();
+        let rgb = image::<3,5,3>();
+
+        write_image(&file,ImageFormat::Png,&gray).unwrap();
+
+        let restored_gray = read_image::<_,1,4,6>(&file,Resize::Exact);
+
+        write_image(&file,ImageFormat::Png,&rgb).unwrap();
+
+        let restored_rgb = read_png::<_,3,5,3>(&file,Resize::Exact);
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(restored_gray.unwrap().as_raw_slice(),gray.as_raw_slice());
+        assert_eq!(restored_rgb.unwrap().as_raw_slice(),rgb.as_raw_slice());
+    }
+
+    #[test]
+    fn test_resize() {
+        let file = file("resize.pgm");
+
+        std::fs::write(&file,b"P5\n2 2\n255\n\x00\x40\x80\xc0").unwrap();
+
+        let exact = read_pnm::<_,1,4,4>(&file,Resize::Exact);
+        let nearest = read_pnm::<_,1,4,4>(&file,Resize::Nearest);
+        let bilinear = read_pnm::<_,1,4,4>(&file,Resize::Bilinear);
+        let shrunk = read_pnm::<_,1,1,1>(&file,Resize::Bilinear);
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(exact.is_err());
+
+        assert_eq!(nearest.unwrap().as_raw_slice(),&[
+            0x00,0x00,0x40,0x40,
+            0x00,0x00,0x40,0x40,
+            0x80,0x80,0xc0,0xc0,
+            0x80,0x80,0xc0,0xc0
+        ]);
+
+        let bilinear = bilinear.unwrap();
+
+        assert_eq!(bilinear.as_raw_slice()[0],0x00);
+        assert_eq!(bilinear.as_raw_slice()[15],0xc0);
+        assert_eq!(bilinear.as_raw_slice()[1],0x10);
+        assert_eq!(bilinear.as_raw_slice()[4],0x20);
+
+        assert_eq!(shrunk.unwrap().as_raw_slice(),&[0x60]);
+    }
+
+    #[test]
+    fn test_ascii_pnm_with_comment_and_max() {
+        let file = file("ascii.pgm");
+
+        std::fs::write(&file,b"P2\n# comment\n2 1\n15\n0 15\n").unwrap();
+
+        let image = read_pnm::<_,1,1,2>(&file,Resize::Exact);
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(image.unwrap().as_raw_slice(),&[0,255]);
+    }
+
+    #[test]
+    fn test_pnm_rejects_truncated_files() {
+        let cases:[(&str,&[u8]);5] = [
+            ("truncated-magic.pgm",b""),
+            ("truncated-size.pgm",b"P5\n2"),
+            ("truncated-max.pgm",b"P5\n2 2\n"),
+            ("truncated-data.pgm",b"P5\n2 2\n255\n\x00\x01\x02"),
+            ("truncated-ascii.pgm",b"P2\n2 2\n255\n0 1 2")
+        ];
+
+        for (name,bytes) in cases.iter() {
+            let file = file(name);
+
+            std::fs::write(&file,bytes).unwrap();
+
+            let image = read_pnm::<_,1,2,2>(&file,Resize::Exact);
+
+            std::fs::remove_file(&file).unwrap();
+
+            assert!(image.is_err(),"{}",name);
+        }
+    }
+
+    #[test]
+    fn test_pnm_rejects_oversized_header() {
+        let file = file("oversized.ppm");
+
+        std::fs::write(&file,format!("P6\n{} {}\n255\n\x00",usize::MAX,usize::MAX)).unwrap();
+
+        let overflow = read_pnm::<_,3,2,2>(&file,Resize::Nearest);
+
+        std::fs::write(&file,b"P6\n100000 100000\n255\n\x00\x00\x00").unwrap();
+
+        let truncated = read_pnm::<_,3,2,2>(&file,Resize::Nearest);
+
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(overflow.is_err());
+        assert!(truncated.is_err());
+    }
+
+    #[test]
+    fn test_zero_sized_images() {
+        let file = file("zero-sized.pgm");
+
+        write_image(&file,ImageFormat::Pnm,&image::<1,2,2>()).unwrap();
+
+        let image = read_pnm::<_,1,0,2>(&file,Resize::Nearest);
+
+        let feature_map = write_feature_map_tiles(&file,ImageFormat::Pnm,&Images::<f32,2,0,3>::new(),2);
+        let kernel = write_kernel_tiles(&file,ImageFormat::Pnm,&Arr4::<f32,2,2,3,0>::new());
+
+        let _ = std::fs::remove_file(&file);
+
+        assert!(image.unwrap().as_raw_slice().is_empty());
+        assert!(feature_map.is_err());
+        assert!(kernel.is_err());
+    }
+}
@@ -0,0 +1,255 @@
+use num_traits::FromPrimitive;
+use nncombinator::arr::Arr4;
+use nncombinator::mem::AsRawMutSlice;
+use nncombinator::ope::UnitValue;
+use rand::Rng;
+use rand_distr::StandardNormal;
+use rand_xorshift::XorShiftRng;
+
+/// Initialization method of convolution kernels
+///
+/// The fan-in of a kernel is C * FH * FW and the fan-out is K * FH * FW.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Initializer {
+    /// He (Kaiming) normal, N(0, 2 / fan_in)
+    HeNormal,
+    /// He (Kaiming) uniform, U(-sqrt(6 / fan_in), sqrt(6 / fan_in))
+    HeUniform,
+    /// Xavier (Glorot) normal, N(0, 2 / (fan_in + fan_out))
+    XavierNormal,
+    /// Xavier (Glorot) uniform, U(-sqrt(6 / (fan_in + fan_out)), sqrt(6 / (fan_in + fan_out)))
+    XavierUniform,
+    /// LeCun normal, N(0, 1 / fan_in)
+    LeCunNormal,
+    /// LeCun uniform, U(-sqrt(3 / fan_in), sqrt(3 / fan_in))
+    LeCunUniform,
+    /// Orthogonal matrix of K x C*FH*FW multiplied by the gain
+    Orthogonal(f64),
+    /// Dirac (identity), which passes input channel i to output channel i unchanged
+    Dirac,
+}
+/// get the fan-in of a kernel
+pub fn fan_in<const C:usize,const FH:usize,const FW:usize>() -> usize {
+    C * FH * FW
+}
+/// get the fan-out of a kernel
+pub fn fan_out<const K:usize,const FH:usize,const FW:usize>() -> usize {
+    K * FH * FW
+}
+fn from_f64<U>(v:f64) -> U where U: UnitValue<U> + FromPrimitive {
+    <U as FromPrimitive>::from_f64(v).expect("Error in type conversion from f64.")
+}
+impl Initializer {
+    /// Initialize the filter weights
+    /// # Arguments
+    /// * `kernel` - filter weights to be initialized
+    /// * `rnd` - Random number generator
+    pub fn initialize<U,const K:usize,const C:usize,const FH:usize,const FW:usize>(&self,kernel:&mut Arr4<U,K,C,FH,FW>,rnd:&mut XorShiftRng)
+        where U: UnitValue<U> + FromPrimitive {
+        let fan_in = fan_in::<C,FH,FW>().max(1) as f64;
+        let fan_out = fan_out::<K,FH,FW>().max(1) as f64;
+
+        match *self {
+            Initializer::HeNormal => normal(kernel,(2. / fan_in).sqrt(),rnd),
+            Initializer::HeUniform => uniform(kernel,(6. / fan_in).sqrt(),rnd),
+            Initializer::XavierNormal => normal(kernel,(2. / (fan_in + fan_out)).sqrt(),rnd),
+            Initializer::XavierUniform => uniform(kernel,(6. / (fan_in + fan_out)).sqrt(),rnd),
+            Initializer::LeCunNormal => normal(kernel,(1. / fan_in).sqrt(),rnd),
+            Initializer::LeCunUniform => uniform(kernel,(3. / fan_in).sqrt(),rnd),
+            Initializer::Orthogonal(gain) => orthogonal(kernel,gain,rnd),
+            Initializer::Dirac => dirac(kernel),
+        }
+    }
+
+    /// Create an initialized kernel
+    /// # Arguments
+    /// * `rnd` - Random number generator
+    pub fn kernel<U,const K:usize,const C:usize,const FH:usize,const FW:usize>(&self,rnd:&mut XorShiftRng) -> Arr4<U,K,C,FH,FW>
+        where U: UnitValue<U> + FromPrimitive {
+        let mut kernel = Arr4::new();
+
+        self.initialize(&mut kernel,rnd);
+
+        kernel
+    }
+}
+fn normal<U,const K:usize,const C:usize,const FH:usize,const FW:usize>(kernel:&mut Arr4<U,K,C,FH,FW>,std:f64,rnd:&mut XorShiftRng)
+    where U: UnitValue<U> + FromPrimitive {
+    for w in kernel.as_raw_mut_slice().iter_mut() {
+        let n:f64 = rnd.sample(StandardNormal);
+
+        *w = from_f64(n * std);
+    }
+}
+fn uniform<U,const K:usize,const C:usize,const FH:usize,const FW:usize>(kernel:&mut Arr4<U,K,C,FH,FW>,bound:f64,rnd:&mut XorShiftRng)
+    where U: UnitValue<U> + FromPrimitive {
+    for w in kernel.as_raw_mut_slice().iter_mut() {
+        *w = from_f64(if bound > 0. { rnd.gen_range(-bound..bound) } else { 0. });
+    }
+}
+/// Orthogonalize a Gaussian matrix by modified Gram-Schmidt and write it as K rows of C*FH*FW
+fn orthogonal<U,const K:usize,const C:usize,const FH:usize,const FW:usize>(kernel:&mut Arr4<U,K,C,FH,FW>,gain:f64,rnd:&mut XorShiftRng)
+    where U: UnitValue<U> + FromPrimitive {
+    let n = C * FH * FW;
+
+    // Columns of a tall matrix of m x r, whose orthonormal columns become the rows or the columns of the kernel
+    let (m,r) = if K >= n { (K,n) } else { (n,K) };
+
+    let mut q = (0..r).map(|_| {
+        (0..m).map(|_| rnd.sample(StandardNormal)).collect::<Vec<f64>>()
+    }).collect::<Vec<Vec<f64>>>();
+
+    for j in 0..r {
+        for i in 0..j {
+            let (done,rest) = q.split_at_mut(j);
+            let p = done[i].iter().zip(rest[0].iter()).map(|(a,b)| a * b).sum::<f64>();
+
+            for (v,u) in rest[0].iter_mut().zip(done[i].iter()) {
+                *v -= p * u;
+            }
+        }
+
+        let norm = q[j].iter().map(|v| v * v).sum::<f64>().sqrt();
+
+        if norm > 0. {
+            for v in q[j].iter_mut() {
+                *v /= norm;
+            }
+        }
+    }
+
+    for (i,w) in kernel.as_raw_mut_slice().iter_mut().enumerate() {
+        let k = i / n.max(1);
+        let j = i % n.max(1);
+
+        let v = if K >= n { q[j][k] } else { q[k][j] };
+
+        *w = from_f64(v * gain);
+    }
+}
+/// Set 1 at the center of the filter connecting input channel i to output channel i, and 0 elsewhere
+fn dirac<U,const K:usize,const C:usize,const FH:usize,const FW:usize>(kernel:&mut Arr4<U,K,C,FH,FW>)
+    where U: UnitValue<U> + FromPrimitive {
+    let w = kernel.as_raw_mut_slice();
+
+    for v in w.iter_mut() {
+        *v = from_f64(0.);
+    }
+
+    if FH == 0 || FW == 0 {
+        return;
+    }
+
+    for i in 0..K.min(C) {
+        w[((i * C + i) * FH + FH / 2) * FW + FW / 2] = from_f64(1.);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use nncombinator::arr::Arr4;
+    use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use crate::collection::Images;
+    use crate::device::DeviceConvolution;
+    use crate::device::reference::DeviceReference;
+    use crate::layer::initializer::Initializer;
+
+    /// Inner product of rows i and j of a matrix of cols columns, or of columns i and j if transpose is true
+    fn dot(w:&[f64],rows:usize,cols:usize,i:usize,j:usize,transpose:bool) -> f64 {
+        if transpose {
+            (0..rows).map(|r| w[r * cols + i] * w[r * cols + j]).sum()
+        } else {
+            (0..cols).map(|c| w[i * cols + c] * w[j * cols + c]).sum()
+        }
+    }
+
+    fn assert_orthonormal(w:&[f64],rows:usize,cols:usize,transpose:bool,gain:f64) {
+        let n = if transpose { cols } else { rows };
+
+        for i in 0..n {
+            for j in 0..n {
+                let expected = if i == j { gain * gain } else { 0. };
+
+                assert!((dot(w,rows,cols,i,j,transpose) - expected).abs() < 1e-9,"{} {}",i,j);
+            }
+        }
+    }
+
+    #[test]
+    fn test_orthogonal() {
+        let mut rnd = XorShiftRng::seed_from_u64(1);
+
+        // K >= C * FH * FW, the columns are orthonormal
+        let kernel = Initializer::Orthogonal(2.).kernel::<f64,8,2,1,2>(&mut rnd);
+
+        assert_orthonormal(kernel.as_raw_slice(),8,4,true,2.);
+
+        // K < C * FH * FW, the rows are orthonormal
+        let kernel = Initializer::Orthogonal(1.).kernel::<f64,3,2,3,3>(&mut rnd);
+
+        assert_orthonormal(kernel.as_raw_slice(),3,18,false,1.);
+
+        let kernel = Initializer::Orthogonal(1.).kernel::<f64,4,1,2,2>(&mut rnd);
+
+        assert_orthonormal(kernel.as_raw_slice(),4,4,false,1.);
+        assert_orthonormal(kernel.as_raw_slice(),4,4,true,1.);
+    }
+
+    fn input<const C:usize>(rnd:&mut XorShiftRng) -> Images<f64,C,5,5> {
+        let mut input = Images::new();
+
+        for v in input.as_raw_mut_slice().iter_mut() {
+            *v = rnd.gen_range(-1.0..1.0);
+        }
+
+        input
+    }
+
+    #[test]
+    fn test_dirac_preserves_channels() {
+        let mut rnd = XorShiftRng::seed_from_u64(2);
+        let device = DeviceReference::<f64>::new();
+
+        let x = input::<3>(&mut rnd);
+        let kernel:Arr4<f64,3,3,3,3> = Initializer::Dirac.kernel(&mut rnd);
+
+        let y = DeviceConvolution::<f64,Arr4<f64,3,3,3,3>,3,3,5,5,3,3,1,1>::forward_convolution::<3>(&device,&x,&kernel).unwrap();
+
+        assert_eq!(y.as_raw_slice(),x.as_raw_slice());
+
+        let x = input::<2>(&mut rnd);
+        let kernel:Arr4<f64,4,2,3,3> = Initializer::Dirac.kernel(&mut rnd);
+
+        let y = DeviceConvolution::<f64,Arr4<f64,4,2,3,3>,2,4,5,5,3,3,1,1>::forward_convolution::<2>(&device,&x,&kernel).unwrap();
+
+        assert_eq!(&y.as_raw_slice()[..2 * 5 * 5],x.as_raw_slice());
+        assert!(y.as_raw_slice()[2 * 5 * 5..].iter().all(|&v| v == 0.));
+    }
+
+    fn assert_std(initializer:Initializer,expected:f64) {
+        let kernel = initializer.kernel::<f64,64,32,3,3>(&mut XorShiftRng::seed_from_u64(3));
+        let w = kernel.as_raw_slice();
+
+        let mean = w.iter().sum::<f64>() / w.len() as f64;
+        let std = (w.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / w.len() as f64).sqrt();
+
+        assert!(mean.abs() < 0.05 * expected,"{:?} mean {}",initializer,mean);
+        assert!((std - expected).abs() < 0.03 * expected,"{:?} std {} expected {}",initializer,std,expected);
+    }
+
+    #[test]
+    fn test_empirical_std() {
+        // fan_in = 32 * 3 * 3 and fan_out = 64 * 3 * 3
+        let fan_in = 288.;
+        let fan_out = 576.;
+
+        assert_std(Initializer::HeNormal,(2. / fan_in).sqrt());
+        assert_std(Initializer::HeUniform,(2. / fan_in).sqrt());
+        assert_std(Initializer::XavierNormal,(2. / (fan_in + fan_out)).sqrt());
+        assert_std(Initializer::XavierUniform,(2. / (fan_in + fan_out)).sqrt());
+        assert_std(Initializer::LeCunNormal,(1. / fan_in).sqrt());
+        assert_std(Initializer::LeCunUniform,(1. / fan_in).sqrt());
+    }
+}
@@ -0,0 +1,557 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use num_traits::{FromPrimitive, ToPrimitive};
+use nncombinator::arr::{Arr, Arr4};
+use nncombinator::error::{ConfigReadError, PersistenceError, TrainingError};
+use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+use nncombinator::ope::UnitValue;
+use nncombinator::optimizer::Optimizer;
+use nncombinator::persistence::{Linear, LinearPersistence, Persistence, Specialized, TextFilePersistence, UnitOrMarker};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+use crate::collection::Images;
+use crate::layer::initializer::Initializer;
+use crate::optimizer::ConvolutionOptimizer;
+
+pub mod initializer;
+
+/// Convolution Layer Implementation
+pub struct ConvolutionLayer<U,P,D,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH:usize,const FW:usize,const PAD:usize,const S:usize>
+    where U: UnitValue<U> {
+    parent:P,
+    device:D,
+    kernel:Arr4<U,K,C,FH,FW>,
+    bias:Arr<U,K>,
+}
+impl<U,P,D,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH:usize,const FW:usize,const PAD:usize,const S:usize> ConvolutionLayer<U,P,D,C,K,H,W,FH,FW,PAD,S>
+    where U: UnitValue<U>, D: Clone {
+    /// Create and return an instance of ConvolutionLayer
+    /// # Arguments
+    /// * `parent` - upper layer
+    /// * `device` - Device object used for neural network computation
+    /// * `ui` - Callback to generate weight of kernel
+    /// * `bi` - Callback to generate weight of bias
+    pub fn new<UI: FnMut() -> U, BI: FnMut() -> U>(parent:P,device:&D,mut ui:UI,mut bi:BI)
+        -> ConvolutionLayer<U,P,D,C,K,H,W,FH,FW,PAD,S> {
+        let mut kernel = Arr4::new();
+
+        for k in kernel.as_raw_mut_slice().iter_mut() {
+            *k = ui();
+        }
+
+        let mut bias = Arr::new();
+
+        for b in bias.iter_mut() {
+            *b = bi();
+        }
+
+        ConvolutionLayer {
+            parent:parent,
+            device:device.clone(),
+            kernel:kernel,
+            bias:bias,
+        }
+    }
+
+    /// Create and return an instance of ConvolutionLayer whose kernel is initialized by the specified method and bias is 0
+    /// # Arguments
+    /// * `parent` - upper layer
+    /// * `device` - Device object used for neural network computation
+    /// * `initializer` - Initialization method of the kernel
+    /// * `seed` - Seed of the random number generator
+    pub fn with_initializer(parent:P,device:&D,initializer:Initializer,seed:u64)
+        -> ConvolutionLayer<U,P,D,C,K,H,W,FH,FW,PAD,S> where U: FromPrimitive {
+        let mut rnd = XorShiftRng::seed_from_u64(seed);
+
+        ConvolutionLayer {
+            parent:parent,
+            device:device.clone(),
+            kernel:initializer.kernel(&mut rnd),
+            bias:Arr::new(),
+        }
+    }
+}
+impl<U,P,D,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH:usize,const FW:usize,const PAD:usize,const S:usize> ConvolutionLayer<U,P,D,C,K,H,W,FH,FW,PAD,S>
+    where U: UnitValue<U> {
+    /// get the filter weights
+    pub fn kernel(&self) -> &Arr4<U,K,C,FH,FW> {
+        &self.kernel
+    }
+
+    /// get the bias of each output channel
+    pub fn bias(&self) -> &Arr<U,K> {
+        &self.bias
+    }
+
+    /// get the upper layer
+    pub fn parent(&self) -> &P {
+        &self.parent
+    }
+
+    /// get the device object
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    /// Update the kernel and bias by their gradients with nncombinator optimizers
+    /// # Arguments
+    /// * `optimizer` - Optimizer holding the state of the kernel and bias
+    /// * `kernel_gradient` - gradient of the kernel
+    /// * `bias_gradient` - gradient of the bias
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`TrainingError`]
+    pub fn update<O: Optimizer<U>>(&mut self,optimizer:&mut ConvolutionOptimizer<U,O,K,C,FH,FW>,
+                                   kernel_gradient:&Arr4<U,K,C,FH,FW>,bias_gradient:&Arr<U,K>) -> Result<(),TrainingError> {
+        optimizer.update_kernel(kernel_gradient,&mut self.kernel)?;
+        optimizer.update_bias(bias_gradient,&mut self.bias)?;
+
+        Ok(())
+    }
+
+    /// Fold a trained batch normalization that follows this layer into the kernel and bias for inference
+    ///
+    /// The returned layer computes the output of the batch normalization applied to the output of this layer,
+    /// so the batch normalization layer is removed from the network and one pass over the feature map is saved.
+    /// The running statistics are fixed, so the result is only valid for inference.
+    /// # Arguments
+    /// * `batch_norm` - Batch normalization layer that follows this layer
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`BatchNormalizationError`]
+    pub fn fold_batch_norm(mut self,batch_norm:&BatchNormalization<U,K>)
+        -> Result<ConvolutionLayer<U,P,D,C,K,H,W,FH,FW,PAD,S>,BatchNormalizationError> where U: FromPrimitive + ToPrimitive {
+        let cff = C * FH * FW;
+
+        for (k,kernel) in self.kernel.as_raw_mut_slice().chunks_mut(cff).enumerate() {
+            let s = batch_norm.channel_scale(k)?;
+
+            for w in kernel.iter_mut() {
+                *w = from_f64(to_f64(w) * s);
+            }
+
+            self.bias[k] = from_f64((to_f64(&self.bias[k]) - to_f64(&batch_norm.mean[k])) * s + to_f64(&batch_norm.shift[k]));
+        }
+
+        Ok(self)
+    }
+}
+/// Error raised when the running statistics of a batch normalization cannot be applied
+#[derive(Debug,Clone,PartialEq)]
+pub enum BatchNormalizationError {
+    /// The variance plus epsilon of a channel is not positive (channel, variance plus epsilon)
+    NonPositiveVariance(usize,f64),
+}
+impl fmt::Display for BatchNormalizationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchNormalizationError::NonPositiveVariance(k,v) => {
+                write!(f, "The variance plus epsilon of the batch normalization must be positive. (channel {}: {})", k, v)
+            }
+        }
+    }
+}
+impl Error for BatchNormalizationError {}
+/// Batch normalization layer with fixed running statistics, used for inference
+pub struct BatchNormalization<U,const K:usize> where U: UnitValue<U> {
+    scale:Arr<U,K>,
+    shift:Arr<U,K>,
+    mean:Arr<U,K>,
+    variance:Arr<U,K>,
+    epsilon:f64,
+}
+impl<U,const K:usize> BatchNormalization<U,K> where U: UnitValue<U> {
+    /// Create an instance of BatchNormalization
+    /// # Arguments
+    /// * `scale` - scale of each channel
+    /// * `shift` - shift of each channel
+    /// * `mean` - running mean of each channel
+    /// * `variance` - running variance of each channel
+    /// * `epsilon` - value added to the variance
+    pub fn new(scale:Arr<U,K>,shift:Arr<U,K>,mean:Arr<U,K>,variance:Arr<U,K>,epsilon:f64) -> BatchNormalization<U,K> {
+        BatchNormalization {
+            scale:scale,
+            shift:shift,
+            mean:mean,
+            variance:variance,
+            epsilon:epsilon
+        }
+    }
+
+    /// get the scale of each channel
+    pub fn scale(&self) -> &Arr<U,K> {
+        &self.scale
+    }
+
+    /// get the shift of each channel
+    pub fn shift(&self) -> &Arr<U,K> {
+        &self.shift
+    }
+
+    /// get the running mean of each channel
+    pub fn mean(&self) -> &Arr<U,K> {
+        &self.mean
+    }
+
+    /// get the running variance of each channel
+    pub fn variance(&self) -> &Arr<U,K> {
+        &self.variance
+    }
+
+    /// get the value added to the variance
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Compute scale / sqrt(variance + epsilon) of a channel
+    fn channel_scale(&self,k:usize) -> Result<f64,BatchNormalizationError> where U: ToPrimitive {
+        let v = to_f64(&self.variance[k]) + self.epsilon;
+
+        if !(v > 0.) {
+            return Err(BatchNormalizationError::NonPositiveVariance(k,v));
+        }
+
+        Ok(to_f64(&self.scale[k]) / v.sqrt())
+    }
+
+    /// Normalize the output of a convolution with the running statistics
+    /// # Arguments
+    /// * `input` - output of the convolution
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`BatchNormalizationError`]
+    pub fn forward<const H:usize,const W:usize>(&self,input:&Images<U,K,H,W>) -> Result<Images<U,K,H,W>,BatchNormalizationError>
+        where U: FromPrimitive + ToPrimitive {
+        let mut output = input.clone();
+        let raw = output.as_raw_mut_slice();
+
+        for k in 0..K {
+            let plane = &mut raw[k * H * W..(k + 1) * H * W];
+            let s = self.channel_scale(k)?;
+            let mean = to_f64(&self.mean[k]);
+            let shift = to_f64(&self.shift[k]);
+
+            for v in plane.iter_mut() {
+                *v = from_f64((to_f64(v) - mean) * s + shift);
+            }
+        }
+
+        Ok(output)
+    }
+}
+/// Convert a unit value into f64
+fn to_f64<U>(v:&U) -> f64 where U: ToPrimitive {
+    <U as ToPrimitive>::to_f64(v).expect("Error in type conversion to f64.")
+}
+/// Convert f64 into a unit value
+fn from_f64<U>(v:f64) -> U where U: FromPrimitive {
+    <U as FromPrimitive>::from_f64(v).expect("Error in type conversion from f64.")
+}
+/// Verify that a value read from the header of the persisted kernel matches the shape of the layer
+pub(crate) fn verify_kernel_shape<U>(name:&str,expected:usize,actual:U) -> Result<(),ConfigReadError>
+    where U: UnitValue<U> + ToPrimitive {
+    match <U as ToPrimitive>::to_f64(&actual) {
+        Some(v) if v == expected as f64 => Ok(()),
+        Some(v) => Err(ConfigReadError::InvalidState(format!(
+            "The shape of the convolution kernel does not match. ({}: expected {}, but {} was read)",name,expected,v
+        ))),
+        None => Err(ConfigReadError::InvalidState(format!(
+            "The shape of the convolution kernel could not be read. ({})",name
+        )))
+    }
+}
+/// Convert a dimension of the kernel into a unit value to be written in the header
+fn kernel_shape_unit<U>(v:usize) -> U where U: UnitValue<U> + FromPrimitive {
+    <U as FromPrimitive>::from_usize(v).expect("Error in type conversion from usize.")
+}
+impl<U,P,D,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH:usize,const FW:usize,const PAD:usize,const S:usize> Persistence<U,TextFilePersistence<U>,Specialized>
+    for ConvolutionLayer<U,P,D,C,K,H,W,FH,FW,PAD,S>
+    where P: Persistence<U,TextFilePersistence<U>,Specialized>,
+          U: UnitValue<U> + FromStr + FromPrimitive + ToPrimitive,
+          ConfigReadError: From<<U as FromStr>::Err> {
+    fn load(&mut self, persistence: &mut TextFilePersistence<U>) -> Result<(),ConfigReadError> {
+        self.parent.load(persistence)?;
+
+        verify_kernel_shape("K",K,persistence.read()?)?;
+        verify_kernel_shape("C",C,persistence.read()?)?;
+        verify_kernel_shape("FH",FH,persistence.read()?)?;
+        verify_kernel_shape("FW",FW,persistence.read()?)?;
+
+        for b in self.bias.iter_mut() {
+            *b = persistence.read()?;
+        }
+
+        for k in self.kernel.as_raw_mut_slice().iter_mut() {
+            *k = persistence.read()?;
+        }
+
+        Ok(())
+    }
+
+    fn save(&mut self, persistence: &mut TextFilePersistence<U>) -> Result<(), PersistenceError> {
+        self.parent.save(persistence)?;
+
+        persistence.write(UnitOrMarker::LayerStart);
+
+        persistence.write(UnitOrMarker::UnitsStart);
+        persistence.write(UnitOrMarker::Unit(kernel_shape_unit(K)));
+        persistence.write(UnitOrMarker::Unit(kernel_shape_unit(C)));
+        persistence.write(UnitOrMarker::Unit(kernel_shape_unit(FH)));
+        persistence.write(UnitOrMarker::Unit(kernel_shape_unit(FW)));
+
+        persistence.write(UnitOrMarker::UnitsStart);
+        for b in self.bias.iter() {
+            persistence.write(UnitOrMarker::Unit(*b));
+        }
+
+        for k in self.kernel.as_raw_slice().chunks(FH * FW) {
+            persistence.write(UnitOrMarker::UnitsStart);
+
+            for w in k.iter() {
+                persistence.write(UnitOrMarker::Unit(*w));
+            }
+        }
+
+        Ok(())
+    }
+}
+impl<T,U,P,D,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH:usize,const FW:usize,const PAD:usize,const S:usize> Persistence<U,T,Linear>
+    for ConvolutionLayer<U,P,D,C,K,H,W,FH,FW,PAD,S>
+    where T: LinearPersistence<U>,
+          P: Persistence<U,T,Linear>,
+          U: UnitValue<U> + FromPrimitive + ToPrimitive {
+    fn load(&mut self, persistence: &mut T) -> Result<(),ConfigReadError> {
+        self.parent.load(persistence)?;
+
+        verify_kernel_shape("K",K,persistence.read()?)?;
+        verify_kernel_shape("C",C,persistence.read()?)?;
+        verify_kernel_shape("FH",FH,persistence.read()?)?;
+        verify_kernel_shape("FW",FW,persistence.read()?)?;
+
+        for b in self.bias.iter_mut() {
+            *b = persistence.read()?;
+        }
+
+        for k in self.kernel.as_raw_mut_slice().iter_mut() {
+            *k = persistence.read()?;
+        }
+
+        Ok(())
+    }
+
+    fn save(&mut self, persistence: &mut T) -> Result<(), PersistenceError> {
+        self.parent.save(persistence)?;
+
+        persistence.write(kernel_shape_unit(K))?;
+        persistence.write(kernel_shape_unit(C))?;
+        persistence.write(kernel_shape_unit(FH))?;
+        persistence.write(kernel_shape_unit(FW))?;
+
+        for b in self.bias.iter() {
+            persistence.write(*b)?;
+        }
+
+        for k in self.kernel.as_raw_slice().iter() {
+            persistence.write(*k)?;
+        }
+
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use nncombinator::arr::{Arr, Arr4};
+    use nncombinator::error::{ConfigReadError, PersistenceError};
+    use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+    use nncombinator::persistence::{Linear, LinearPersistence, Persistence, SaveToFile, Specialized, TextFilePersistence};
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use crate::checkpoint::CheckpointPersistence;
+    use crate::collection::Images;
+    use crate::device::DeviceConvolution;
+    use crate::device::reference::DeviceReference;
+    use crate::layer::{verify_kernel_shape, BatchNormalization, BatchNormalizationError, ConvolutionLayer};
+
+    /// Upper layer without parameters
+    struct Root;
+    impl Persistence<f32,TextFilePersistence<f32>,Specialized> for Root {
+        fn load(&mut self, _: &mut TextFilePersistence<f32>) -> Result<(), ConfigReadError> {
+            Ok(())
+        }
+
+        fn save(&mut self, _: &mut TextFilePersistence<f32>) -> Result<(), PersistenceError> {
+            Ok(())
+        }
+    }
+    impl<T> Persistence<f32,T,Linear> for Root where T: LinearPersistence<f32> {
+        fn load(&mut self, _: &mut T) -> Result<(), ConfigReadError> {
+            Ok(())
+        }
+
+        fn save(&mut self, _: &mut T) -> Result<(), PersistenceError> {
+            Ok(())
+        }
+    }
+
+    fn layer<const FH:usize,const FW:usize,const PAD:usize>(seed:u64) -> ConvolutionLayer<f32,Root,(),2,3,5,5,FH,FW,PAD,1> {
+        let mut rnd = XorShiftRng::seed_from_u64(seed);
+        let mut bias = seed as f32;
+
+        ConvolutionLayer::new(Root,&(),|| rnd.gen_range(-1.0..1.0),|| { bias += 0.5; bias })
+    }
+
+    fn assert_same_parameters<const FH:usize,const FW:usize,const PAD:usize>(a:&ConvolutionLayer<f32,Root,(),2,3,5,5,FH,FW,PAD,1>,
+                                                                              b:&ConvolutionLayer<f32,Root,(),2,3,5,5,FH,FW,PAD,1>) {
+        assert_eq!(a.kernel().as_raw_slice(),b.kernel().as_raw_slice());
+        assert_eq!(&a.bias()[..],&b.bias()[..]);
+    }
+
+    fn forward(kernel:&Arr4<f64,4,3,3,3>,bias:&Arr<f64,4>,input:&Images<f64,3,6,6>) -> Images<f64,4,6,6> {
+        let device = DeviceReference::<f64>::new();
+
+        let mut output = DeviceConvolution::<f64,Arr4<f64,4,3,3,3>,3,4,6,6,3,3,1,1>::forward_convolution::<3>(&device,input,kernel).unwrap();
+
+        for (k,plane) in output.as_raw_mut_slice().chunks_mut(36).enumerate() {
+            for v in plane.iter_mut() {
+                *v += bias[k];
+            }
+        }
+
+        output
+    }
+
+    fn batch_norm(variance:f64,epsilon:f64,rnd:&mut XorShiftRng) -> BatchNormalization<f64,4> {
+        let mut scale = Arr::new();
+        let mut shift = Arr::new();
+        let mut mean = Arr::new();
+        let mut variances = Arr::new();
+
+        for k in 0..4 {
+            scale[k] = rnd.gen_range(0.5..2.0);
+            shift[k] = rnd.gen_range(-1.0..1.0);
+            mean[k] = rnd.gen_range(-1.0..1.0);
+            variances[k] = variance * (k + 1) as f64;
+        }
+
+        BatchNormalization::new(scale,shift,mean,variances,epsilon)
+    }
+
+    #[test]
+    fn test_fold_batch_norm_matches_convolution_and_batch_norm() {
+        let mut rnd = XorShiftRng::seed_from_u64(5);
+
+        let layer = ConvolutionLayer::<f64,(),(),3,4,6,6,3,3,1,1>::new((),&(),|| rnd.gen_range(-1.0..1.0),|| 0.25);
+
+        let mut rnd = XorShiftRng::seed_from_u64(6);
+
+        let bn = batch_norm(0.5,1e-5,&mut rnd);
+
+        let mut input = Images::<f64,3,6,6>::new();
+
+        for v in input.as_raw_mut_slice().iter_mut() {
+            *v = rnd.gen_range(-1.0..1.0);
+        }
+
+        let expected = bn.forward(&forward(layer.kernel(),layer.bias(),&input)).unwrap();
+
+        let folded = layer.fold_batch_norm(&bn).unwrap();
+
+        let actual = forward(folded.kernel(),folded.bias(),&input);
+
+        for (e,a) in expected.as_raw_slice().iter().zip(actual.as_raw_slice().iter()) {
+            assert!((e - a).abs() <= 1e-9,"{} != {}",e,a);
+        }
+    }
+
+    #[test]
+    fn test_fold_batch_norm_rejects_non_positive_variance() {
+        let mut rnd = XorShiftRng::seed_from_u64(7);
+
+        for (variance,epsilon) in [(0.,0.),(-1.,1e-5),(f64::NAN,1e-5)] {
+            let layer = ConvolutionLayer::<f64,(),(),3,4,6,6,3,3,1,1>::new((),&(),|| 1.,|| 0.);
+
+            let bn = batch_norm(variance,epsilon,&mut rnd);
+
+            assert!(matches!(bn.forward(&Images::<f64,4,6,6>::new()),Err(BatchNormalizationError::NonPositiveVariance(0,_))));
+            assert!(matches!(layer.fold_batch_norm(&bn),Err(BatchNormalizationError::NonPositiveVariance(0,_))));
+        }
+    }
+
+    #[test]
+    fn test_text_persistence_round_trip() {
+        let file = std::env::temp_dir().join(format!("nncombinator-convolution-layer-{}.txt",std::process::id()));
+        let name = file.to_str().unwrap().to_string();
+
+        let _ = std::fs::remove_file(&file);
+
+        let mut layer = layer::<3,3,1>(1);
+
+        let mut persistence = TextFilePersistence::new(&name).unwrap();
+
+        layer.save(&mut persistence).unwrap();
+        persistence.save(&name).unwrap();
+
+        let mut restored = layer::<3,3,1>(2);
+
+        let loaded = TextFilePersistence::new(&name).and_then(|mut p| restored.load(&mut p));
+
+        let mut other_shape = layer::<1,1,0>(3);
+
+        let rejected = TextFilePersistence::new(&name).and_then(|mut p| other_shape.load(&mut p));
+
+        std::fs::remove_file(&file).unwrap();
+
+        loaded.unwrap();
+
+        assert_same_parameters(&layer,&restored);
+        assert!(rejected.is_err());
+    }
+
+    #[test]
+    fn test_linear_persistence_round_trip() {
+        let mut layer = layer::<3,3,1>(1);
+        let mut persistence = CheckpointPersistence::new();
+
+        layer.save(&mut persistence).unwrap();
+
+        // K, C, FH and FW, then the bias and the kernel
+        assert_eq!(&persistence.values()[..4],&[3.,2.,3.,3.]);
+        assert_eq!(persistence.values().len(),4 + 3 + 3 * 2 * 3 * 3);
+
+        let values = persistence.values().to_vec();
+
+        let mut restored = layer::<3,3,1>(2);
+        let mut persistence = CheckpointPersistence::with_values(values.clone());
+
+        restored.load(&mut persistence).unwrap();
+        persistence.verify_eof().unwrap();
+
+        assert_same_parameters(&layer,&restored);
+
+        let mut other_shape = layer::<1,1,0>(3);
+
+        assert!(other_shape.load(&mut CheckpointPersistence::with_values(values)).is_err());
+    }
+
+    #[test]
+    fn test_verify_kernel_shape() {
+        assert!(verify_kernel_shape("K",3,3f32).is_ok());
+        assert!(verify_kernel_shape("K",3,2f32).is_err());
+        assert!(verify_kernel_shape("FH",3,3.5f32).is_err());
+        assert!(verify_kernel_shape("FW",3,f32::NAN).is_err());
+    }
+}
@@ -11,8 +11,23 @@ extern crate rcudnn;
 extern crate rcudnn_sys;
 extern crate const_guards;
 extern crate rayon;
+extern crate num_traits;
+extern crate zip;
+extern crate png;
+extern crate rand;
+extern crate rand_xorshift;
+extern crate rand_distr;
 
 extern crate nncombinator;
 
+pub mod checkpoint;
 pub mod collection;
+pub mod dataset;
 pub mod device;
+pub mod gradient_check;
+pub mod imageio;
+pub mod layer;
+pub mod numpy;
+pub mod onnx;
+pub mod optimizer;
+pub mod preprocessing;
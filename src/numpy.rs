@@ -0,0 +1,546 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use nncombinator::arr::Arr4;
+use nncombinator::error::{ConfigReadError, PersistenceError};
+use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+
+use crate::checkpoint::TensorElement;
+use crate::collection::{Images, VecImages};
+
+/// Magic string at the start of a .npy file
+const NPY_MAGIC:&[u8] = b"\x93NUMPY";
+
+/// Trait that defines the element types that can be read from and written to .npy files
+pub trait NpyElement: TensorElement {
+    /// Value of descr in the header
+    const DESCR:&'static str;
+}
+impl NpyElement for f32 {
+    const DESCR: &'static str = "<f4";
+}
+impl NpyElement for f64 {
+    const DESCR: &'static str = "<f8";
+}
+impl NpyElement for u8 {
+    const DESCR: &'static str = "|u1";
+}
+/// Layout of the dimensions of a convolution kernel stored in an array
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum KernelLayout {
+    /// Output channels, input channels, height, width (the layout of Arr4 and PyTorch)
+    OIHW,
+    /// Height, width, input channels, output channels (the layout of TensorFlow)
+    HWIO,
+}
+/// Array read from a .npy file
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct NpyArray {
+    /// Data type of the elements
+    pub descr:String,
+    /// Whether the elements are stored in column-major order
+    pub fortran_order:bool,
+    /// Shape of the array
+    pub shape:Vec<usize>,
+    /// Raw data of the elements
+    pub data:Vec<u8>,
+}
+impl NpyArray {
+    /// Read an array in .npy format
+    /// # Arguments
+    /// * `reader` - Source of the .npy data
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn read<R: Read>(reader:&mut R) -> Result<NpyArray,ConfigReadError> {
+        let mut magic = [0u8; 8];
+
+        reader.read_exact(&mut magic)?;
+
+        if &magic[0..6] != NPY_MAGIC {
+            return Err(ConfigReadError::InvalidState(String::from("The data is not in .npy format.")));
+        }
+
+        let header_len = match magic[6] {
+            1 => {
+                let mut len = [0u8; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_le_bytes(len) as usize
+            },
+            2 | 3 => {
+                let mut len = [0u8; 4];
+                reader.read_exact(&mut len)?;
+                u32::from_le_bytes(len) as usize
+            },
+            v => {
+                return Err(ConfigReadError::InvalidState(format!("Unsupported .npy format version {}.",v)));
+            }
+        };
+
+        let mut header = vec![0u8; header_len];
+
+        reader.read_exact(&mut header)?;
+
+        let header = String::from_utf8(header).map_err(|_| {
+            ConfigReadError::InvalidState(String::from("The header of the .npy data is not valid text."))
+        })?;
+
+        let descr = npy_header_value(&header,"descr")?;
+        let descr = descr.trim_matches(|c| c == '\'' || c == '"').to_string();
+
+        let fortran_order = match npy_header_value(&header,"fortran_order")?.as_str() {
+            "True" => true,
+            "False" => false,
+            v => {
+                return Err(ConfigReadError::InvalidState(format!("Invalid value of fortran_order in the .npy header. ({})",v)));
+            }
+        };
+
+        let shape = npy_header_value(&header,"shape")?;
+        let shape = shape.trim_start_matches('(').trim_end_matches(')')
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<usize>().map_err(|_| {
+                ConfigReadError::InvalidState(format!("Invalid value of shape in the .npy header. ({})",s))
+            }))
+            .collect::<Result<Vec<usize>,ConfigReadError>>()?;
+
+        let mut data = Vec::new();
+
+        reader.read_to_end(&mut data)?;
+
+        Ok(NpyArray {
+            descr:descr,
+            fortran_order:fortran_order,
+            shape:shape,
+            data:data
+        })
+    }
+
+    /// Read a .npy file
+    /// # Arguments
+    /// * `file` - Path of the file to be read
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn load<P: AsRef<Path>>(file:P) -> Result<NpyArray,ConfigReadError> {
+        let mut reader = BufReader::new(File::open(file)?);
+
+        NpyArray::read(&mut reader)
+    }
+
+    /// Decode the elements after checking the data type and shape
+    /// # Arguments
+    /// * `shape` - Expected shape
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn to_vec<T: NpyElement>(&self,shape:&[usize]) -> Result<Vec<T>,ConfigReadError> {
+        let descr = self.descr.replacen('=',"<",1);
+
+        if descr != T::DESCR {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The dtype of the .npy data does not match. (expected {}, actual {})",T::DESCR,self.descr
+            )));
+        } else if self.fortran_order {
+            return Err(ConfigReadError::InvalidState(String::from(
+                "The .npy data in Fortran order is not supported."
+            )));
+        } else if self.shape != shape {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The shape of the .npy data does not match. (expected {:?}, actual {:?})",shape,self.shape
+            )));
+        } else if self.data.len() != shape.iter().product::<usize>() * T::SIZE {
+            return Err(ConfigReadError::InvalidState(format!(
+                "The .npy data is truncated. (expected {} bytes, actual {} bytes)",
+                shape.iter().product::<usize>() * T::SIZE,self.data.len()
+            )));
+        }
+
+        Ok(self.data.chunks(T::SIZE).map(T::read_le).collect())
+    }
+
+    /// Convert to Arr4 of convolution kernel
+    /// # Arguments
+    /// * `layout` - Layout of the dimensions of the array
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn to_arr4<T: NpyElement,const K:usize,const C:usize,const FH:usize,const FW:usize>(&self,layout:KernelLayout)
+        -> Result<Arr4<T,K,C,FH,FW>,ConfigReadError> {
+        let mut kernel = Arr4::new();
+
+        match layout {
+            KernelLayout::OIHW => {
+                let values = self.to_vec::<T>(&[K,C,FH,FW])?;
+
+                kernel.as_raw_mut_slice().copy_from_slice(&values);
+            },
+            KernelLayout::HWIO => {
+                let values = self.to_vec::<T>(&[FH,FW,C,K])?;
+
+                let dst = kernel.as_raw_mut_slice();
+
+                for y in 0..FH {
+                    for x in 0..FW {
+                        for c in 0..C {
+                            for k in 0..K {
+                                dst[((k * C + c) * FH + y) * FW + x] = values[((y * FW + x) * C + c) * K + k];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(kernel)
+    }
+
+    /// Convert to Images
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn to_images<T: NpyElement,const C:usize,const H:usize,const W:usize>(&self) -> Result<Images<T,C,H,W>,ConfigReadError> {
+        let values = self.to_vec::<T>(&[C,H,W])?;
+
+        let mut images = Images::new();
+
+        images.as_raw_mut_slice().copy_from_slice(&values);
+
+        Ok(images)
+    }
+
+    /// Convert to VecImages, whose first dimension is the number of elements
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn to_vec_images<T: NpyElement,const C:usize,const H:usize,const W:usize>(&self) -> Result<VecImages<T,C,H,W>,ConfigReadError> {
+        let len = self.shape.first().cloned().unwrap_or(0);
+
+        let values = self.to_vec::<T>(&[len,C,H,W])?;
+
+        let mut images = VecImages::with_size(len);
+
+        images.as_raw_mut_slice().copy_from_slice(&values);
+
+        Ok(images)
+    }
+}
+/// Obtain the text of the value of a key in the dictionary of the .npy header
+fn npy_header_value(header:&str,key:&str) -> Result<String,ConfigReadError> {
+    let missing = || ConfigReadError::InvalidState(format!("The .npy header does not contain {}.",key));
+
+    let start = header.find(&format!("'{}'",key))
+        .or_else(|| header.find(&format!("\"{}\"",key)))
+        .ok_or_else(missing)? + key.len() + 2;
+
+    let rest = header[start..].trim_start();
+    let rest = rest.strip_prefix(':').ok_or_else(missing)?.trim_start();
+
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|e| e + 1)
+    } else {
+        rest.find(|c| c == ',' || c == '}')
+    }.ok_or_else(missing)?;
+
+    Ok(rest[..end].trim().to_string())
+}
+/// Write an array in .npy format
+/// # Arguments
+/// * `writer` - Destination of the .npy data
+/// * `shape` - Shape of the array
+/// * `data` - Elements of the array in row-major order
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`PersistenceError`]
+pub fn write_npy<T: NpyElement,Wr: Write>(writer:&mut Wr,shape:&[usize],data:&[T]) -> Result<(),PersistenceError> {
+    let shape = match shape.len() {
+        1 => format!("({},)",shape[0]),
+        _ => format!("({})",shape.iter().map(|s| s.to_string()).collect::<Vec<String>>().join(", "))
+    };
+
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",T::DESCR,shape);
+
+    while (NPY_MAGIC.len() + 4 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+
+    header.push('\n');
+
+    writer.write_all(NPY_MAGIC)?;
+    writer.write_all(&[1u8,0u8])?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+
+    let mut buffer = Vec::with_capacity(data.len() * T::SIZE);
+
+    for v in data.iter() {
+        v.write_le(&mut buffer);
+    }
+
+    writer.write_all(&buffer)?;
+
+    Ok(())
+}
+/// Write an array to a .npy file
+/// # Arguments
+/// * `file` - Path of the file to be written
+/// * `shape` - Shape of the array
+/// * `data` - Elements of the array in row-major order
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`PersistenceError`]
+pub fn save_npy<T: NpyElement,P: AsRef<Path>>(file:P,shape:&[usize],data:&[T]) -> Result<(),PersistenceError> {
+    let mut writer = BufWriter::new(File::create(file)?);
+
+    write_npy(&mut writer,shape,data)?;
+
+    writer.flush()?;
+
+    Ok(())
+}
+/// Write a convolution kernel to a .npy file in OIHW layout
+/// # Arguments
+/// * `file` - Path of the file to be written
+/// * `kernel` - filter weights
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`PersistenceError`]
+pub fn save_npy_arr4<T: NpyElement,P: AsRef<Path>,const K:usize,const C:usize,const FH:usize,const FW:usize>(
+    file:P,kernel:&Arr4<T,K,C,FH,FW>) -> Result<(),PersistenceError> {
+    save_npy(file,&[K,C,FH,FW],kernel.as_raw_slice())
+}
+/// Write Images to a .npy file
+/// # Arguments
+/// * `file` - Path of the file to be written
+/// * `images` - Images to be written
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`PersistenceError`]
+pub fn save_npy_images<T: NpyElement,P: AsRef<Path>,const C:usize,const H:usize,const W:usize>(
+    file:P,images:&Images<T,C,H,W>) -> Result<(),PersistenceError> {
+    save_npy(file,&[C,H,W],images.as_raw_slice())
+}
+/// Write VecImages to a .npy file
+/// # Arguments
+/// * `file` - Path of the file to be written
+/// * `images` - VecImages to be written
+///
+/// # Errors
+///
+/// This function may return the following errors
+/// * [`PersistenceError`]
+pub fn save_npy_vec_images<T: NpyElement,P: AsRef<Path>,const C:usize,const H:usize,const W:usize>(
+    file:P,images:&VecImages<T,C,H,W>) -> Result<(),PersistenceError> {
+    save_npy(file,&[images.len(),C,H,W],images.as_raw_slice())
+}
+/// Implementation of a reader of .npz archives
+pub struct NpzArchive {
+    archive:zip::ZipArchive<BufReader<File>>,
+}
+impl NpzArchive {
+    /// Open a .npz file
+    /// # Arguments
+    /// * `file` - Path of the file to be read
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn open<P: AsRef<Path>>(file:P) -> Result<NpzArchive,ConfigReadError> {
+        let archive = zip::ZipArchive::new(BufReader::new(File::open(file)?)).map_err(|e| {
+            ConfigReadError::InvalidState(format!("The file is not a valid .npz archive. ({})",e))
+        })?;
+
+        Ok(NpzArchive {
+            archive:archive
+        })
+    }
+
+    /// get the names of the arrays
+    pub fn names(&self) -> Vec<String> {
+        self.archive.file_names().map(|n| n.trim_end_matches(".npy").to_string()).collect()
+    }
+
+    /// Read an array
+    /// # Arguments
+    /// * `name` - Name of the array, with or without the .npy extension
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn array(&mut self,name:&str) -> Result<NpyArray,ConfigReadError> {
+        let name = if name.ends_with(".npy") {
+            name.to_string()
+        } else {
+            format!("{}.npy",name)
+        };
+
+        let mut file = self.archive.by_name(&name).map_err(|e| {
+            ConfigReadError::InvalidState(format!("The array {} could not be read from the .npz archive. ({})",name,e))
+        })?;
+
+        NpyArray::read(&mut file)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use nncombinator::mem::AsRawSlice;
+
+    use crate::numpy::{write_npy, KernelLayout, NpyArray, NpyElement, NpzArchive};
+
+    fn npy<T: NpyElement>(shape:&[usize],data:&[T]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        write_npy(&mut bytes,shape,data).unwrap();
+
+        bytes
+    }
+
+    fn npy_with_header(header:&str,data:&[u8]) -> Vec<u8> {
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+
+        bytes
+    }
+
+    #[test]
+    fn test_npy_round_trip() {
+        let data = (0..24).map(|i| i as f32 * 0.5 - 3.).collect::<Vec<f32>>();
+        let bytes = npy(&[2,3,4],&data);
+
+        assert_eq!((bytes.len() - data.len() * 4) % 64,0);
+
+        let array = NpyArray::read(&mut &bytes[..]).unwrap();
+
+        assert_eq!(array.descr,"<f4");
+        assert!(!array.fortran_order);
+        assert_eq!(array.shape,vec![2,3,4]);
+        assert_eq!(array.to_vec::<f32>(&[2,3,4]).unwrap(),data);
+
+        let bytes = npy(&[5],&[1u8,2,3,4,5]);
+        let array = NpyArray::read(&mut &bytes[..]).unwrap();
+
+        assert_eq!(array.shape,vec![5]);
+        assert_eq!(array.to_vec::<u8>(&[5]).unwrap(),vec![1,2,3,4,5]);
+
+        let images = NpyArray::read(&mut &npy(&[2,1,2,3],&data[..12].iter().map(|&v| v as f64).collect::<Vec<f64>>())[..]).unwrap()
+            .to_vec_images::<f64,1,2,3>().unwrap();
+
+        assert_eq!(images.len(),2);
+        assert_eq!(images.as_raw_slice(),&data[..12].iter().map(|&v| v as f64).collect::<Vec<f64>>()[..]);
+    }
+
+    #[test]
+    fn test_hwio_transpose() {
+        let data = (0..24).map(|i| i as f32).collect::<Vec<f32>>();
+        let array = NpyArray::read(&mut &npy(&[2,2,3,2],&data)[..]).unwrap();
+
+        let kernel = array.to_arr4::<f32,2,3,2,2>(KernelLayout::HWIO).unwrap();
+        let kernel = kernel.as_raw_slice();
+
+        // kernel[k][c][y][x] is at ((k * 3 + c) * 2 + y) * 2 + x in OIHW, and at ((y * 2 + x) * 3 + c) * 2 + k in HWIO
+        assert_eq!(kernel[0],0.);
+        assert_eq!(kernel[5],8.);
+        assert_eq!(kernel[15],19.);
+        assert_eq!(kernel[22],17.);
+        assert_eq!(kernel[23],23.);
+
+        let mut sorted = kernel.to_vec();
+
+        sorted.sort_by(|a,b| a.partial_cmp(b).unwrap());
+
+        assert_eq!(sorted,data);
+
+        let oihw = NpyArray::read(&mut &npy(&[2,3,2,2],&data)[..]).unwrap().to_arr4::<f32,2,3,2,2>(KernelLayout::OIHW).unwrap();
+
+        assert_eq!(oihw.as_raw_slice(),&data[..]);
+        assert!(array.to_arr4::<f32,2,3,2,2>(KernelLayout::OIHW).is_err());
+    }
+
+    #[test]
+    fn test_npy_rejects_invalid_arrays() {
+        let data = [0u8; 16];
+
+        let array = NpyArray::read(&mut &npy(&[4],&[1f32,2.,3.,4.])[..]).unwrap();
+
+        assert!(array.to_vec::<f64>(&[4]).is_err());
+        assert!(array.to_vec::<f32>(&[2,2]).is_err());
+        assert!(array.to_vec::<f32>(&[5]).is_err());
+
+        let fortran = NpyArray::read(&mut &npy_with_header("{'descr': '<f4', 'fortran_order': True, 'shape': (2, 2), }\n",&data)[..]).unwrap();
+
+        assert!(fortran.fortran_order);
+        assert!(fortran.to_vec::<f32>(&[2,2]).is_err());
+
+        let truncated = NpyArray::read(&mut &npy_with_header("{'descr': '<f4', 'fortran_order': False, 'shape': (2, 2), }\n",&data[..12])[..]).unwrap();
+
+        assert!(truncated.to_vec::<f32>(&[2,2]).is_err());
+
+        assert!(NpyArray::read(&mut &npy_with_header("{'descr': '<f4', 'fortran_order': 1, 'shape': (4,), }\n",&data)[..]).is_err());
+        assert!(NpyArray::read(&mut &npy_with_header("{'descr': '<f4', 'fortran_order': False, 'shape': (a,), }\n",&data)[..]).is_err());
+        assert!(NpyArray::read(&mut &npy_with_header("{'descr': '<f4', 'fortran_order': False, }\n",&data)[..]).is_err());
+        assert!(NpyArray::read(&mut &b"\x93NUMPZ\x01\x00\x00\x00"[..]).is_err());
+    }
+
+    #[test]
+    fn test_npz_archive() {
+        let file = std::env::temp_dir().join(format!("nncombinator-convolution-arrays-{}.npz",std::process::id()));
+
+        {
+            let mut zip = zip::ZipWriter::new(std::fs::File::create(&file).unwrap());
+            let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+            zip.start_file("kernel.npy",options).unwrap();
+            zip.write_all(&npy(&[1,1,2,2],&[1f32,2.,3.,4.])).unwrap();
+
+            zip.start_file("labels.npy",options.compression_method(zip::CompressionMethod::Deflated)).unwrap();
+            zip.write_all(&npy(&[3],&[7u8,8,9])).unwrap();
+
+            zip.finish().unwrap();
+        }
+
+        let result = NpzArchive::open(&file).and_then(|mut archive| {
+            let mut names = archive.names();
+
+            names.sort();
+
+            Ok((names,archive.array("kernel")?,archive.array("labels.npy")?,archive.array("missing").is_err()))
+        });
+
+        std::fs::remove_file(&file).unwrap();
+
+        let (names,kernel,labels,missing) = result.unwrap();
+
+        assert_eq!(names,vec![String::from("kernel"),String::from("labels")]);
+        assert_eq!(kernel.to_arr4::<f32,1,1,2,2>(KernelLayout::OIHW).unwrap().as_raw_slice(),&[1.,2.,3.,4.]);
+        assert_eq!(labels.to_vec::<u8>(&[3]).unwrap(),vec![7,8,9]);
+        assert!(missing);
+    }
+}
@@ -0,0 +1,1210 @@
+use std::fs;
+use std::marker::PhantomData;
+use std::path::Path;
+use num_traits::ToPrimitive;
+use nncombinator::activation::{Identity, ReLu, Sigmoid, SoftMax, Swish, Tanh};
+use nncombinator::arr::{Arr, Arr2, Arr4};
+use nncombinator::device::Device;
+use nncombinator::error::{ConfigReadError, PersistenceError};
+use nncombinator::layer::{ActivationLayer, InputLayer, LinearLayer, LinearOutputLayer};
+use nncombinator::mem::AsRawSlice;
+use nncombinator::ope::UnitValue;
+use nncombinator::persistence::{Linear, Persistence};
+
+use crate::checkpoint::{CheckpointPersistence, TensorElement};
+use crate::layer::{verify_kernel_shape, BatchNormalization, ConvolutionLayer};
+
+/// Trait that defines the element types that can be exported to ONNX
+pub trait OnnxElement: TensorElement {
+    /// Value of TensorProto.DataType
+    const DATA_TYPE:i32;
+}
+impl OnnxElement for f32 {
+    const DATA_TYPE: i32 = 1;
+}
+impl OnnxElement for u8 {
+    const DATA_TYPE: i32 = 2;
+}
+impl OnnxElement for f64 {
+    const DATA_TYPE: i32 = 11;
+}
+/// Value of AttributeProto.AttributeType for FLOAT
+const ATTRIBUTE_TYPE_FLOAT:u64 = 1;
+/// Value of AttributeProto.AttributeType for INT
+const ATTRIBUTE_TYPE_INT:u64 = 2;
+/// Value of AttributeProto.AttributeType for INTS
+const ATTRIBUTE_TYPE_INTS:u64 = 7;
+/// IR version written to the model
+const IR_VERSION:u64 = 7;
+/// Version of the default operator set written to the model
+const OPSET_VERSION:u64 = 13;
+
+/// Implementation of an encoder of protocol buffers messages
+struct ProtoWriter {
+    buffer:Vec<u8>,
+}
+impl ProtoWriter {
+    fn new() -> ProtoWriter {
+        ProtoWriter {
+            buffer:Vec::new()
+        }
+    }
+
+    fn varint(&mut self,mut v:u64) {
+        while v >= 0x80 {
+            self.buffer.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        self.buffer.push(v as u8);
+    }
+
+    fn key(&mut self,field:u32,wire_type:u8) {
+        self.varint(((field as u64) << 3) | wire_type as u64);
+    }
+
+    fn uint(&mut self,field:u32,v:u64) {
+        self.key(field,0);
+        self.varint(v);
+    }
+
+    fn float(&mut self,field:u32,v:f32) {
+        self.key(field,5);
+        self.buffer.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn bytes(&mut self,field:u32,v:&[u8]) {
+        self.key(field,2);
+        self.varint(v.len() as u64);
+        self.buffer.extend_from_slice(v);
+    }
+
+    fn string(&mut self,field:u32,v:&str) {
+        self.bytes(field,v.as_bytes());
+    }
+
+    fn message(&mut self,field:u32,v:ProtoWriter) {
+        self.bytes(field,&v.buffer);
+    }
+}
+/// Implementation of a builder of ONNX models of convolutional networks
+///
+/// Each method appends a node that takes the tensor with the name given as `input`
+/// and returns the name of the tensor it outputs.
+/// The weights are stored as initializers in the layout of the Arr4 kernels (OIHW).
+pub struct OnnxGraphBuilder<U> where U: OnnxElement {
+    nodes:Vec<ProtoWriter>,
+    initializers:Vec<ProtoWriter>,
+    inputs:Vec<ProtoWriter>,
+    outputs:Vec<ProtoWriter>,
+    count:usize,
+    u:PhantomData<U>,
+}
+impl<U> OnnxGraphBuilder<U> where U: OnnxElement {
+    /// Create an instance of OnnxGraphBuilder
+    pub fn new() -> OnnxGraphBuilder<U> {
+        OnnxGraphBuilder {
+            nodes:Vec::new(),
+            initializers:Vec::new(),
+            inputs:Vec::new(),
+            outputs:Vec::new(),
+            count:0,
+            u:PhantomData::<U>
+        }
+    }
+
+    fn next_name(&mut self,prefix:&str) -> String {
+        self.count += 1;
+        format!("{}_{}",prefix,self.count)
+    }
+
+    fn value_info(name:&str,shape:Option<&[usize]>) -> ProtoWriter {
+        let mut tensor_type = ProtoWriter::new();
+
+        tensor_type.uint(1,U::DATA_TYPE as u64);
+
+        if let Some(shape) = shape {
+            let mut tensor_shape = ProtoWriter::new();
+
+            let mut batch = ProtoWriter::new();
+            batch.string(2,"N");
+            tensor_shape.message(1,batch);
+
+            for &d in shape.iter() {
+                let mut dim = ProtoWriter::new();
+                dim.uint(1,d as u64);
+                tensor_shape.message(1,dim);
+            }
+
+            tensor_type.message(2,tensor_shape);
+        }
+
+        let mut type_proto = ProtoWriter::new();
+        type_proto.message(1,tensor_type);
+
+        let mut value_info = ProtoWriter::new();
+        value_info.string(1,name);
+        value_info.message(2,type_proto);
+
+        value_info
+    }
+
+    fn initializer(&mut self,prefix:&str,dims:&[usize],data:&[U]) -> String {
+        let name = self.next_name(prefix);
+
+        let mut tensor = ProtoWriter::new();
+
+        for &d in dims.iter() {
+            tensor.uint(1,d as u64);
+        }
+
+        tensor.uint(2,U::DATA_TYPE as u64);
+        tensor.string(8,&name);
+
+        let mut raw = Vec::with_capacity(data.len() * U::SIZE);
+
+        for v in data.iter() {
+            v.write_le(&mut raw);
+        }
+
+        tensor.bytes(9,&raw);
+
+        self.initializers.push(tensor);
+
+        name
+    }
+
+    fn node(&mut self,op_type:&str,inputs:&[&str],attributes:Vec<ProtoWriter>) -> String {
+        let output = self.next_name(&op_type.to_lowercase());
+
+        let mut node = ProtoWriter::new();
+
+        for i in inputs.iter() {
+            node.string(1,i);
+        }
+
+        node.string(2,&output);
+        node.string(3,&format!("{}_node",output));
+        node.string(4,op_type);
+
+        for a in attributes.into_iter() {
+            node.message(5,a);
+        }
+
+        self.nodes.push(node);
+
+        output
+    }
+
+    fn attribute_ints(name:&str,values:&[usize]) -> ProtoWriter {
+        let mut attribute = ProtoWriter::new();
+
+        attribute.string(1,name);
+
+        for &v in values.iter() {
+            attribute.uint(8,v as u64);
+        }
+
+        attribute.uint(20,ATTRIBUTE_TYPE_INTS);
+
+        attribute
+    }
+
+    fn attribute_int(name:&str,value:usize) -> ProtoWriter {
+        let mut attribute = ProtoWriter::new();
+
+        attribute.string(1,name);
+        attribute.uint(3,value as u64);
+        attribute.uint(20,ATTRIBUTE_TYPE_INT);
+
+        attribute
+    }
+
+    fn attribute_float(name:&str,value:f32) -> ProtoWriter {
+        let mut attribute = ProtoWriter::new();
+
+        attribute.string(1,name);
+        attribute.float(2,value);
+        attribute.uint(20,ATTRIBUTE_TYPE_FLOAT);
+
+        attribute
+    }
+
+    /// Add an input of the graph
+    /// # Arguments
+    /// * `name` - Name of the input
+    /// * `shape` - Shape of the input excluding the batch dimension
+    pub fn input(&mut self,name:&str,shape:&[usize]) -> String {
+        self.inputs.push(Self::value_info(name,Some(shape)));
+
+        name.to_string()
+    }
+
+    /// Mark a tensor as an output of the graph
+    /// # Arguments
+    /// * `name` - Name of the tensor
+    pub fn output(&mut self,name:&str) {
+        self.outputs.push(Self::value_info(name,None));
+    }
+
+    /// Add a Conv node
+    /// # Arguments
+    /// * `input` - Name of the input tensor
+    /// * `kernel` - filter weights
+    /// * `bias` - bias of each output channel
+    /// * `pad` - padding of each side
+    /// * `stride` - stride
+    pub fn conv<const K:usize,const C:usize,const FH:usize,const FW:usize>(&mut self,input:&str,
+                                                                          kernel:&Arr4<U,K,C,FH,FW>,
+                                                                          bias:Option<&Arr<U,K>>,
+                                                                          pad:usize,stride:usize) -> String {
+        self.conv_values(input,&[K,C,FH,FW],kernel.as_raw_slice(),bias.map(|b| &b[..]),pad,stride)
+    }
+
+    fn conv_values(&mut self,input:&str,dims:&[usize;4],kernel:&[U],bias:Option<&[U]>,pad:usize,stride:usize) -> String {
+        let w = self.initializer("conv_weight",dims,kernel);
+
+        let b = bias.map(|b| self.initializer("conv_bias",&[dims[0]],b));
+
+        let mut inputs = vec![input,w.as_str()];
+
+        if let Some(b) = b.as_ref() {
+            inputs.push(b.as_str());
+        }
+
+        self.node("Conv",&inputs,vec![
+            Self::attribute_ints("kernel_shape",&[dims[2],dims[3]]),
+            Self::attribute_ints("pads",&[pad,pad,pad,pad]),
+            Self::attribute_ints("strides",&[stride,stride])
+        ])
+    }
+
+    /// Add a MaxPool node
+    /// # Arguments
+    /// * `input` - Name of the input tensor
+    /// * `kernel_shape` - height and width of the pooling window
+    /// * `pad` - padding of each side
+    /// * `stride` - stride
+    pub fn max_pool(&mut self,input:&str,kernel_shape:(usize,usize),pad:usize,stride:usize) -> String {
+        self.node("MaxPool",&[input],vec![
+            Self::attribute_ints("kernel_shape",&[kernel_shape.0,kernel_shape.1]),
+            Self::attribute_ints("pads",&[pad,pad,pad,pad]),
+            Self::attribute_ints("strides",&[stride,stride])
+        ])
+    }
+
+    /// Add a BatchNormalization node
+    /// # Arguments
+    /// * `input` - Name of the input tensor
+    /// * `scale` - scale of each channel
+    /// * `shift` - shift of each channel
+    /// * `mean` - running mean of each channel
+    /// * `variance` - running variance of each channel
+    /// * `epsilon` - value added to the variance
+    pub fn batch_normalization<const C:usize>(&mut self,input:&str,
+                                              scale:&Arr<U,C>,shift:&Arr<U,C>,
+                                              mean:&Arr<U,C>,variance:&Arr<U,C>,epsilon:f32) -> String {
+        let scale = self.initializer("bn_scale",&[C],scale);
+        let shift = self.initializer("bn_shift",&[C],shift);
+        let mean = self.initializer("bn_mean",&[C],mean);
+        let variance = self.initializer("bn_variance",&[C],variance);
+
+        self.node("BatchNormalization",&[input,scale.as_str(),shift.as_str(),mean.as_str(),variance.as_str()],vec![
+            Self::attribute_float("epsilon",epsilon)
+        ])
+    }
+
+    /// Add a Flatten node that flattens all dimensions except the batch dimension
+    /// # Arguments
+    /// * `input` - Name of the input tensor
+    pub fn flatten(&mut self,input:&str) -> String {
+        self.node("Flatten",&[input],vec![
+            Self::attribute_int("axis",1)
+        ])
+    }
+
+    /// Add a Gemm node corresponding to a fully connected layer of nncombinator
+    /// # Arguments
+    /// * `input` - Name of the input tensor
+    /// * `units` - weights in the layout of the number of inputs x the number of outputs
+    /// * `bias` - bias of each output
+    pub fn gemm<const NI:usize,const NO:usize>(&mut self,input:&str,units:&Arr2<U,NI,NO>,bias:&Arr<U,NO>) -> String {
+        self.gemm_values(input,NI,NO,units.as_raw_slice(),bias)
+    }
+
+    fn gemm_values(&mut self,input:&str,ni:usize,no:usize,units:&[U],bias:&[U]) -> String {
+        let w = self.initializer("gemm_weight",&[ni,no],units);
+        let b = self.initializer("gemm_bias",&[no],bias);
+
+        self.node("Gemm",&[input,w.as_str(),b.as_str()],vec![])
+    }
+
+    /// Add a node of an activation function without attributes, such as Relu, Sigmoid, Tanh or Softmax
+    /// # Arguments
+    /// * `input` - Name of the input tensor
+    /// * `op_type` - Name of the operator
+    pub fn activation(&mut self,input:&str,op_type:&str) -> String {
+        self.node(op_type,&[input],vec![])
+    }
+
+    /// Add the nodes of a layer
+    /// # Arguments
+    /// * `layer` - Layer to be exported
+    /// * `input` - Name of the input tensor
+    pub fn layer<L: OnnxExport<U>>(&mut self,layer:&L,input:&str) -> String {
+        layer.export_onnx(self,input)
+    }
+
+    /// Add the nodes of all the layers of a network, from the input layer to the last layer
+    ///
+    /// The parameters are taken from the values that the network saves with Persistence,
+    /// so the layers of nncombinator whose weights are not accessible can be exported as well.
+    ///
+    /// Only the layers that implement OnnxNetwork can be walked, that is InputLayer, ConvolutionLayer,
+    /// LinearLayer, ActivationLayer and LinearOutputLayer.
+    /// Pooling and normalization are not layers of a network in this crate, so they are not exported by this function.
+    /// Add them with max_pool, or with layer for a BatchNormalization, before or after the exported network.
+    /// # Arguments
+    /// * `network` - Network to be exported
+    /// * `input` - Name of the input tensor
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn network<N>(&mut self,network:&mut N,input:&str) -> Result<String,ConfigReadError>
+        where U: UnitValue<U>, N: OnnxNetwork<U> + Persistence<U,CheckpointPersistence<U>,Linear> {
+        let mut persistence = CheckpointPersistence::new();
+
+        network.save(&mut persistence).map_err(|e| {
+            ConfigReadError::InvalidState(format!("The parameters of the network could not be collected. ({})",e))
+        })?;
+
+        let mut values = persistence.values();
+
+        let output = N::export_network(self,&mut values,input)?;
+
+        if values.is_empty() {
+            Ok(output)
+        } else {
+            Err(ConfigReadError::InvalidState(format!(
+                "The network saved {} values that were not exported.",values.len()
+            )))
+        }
+    }
+
+    /// Encode the model as ONNX protobuf
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut graph = ProtoWriter::new();
+
+        for n in self.nodes.iter() {
+            graph.bytes(1,&n.buffer);
+        }
+
+        graph.string(2,"nncombinator_cnn");
+
+        for i in self.initializers.iter() {
+            graph.bytes(5,&i.buffer);
+        }
+
+        for i in self.inputs.iter() {
+            graph.bytes(11,&i.buffer);
+        }
+
+        for o in self.outputs.iter() {
+            graph.bytes(12,&o.buffer);
+        }
+
+        let mut opset = ProtoWriter::new();
+        opset.string(1,"");
+        opset.uint(2,OPSET_VERSION);
+
+        let mut model = ProtoWriter::new();
+
+        model.uint(1,IR_VERSION);
+        model.string(2,"nncombinator_cnn");
+        model.message(7,graph);
+        model.message(8,opset);
+
+        model.buffer
+    }
+
+    /// Write the model to a file
+    /// # Arguments
+    /// * `file` - Path of the file to be written
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`PersistenceError`]
+    pub fn save<P: AsRef<Path>>(&self,file:P) -> Result<(),PersistenceError> {
+        fs::write(file,self.to_bytes())?;
+
+        Ok(())
+    }
+}
+/// Trait that defines the export of a layer as ONNX nodes
+pub trait OnnxExport<U> where U: OnnxElement {
+    /// Append the nodes of the layer to the graph and return the name of the output tensor
+    /// # Arguments
+    /// * `builder` - Graph to which the nodes are added
+    /// * `input` - Name of the input tensor
+    fn export_onnx(&self,builder:&mut OnnxGraphBuilder<U>,input:&str) -> String;
+}
+impl<U,P,D,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH:usize,const FW:usize,const PAD:usize,const S:usize> OnnxExport<U> for ConvolutionLayer<U,P,D,C,K,H,W,FH,FW,PAD,S>
+    where U: UnitValue<U> + OnnxElement {
+    fn export_onnx(&self, builder: &mut OnnxGraphBuilder<U>, input: &str) -> String {
+        builder.conv(input,self.kernel(),Some(self.bias()),PAD,S)
+    }
+}
+impl<U,const K:usize> OnnxExport<U> for BatchNormalization<U,K>
+    where U: UnitValue<U> + OnnxElement {
+    fn export_onnx(&self, builder: &mut OnnxGraphBuilder<U>, input: &str) -> String {
+        builder.batch_normalization(input,self.scale(),self.shift(),self.mean(),self.variance(),self.epsilon() as f32)
+    }
+}
+/// Trait that defines the export of a layer and all its upper layers while walking a network
+///
+/// Each layer exports its upper layer first and then takes its own parameters from the front of `values`,
+/// which holds the values saved by the network with Persistence in the same order.
+pub trait OnnxNetwork<U> where U: OnnxElement {
+    /// Append the nodes of the upper layers and this layer to the graph and return the name of the output tensor
+    /// # Arguments
+    /// * `builder` - Graph to which the nodes are added
+    /// * `values` - Parameters of the network that are not exported yet
+    /// * `input` - Name of the input tensor of the network
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    fn export_network(builder:&mut OnnxGraphBuilder<U>,values:&mut &[U],input:&str) -> Result<String,ConfigReadError>;
+}
+/// Take the parameters of a layer from the front of the values saved by the network
+fn take_values<'a,U>(values:&mut &'a [U],len:usize,layer:&str) -> Result<&'a [U],ConfigReadError> {
+    if values.len() < len {
+        return Err(ConfigReadError::InvalidState(format!(
+            "The network did not save enough values for the {} layer. (expected {}, actual {})",layer,len,values.len()
+        )));
+    }
+
+    let (l,r) = values.split_at(len);
+
+    *values = r;
+
+    Ok(l)
+}
+impl<U,O,LI> OnnxNetwork<U> for InputLayer<U,O,LI> where U: UnitValue<U> + OnnxElement {
+    fn export_network(_: &mut OnnxGraphBuilder<U>, _: &mut &[U], input: &str) -> Result<String,ConfigReadError> {
+        Ok(input.to_string())
+    }
+}
+impl<U,P,D,const C:usize,const K:usize,const H:usize,const W:usize,
+    const FH:usize,const FW:usize,const PAD:usize,const S:usize> OnnxNetwork<U> for ConvolutionLayer<U,P,D,C,K,H,W,FH,FW,PAD,S>
+    where U: UnitValue<U> + OnnxElement + ToPrimitive, P: OnnxNetwork<U> {
+    fn export_network(builder: &mut OnnxGraphBuilder<U>, values: &mut &[U], input: &str) -> Result<String,ConfigReadError> {
+        let input = P::export_network(builder,values,input)?;
+
+        let shape = take_values(values,4,"convolution")?;
+
+        verify_kernel_shape("K",K,shape[0])?;
+        verify_kernel_shape("C",C,shape[1])?;
+        verify_kernel_shape("FH",FH,shape[2])?;
+        verify_kernel_shape("FW",FW,shape[3])?;
+
+        let bias = take_values(values,K,"convolution")?;
+        let kernel = take_values(values,K * C * FH * FW,"convolution")?;
+
+        Ok(builder.conv_values(&input,&[K,C,FH,FW],kernel,Some(bias),PAD,S))
+    }
+}
+impl<U,C,P,D,I,const NI:usize,const NO:usize> OnnxNetwork<U> for LinearLayer<U,C,P,D,I,NI,NO>
+    where U: UnitValue<U> + OnnxElement, P: OnnxNetwork<U>, D: Device<U> {
+    fn export_network(builder: &mut OnnxGraphBuilder<U>, values: &mut &[U], input: &str) -> Result<String,ConfigReadError> {
+        let input = P::export_network(builder,values,input)?;
+
+        let bias = take_values(values,NO,"linear")?;
+        let units = take_values(values,NI * NO,"linear")?;
+
+        let input = builder.flatten(&input);
+
+        Ok(builder.gemm_values(&input,NI,NO,units,bias))
+    }
+}
+impl<U,P,A,I,PI,D,const N:usize> OnnxNetwork<U> for ActivationLayer<U,P,A,I,PI,D,N>
+    where U: UnitValue<U> + OnnxElement, P: OnnxNetwork<U>, A: OnnxActivation<U>, D: Device<U> {
+    fn export_network(builder: &mut OnnxGraphBuilder<U>, values: &mut &[U], input: &str) -> Result<String,ConfigReadError> {
+        let input = P::export_network(builder,values,input)?;
+
+        Ok(A::export_activation(builder,&input))
+    }
+}
+impl<U,P,D,I,IO> OnnxNetwork<U> for LinearOutputLayer<U,P,D,I,IO>
+    where U: UnitValue<U> + OnnxElement, P: OnnxNetwork<U>, D: Device<U> {
+    fn export_network(builder: &mut OnnxGraphBuilder<U>, values: &mut &[U], input: &str) -> Result<String,ConfigReadError> {
+        P::export_network(builder,values,input)
+    }
+}
+/// Trait that defines the export of an activation function of nncombinator as ONNX nodes
+pub trait OnnxActivation<U> where U: OnnxElement {
+    /// Append the nodes of the activation function to the graph and return the name of the output tensor
+    /// # Arguments
+    /// * `builder` - Graph to which the nodes are added
+    /// * `input` - Name of the input tensor
+    fn export_activation(builder:&mut OnnxGraphBuilder<U>,input:&str) -> String;
+}
+impl<U,D> OnnxActivation<U> for Identity<U,D> where U: UnitValue<U> + OnnxElement, D: Device<U> {
+    fn export_activation(builder: &mut OnnxGraphBuilder<U>, input: &str) -> String {
+        builder.activation(input,"Identity")
+    }
+}
+impl<U,D> OnnxActivation<U> for ReLu<U,D> where U: UnitValue<U> + OnnxElement, D: Device<U> {
+    fn export_activation(builder: &mut OnnxGraphBuilder<U>, input: &str) -> String {
+        builder.activation(input,"Relu")
+    }
+}
+impl<U,D> OnnxActivation<U> for Sigmoid<U,D> where U: UnitValue<U> + OnnxElement, D: Device<U> {
+    fn export_activation(builder: &mut OnnxGraphBuilder<U>, input: &str) -> String {
+        builder.activation(input,"Sigmoid")
+    }
+}
+impl<U,D> OnnxActivation<U> for Tanh<U,D> where U: UnitValue<U> + OnnxElement, D: Device<U> {
+    fn export_activation(builder: &mut OnnxGraphBuilder<U>, input: &str) -> String {
+        builder.activation(input,"Tanh")
+    }
+}
+impl<U,D> OnnxActivation<U> for SoftMax<U,D> where U: UnitValue<U> + OnnxElement, D: Device<U> {
+    fn export_activation(builder: &mut OnnxGraphBuilder<U>, input: &str) -> String {
+        builder.activation(input,"Softmax")
+    }
+}
+impl<U,D> OnnxActivation<U> for Swish<U,D> where U: UnitValue<U> + OnnxElement, D: Device<U> {
+    fn export_activation(builder: &mut OnnxGraphBuilder<U>, input: &str) -> String {
+        let s = builder.activation(input,"Sigmoid");
+
+        builder.node("Mul",&[input,s.as_str()],vec![])
+    }
+}
+/// Attribute of a node read from an ONNX model
+#[derive(Debug,Clone,PartialEq)]
+pub enum OnnxAttribute {
+    /// Float value
+    Float(f32),
+    /// Integer value
+    Int(i64),
+    /// List of integers
+    Ints(Vec<i64>),
+    /// Attribute of a type that is not interpreted
+    Other,
+}
+/// Node read from an ONNX model
+#[derive(Debug,Clone,PartialEq)]
+pub struct OnnxNode {
+    /// Name of the operator
+    pub op_type:String,
+    /// Names of the input tensors
+    pub inputs:Vec<String>,
+    /// Names of the output tensors
+    pub outputs:Vec<String>,
+    /// Attributes of the node
+    pub attributes:Vec<(String,OnnxAttribute)>,
+}
+/// Initializer read from an ONNX model
+#[derive(Debug,Clone,PartialEq)]
+pub struct OnnxTensor {
+    /// Name of the tensor
+    pub name:String,
+    /// Shape of the tensor
+    pub dims:Vec<usize>,
+    /// Value of TensorProto.DataType
+    pub data_type:i32,
+    /// Raw little-endian data
+    pub raw_data:Vec<u8>,
+}
+impl OnnxTensor {
+    /// Decode the raw data into elements
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn to_vec<U: OnnxElement>(&self) -> Result<Vec<U>,ConfigReadError> {
+        if self.data_type != U::DATA_TYPE {
+            Err(ConfigReadError::InvalidState(format!(
+                "The data type of the initializer {} does not match. (expected {}, actual {})",self.name,U::DATA_TYPE,self.data_type
+            )))
+        } else if self.raw_data.len() != self.dims.iter().product::<usize>() * U::SIZE {
+            Err(ConfigReadError::InvalidState(format!(
+                "The size of the raw data of the initializer {} does not match its shape.",self.name
+            )))
+        } else {
+            Ok(self.raw_data.chunks(U::SIZE).map(U::read_le).collect())
+        }
+    }
+}
+/// Contents of an ONNX model read back from protobuf
+#[derive(Debug,Clone,PartialEq)]
+pub struct OnnxModel {
+    /// IR version
+    pub ir_version:u64,
+    /// Version of the default operator set
+    pub opset_version:u64,
+    /// Nodes of the graph
+    pub nodes:Vec<OnnxNode>,
+    /// Initializers of the graph
+    pub initializers:Vec<OnnxTensor>,
+    /// Names of the inputs of the graph
+    pub inputs:Vec<String>,
+    /// Names of the outputs of the graph
+    pub outputs:Vec<String>,
+}
+/// Field of a protocol buffers message
+enum ProtoValue<'a> {
+    Varint(u64),
+    Fixed64,
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+/// Implementation of a decoder of protocol buffers messages
+struct ProtoReader<'a> {
+    bytes:&'a [u8],
+}
+impl<'a> ProtoReader<'a> {
+    fn error() -> ConfigReadError {
+        ConfigReadError::InvalidState(String::from("The ONNX model is not a valid protobuf message."))
+    }
+
+    fn varint(&mut self) -> Result<u64,ConfigReadError> {
+        let mut v = 0u64;
+        let mut shift = 0;
+
+        loop {
+            let (&b,rest) = self.bytes.split_first().ok_or_else(Self::error)?;
+
+            self.bytes = rest;
+
+            if shift >= 64 {
+                return Err(Self::error());
+            }
+
+            v |= ((b & 0x7f) as u64) << shift;
+
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+
+            shift += 7;
+        }
+    }
+
+    fn take(&mut self,len:usize) -> Result<&'a [u8],ConfigReadError> {
+        if self.bytes.len() < len {
+            Err(Self::error())
+        } else {
+            let (l,r) = self.bytes.split_at(len);
+            self.bytes = r;
+            Ok(l)
+        }
+    }
+
+    fn next_field(&mut self) -> Result<Option<(u32,ProtoValue<'a>)>,ConfigReadError> {
+        if self.bytes.is_empty() {
+            return Ok(None);
+        }
+
+        let key = self.varint()?;
+        let field = (key >> 3) as u32;
+
+        let value = match key & 7 {
+            0 => ProtoValue::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                ProtoValue::Fixed64
+            },
+            2 => {
+                let len = self.varint()? as usize;
+                ProtoValue::Bytes(self.take(len)?)
+            },
+            5 => {
+                let b = self.take(4)?;
+                ProtoValue::Fixed32(u32::from_le_bytes([b[0],b[1],b[2],b[3]]))
+            },
+            _ => return Err(Self::error())
+        };
+
+        Ok(Some((field,value)))
+    }
+}
+fn proto_string(bytes:&[u8]) -> Result<String,ConfigReadError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| ProtoReader::error())
+}
+/// Read the repeated int64 values of a field that may be either packed or unpacked
+fn proto_push_ints(values:&mut Vec<i64>,value:ProtoValue) -> Result<(),ConfigReadError> {
+    match value {
+        ProtoValue::Varint(v) => values.push(v as i64),
+        ProtoValue::Bytes(b) => {
+            let mut reader = ProtoReader { bytes: b };
+
+            while !reader.bytes.is_empty() {
+                values.push(reader.varint()? as i64);
+            }
+        },
+        _ => return Err(ProtoReader::error())
+    }
+
+    Ok(())
+}
+fn parse_attribute(bytes:&[u8]) -> Result<(String,OnnxAttribute),ConfigReadError> {
+    let mut reader = ProtoReader { bytes: bytes };
+
+    let mut name = String::new();
+    let mut f = None;
+    let mut i = None;
+    let mut ints = Vec::new();
+    let mut attribute_type = 0;
+
+    while let Some((field,value)) = reader.next_field()? {
+        match (field,value) {
+            (1,ProtoValue::Bytes(b)) => name = proto_string(b)?,
+            (2,ProtoValue::Fixed32(v)) => f = Some(f32::from_bits(v)),
+            (3,ProtoValue::Varint(v)) => i = Some(v as i64),
+            (8,value) => proto_push_ints(&mut ints,value)?,
+            (20,ProtoValue::Varint(v)) => attribute_type = v,
+            _ => ()
+        }
+    }
+
+    let attribute = match (attribute_type,f,i) {
+        (ATTRIBUTE_TYPE_FLOAT,Some(f),_) => OnnxAttribute::Float(f),
+        (ATTRIBUTE_TYPE_INT,_,Some(i)) => OnnxAttribute::Int(i),
+        (ATTRIBUTE_TYPE_INTS,_,_) => OnnxAttribute::Ints(ints),
+        _ => OnnxAttribute::Other
+    };
+
+    Ok((name,attribute))
+}
+fn parse_node(bytes:&[u8]) -> Result<OnnxNode,ConfigReadError> {
+    let mut reader = ProtoReader { bytes: bytes };
+
+    let mut node = OnnxNode {
+        op_type:String::new(),
+        inputs:Vec::new(),
+        outputs:Vec::new(),
+        attributes:Vec::new()
+    };
+
+    while let Some((field,value)) = reader.next_field()? {
+        match (field,value) {
+            (1,ProtoValue::Bytes(b)) => node.inputs.push(proto_string(b)?),
+            (2,ProtoValue::Bytes(b)) => node.outputs.push(proto_string(b)?),
+            (4,ProtoValue::Bytes(b)) => node.op_type = proto_string(b)?,
+            (5,ProtoValue::Bytes(b)) => node.attributes.push(parse_attribute(b)?),
+            _ => ()
+        }
+    }
+
+    Ok(node)
+}
+fn parse_tensor(bytes:&[u8]) -> Result<OnnxTensor,ConfigReadError> {
+    let mut reader = ProtoReader { bytes: bytes };
+
+    let mut dims = Vec::new();
+
+    let mut tensor = OnnxTensor {
+        name:String::new(),
+        dims:Vec::new(),
+        data_type:0,
+        raw_data:Vec::new()
+    };
+
+    while let Some((field,value)) = reader.next_field()? {
+        match (field,value) {
+            (1,value) => proto_push_ints(&mut dims,value)?,
+            (2,ProtoValue::Varint(v)) => tensor.data_type = v as i32,
+            (8,ProtoValue::Bytes(b)) => tensor.name = proto_string(b)?,
+            (9,ProtoValue::Bytes(b)) => tensor.raw_data = b.to_vec(),
+            _ => ()
+        }
+    }
+
+    tensor.dims = dims.into_iter().map(|d| d as usize).collect();
+
+    Ok(tensor)
+}
+fn parse_value_info_name(bytes:&[u8]) -> Result<String,ConfigReadError> {
+    let mut reader = ProtoReader { bytes: bytes };
+
+    while let Some((field,value)) = reader.next_field()? {
+        if let (1,ProtoValue::Bytes(b)) = (field,value) {
+            return proto_string(b);
+        }
+    }
+
+    Ok(String::new())
+}
+impl OnnxModel {
+    /// Parse an ONNX model from protobuf
+    /// # Arguments
+    /// * `bytes` - Contents of the ONNX file
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn parse(bytes:&[u8]) -> Result<OnnxModel,ConfigReadError> {
+        let mut reader = ProtoReader { bytes: bytes };
+
+        let mut model = OnnxModel {
+            ir_version:0,
+            opset_version:0,
+            nodes:Vec::new(),
+            initializers:Vec::new(),
+            inputs:Vec::new(),
+            outputs:Vec::new()
+        };
+
+        while let Some((field,value)) = reader.next_field()? {
+            match (field,value) {
+                (1,ProtoValue::Varint(v)) => model.ir_version = v,
+                (7,ProtoValue::Bytes(b)) => {
+                    let mut graph = ProtoReader { bytes: b };
+
+                    while let Some((field,value)) = graph.next_field()? {
+                        match (field,value) {
+                            (1,ProtoValue::Bytes(b)) => model.nodes.push(parse_node(b)?),
+                            (5,ProtoValue::Bytes(b)) => model.initializers.push(parse_tensor(b)?),
+                            (11,ProtoValue::Bytes(b)) => model.inputs.push(parse_value_info_name(b)?),
+                            (12,ProtoValue::Bytes(b)) => model.outputs.push(parse_value_info_name(b)?),
+                            _ => ()
+                        }
+                    }
+                },
+                (8,ProtoValue::Bytes(b)) => {
+                    let mut opset = ProtoReader { bytes: b };
+
+                    while let Some((field,value)) = opset.next_field()? {
+                        if let (2,ProtoValue::Varint(v)) = (field,value) {
+                            model.opset_version = v;
+                        }
+                    }
+                },
+                _ => ()
+            }
+        }
+
+        Ok(model)
+    }
+
+    /// Read an ONNX model from a file
+    /// # Arguments
+    /// * `file` - Path of the file to be read
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`ConfigReadError`]
+    pub fn load<P: AsRef<Path>>(file:P) -> Result<OnnxModel,ConfigReadError> {
+        let bytes = fs::read(file)?;
+
+        OnnxModel::parse(&bytes)
+    }
+
+    /// get the initializer with the specified name
+    /// # Arguments
+    /// * `name` - Name of the initializer
+    pub fn initializer(&self,name:&str) -> Option<&OnnxTensor> {
+        self.initializers.iter().find(|i| i.name == name)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use nncombinator::activation::{ReLu, Swish};
+    use nncombinator::arr::{Arr, Arr2, Arr4};
+    use nncombinator::device::DeviceCpu;
+    use nncombinator::error::{ConfigReadError, PersistenceError};
+    use nncombinator::layer::{ActivationLayer, AddLayer, AddLayerTrain, ForwardAll, InputLayer, LinearLayer, LinearOutputLayer};
+    use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+    use nncombinator::persistence::{Linear, LinearPersistence, Persistence};
+
+    use crate::layer::{BatchNormalization, ConvolutionLayer};
+    use crate::onnx::{OnnxAttribute, OnnxGraphBuilder, OnnxModel, OnnxNetwork};
+
+    struct TestInputLayer {
+        values:Vec<f32>,
+    }
+    impl<T> Persistence<f32,T,Linear> for TestInputLayer where T: LinearPersistence<f32> {
+        fn load(&mut self, persistence: &mut T) -> Result<(), ConfigReadError> {
+            for v in self.values.iter_mut() {
+                *v = persistence.read()?;
+            }
+
+            Ok(())
+        }
+
+        fn save(&mut self, persistence: &mut T) -> Result<(), PersistenceError> {
+            for v in self.values.iter() {
+                persistence.write(*v)?;
+            }
+
+            Ok(())
+        }
+    }
+    impl OnnxNetwork<f32> for TestInputLayer {
+        fn export_network(_: &mut OnnxGraphBuilder<f32>, _: &mut &[f32], input: &str) -> Result<String, ConfigReadError> {
+            Ok(input.to_string())
+        }
+    }
+
+    fn sequence(n:usize,scale:f32) -> Vec<f32> {
+        (0..n).map(|i| (i as f32 - n as f32 / 2.) * scale).collect()
+    }
+
+    fn variance() -> Arr<f32,4> {
+        let mut variance = Arr::<f32,4>::new();
+
+        variance.copy_from_slice(&[1.,2.,3.,4.]);
+
+        variance
+    }
+
+    fn op_types(model:&OnnxModel) -> Vec<&str> {
+        model.nodes.iter().map(|n| n.op_type.as_str()).collect()
+    }
+
+    fn attribute<'a>(model:&'a OnnxModel,node:usize,name:&str) -> &'a OnnxAttribute {
+        &model.nodes[node].attributes.iter().find(|(n,_)| n == name).expect("The attribute does not exist.").1
+    }
+
+    fn tensor(model:&OnnxModel,name:&str) -> (Vec<usize>,Vec<f32>) {
+        let t = model.initializer(name).expect("The initializer does not exist.");
+
+        (t.dims.clone(),t.to_vec::<f32>().unwrap())
+    }
+
+    /// Verify that each node takes the output of the previous node as its first input
+    fn assert_chained(model:&OnnxModel,input:&str) {
+        let mut previous = input.to_string();
+
+        for n in model.nodes.iter() {
+            assert_eq!(n.inputs[0],previous,"{} does not take the previous output",n.op_type);
+
+            previous = n.outputs[0].clone();
+        }
+
+        assert_eq!(model.outputs,vec![previous]);
+    }
+
+    #[test]
+    fn test_builder_round_trip() {
+        let mut kernel = Arr4::<f32,4,2,3,3>::new();
+
+        kernel.as_raw_mut_slice().copy_from_slice(&sequence(4 * 2 * 3 * 3,0.125));
+
+        let mut bias = Arr::<f32,4>::new();
+
+        bias.copy_from_slice(&sequence(4,0.5));
+
+        let mut units = Arr2::<f32,64,10>::new();
+
+        units.as_raw_mut_slice().copy_from_slice(&sequence(64 * 10,0.01));
+
+        let mut gemm_bias = Arr::<f32,10>::new();
+
+        gemm_bias.copy_from_slice(&sequence(10,0.25));
+
+        let mut builder = OnnxGraphBuilder::<f32>::new();
+
+        let x = builder.input("x",&[2,8,8]);
+        let y = builder.conv(&x,&kernel,Some(&bias),1,1);
+        let y = builder.batch_normalization(&y,&bias,&bias,&bias,&variance(),1e-3);
+        let y = builder.activation(&y,"Relu");
+        let y = builder.max_pool(&y,(2,2),0,2);
+        let y = builder.flatten(&y);
+        let y = builder.gemm(&y,&units,&gemm_bias);
+        let y = builder.activation(&y,"Softmax");
+
+        builder.output(&y);
+
+        let model = OnnxModel::parse(&builder.to_bytes()).unwrap();
+
+        assert_eq!(model.ir_version,7);
+        assert_eq!(model.opset_version,13);
+        assert_eq!(model.inputs,vec![String::from("x")]);
+        assert_eq!(op_types(&model),vec!["Conv","BatchNormalization","Relu","MaxPool","Flatten","Gemm","Softmax"]);
+        assert_chained(&model,"x");
+
+        assert_eq!(attribute(&model,0,"kernel_shape"),&OnnxAttribute::Ints(vec![3,3]));
+        assert_eq!(attribute(&model,0,"pads"),&OnnxAttribute::Ints(vec![1,1,1,1]));
+        assert_eq!(attribute(&model,0,"strides"),&OnnxAttribute::Ints(vec![1,1]));
+        assert_eq!(attribute(&model,1,"epsilon"),&OnnxAttribute::Float(1e-3));
+        assert_eq!(attribute(&model,3,"strides"),&OnnxAttribute::Ints(vec![2,2]));
+        assert_eq!(attribute(&model,4,"axis"),&OnnxAttribute::Int(1));
+
+        let conv = &model.nodes[0];
+
+        assert_eq!(tensor(&model,&conv.inputs[1]),(vec![4,2,3,3],kernel.as_raw_slice().to_vec()));
+        assert_eq!(tensor(&model,&conv.inputs[2]),(vec![4],bias.to_vec()));
+
+        let gemm = &model.nodes[5];
+
+        assert_eq!(tensor(&model,&gemm.inputs[1]),(vec![64,10],units.as_raw_slice().to_vec()));
+        assert_eq!(tensor(&model,&gemm.inputs[2]),(vec![10],gemm_bias.to_vec()));
+
+        assert_eq!(model.nodes[1].inputs.len(),5);
+        assert_eq!(model.initializers.len(),8);
+    }
+
+    #[test]
+    fn test_parse_rejects_truncated_model() {
+        let mut builder = OnnxGraphBuilder::<f32>::new();
+
+        let x = builder.input("x",&[4]);
+        let y = builder.activation(&x,"Relu");
+
+        builder.output(&y);
+
+        let bytes = builder.to_bytes();
+
+        assert!(OnnxModel::parse(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_network_export_convolution() {
+        let mut n = 0.;
+
+        let net = ConvolutionLayer::<f32,_,(),2,3,6,6,3,3,1,1>::new(TestInputLayer { values:Vec::new() },&(),|| { n += 0.125; n },|| 0.5);
+        let mut net = ConvolutionLayer::<f32,_,(),3,4,6,6,3,3,0,1>::new(net,&(),|| { n -= 0.25; n },|| -1.);
+
+        let mut builder = OnnxGraphBuilder::<f32>::new();
+
+        let x = builder.input("x",&[2,6,6]);
+        let y = builder.network(&mut net,&x).unwrap();
+
+        builder.output(&y);
+
+        let model = OnnxModel::parse(&builder.to_bytes()).unwrap();
+
+        assert_eq!(op_types(&model),vec!["Conv","Conv"]);
+        assert_chained(&model,"x");
+
+        assert_eq!(attribute(&model,0,"pads"),&OnnxAttribute::Ints(vec![1,1,1,1]));
+        assert_eq!(attribute(&model,1,"pads"),&OnnxAttribute::Ints(vec![0,0,0,0]));
+
+        assert_eq!(tensor(&model,&model.nodes[0].inputs[1]),(vec![3,2,3,3],net.parent().kernel().as_raw_slice().to_vec()));
+        assert_eq!(tensor(&model,&model.nodes[0].inputs[2]),(vec![3],net.parent().bias().to_vec()));
+        assert_eq!(tensor(&model,&model.nodes[1].inputs[1]),(vec![4,3,3,3],net.kernel().as_raw_slice().to_vec()));
+        assert_eq!(tensor(&model,&model.nodes[1].inputs[2]),(vec![4],net.bias().to_vec()));
+    }
+
+    #[test]
+    fn test_network_export_rejects_values_of_unknown_layers() {
+        let mut net = ConvolutionLayer::<f32,_,(),2,3,6,6,3,3,1,1>::new(TestInputLayer { values:vec![1.; 3] },&(),|| 1.,|| 0.);
+
+        let mut builder = OnnxGraphBuilder::<f32>::new();
+
+        let x = builder.input("x",&[2,6,6]);
+
+        assert!(builder.network(&mut net,&x).is_err());
+    }
+
+    #[test]
+    fn test_network_export_dense_and_activation_layers() {
+        let device = DeviceCpu::<f32>::new().unwrap();
+
+        let mut n = 0.;
+        let mut b = 0.;
+
+        let net:InputLayer<f32,Arr<f32,4>,_> = InputLayer::new();
+
+        let mut net = net.add_layer(|l| {
+            LinearLayer::<_,_,_,DeviceCpu<f32>,_,4,3>::new(l,&device,|| { n += 0.125; n },|| { b += 1.; b })
+        }).add_layer(|l| {
+            ActivationLayer::new(l,ReLu::new(&device),&device)
+        }).add_layer(|l| {
+            LinearLayer::<_,_,_,DeviceCpu<f32>,_,3,2>::new(l,&device,|| { n -= 0.25; n },|| { b -= 0.5; b })
+        }).add_layer(|l| {
+            ActivationLayer::new(l,Swish::new(&device),&device)
+        }).add_layer_train(|l| {
+            LinearOutputLayer::new(l,&device)
+        });
+
+        let mut builder = OnnxGraphBuilder::<f32>::new();
+
+        let x = builder.input("x",&[4]);
+        let y = builder.network(&mut net,&x).unwrap();
+
+        builder.output(&y);
+
+        let model = OnnxModel::parse(&builder.to_bytes()).unwrap();
+
+        assert_eq!(op_types(&model),vec!["Flatten","Gemm","Relu","Flatten","Gemm","Sigmoid","Mul"]);
+        assert_eq!(model.nodes[6].inputs,vec![model.nodes[4].outputs[0].clone(),model.nodes[5].outputs[0].clone()]);
+        assert_eq!(model.outputs,vec![model.nodes[6].outputs[0].clone()]);
+
+        // LinearLayer fills its units row by row and then its bias, each from its own initializer,
+        // so the expected weights follow from the initializers and not from the order of persistence
+        let units1 = (1..=12).map(|i| 0.125 * i as f32).collect::<Vec<f32>>();
+        let bias1 = vec![1.,2.,3.];
+        let units2 = (1..=6).map(|i| 1.5 - 0.25 * i as f32).collect::<Vec<f32>>();
+        let bias2 = vec![2.5,2.];
+
+        let (dims,w1) = tensor(&model,&model.nodes[1].inputs[1]);
+        assert_eq!((dims,w1.clone()),(vec![4,3],units1));
+        let (dims,b1) = tensor(&model,&model.nodes[1].inputs[2]);
+        assert_eq!((dims,b1.clone()),(vec![3],bias1));
+        let (dims,w2) = tensor(&model,&model.nodes[4].inputs[1]);
+        assert_eq!((dims,w2.clone()),(vec![3,2],units2));
+        let (dims,b2) = tensor(&model,&model.nodes[4].inputs[2]);
+        assert_eq!((dims,b2.clone()),(vec![2],bias2));
+
+        let mut input = Arr::<f32,4>::new();
+
+        input.copy_from_slice(&[0.5,-1.,0.25,2.]);
+
+        let expected = net.forward_all(input.clone()).unwrap();
+
+        let hidden = (0..3).map(|o| {
+            (b1[o] + (0..4).map(|i| input[i] * w1[i * 3 + o]).sum::<f32>()).max(0.)
+        }).collect::<Vec<f32>>();
+
+        for o in 0..2 {
+            let z = b2[o] + (0..3).map(|i| hidden[i] * w2[i * 2 + o]).sum::<f32>();
+            let y = z / (1. + (-z).exp());
+
+            assert!((expected[o] - y).abs() <= 1e-4 * y.abs().max(1.),"{} {}",expected[o],y);
+        }
+    }
+
+    #[test]
+    fn test_batch_normalization_export() {
+        let mut scale = Arr::<f32,4>::new();
+        let mut shift = Arr::<f32,4>::new();
+        let mut mean = Arr::<f32,4>::new();
+
+        scale.copy_from_slice(&sequence(4,0.5));
+        shift.copy_from_slice(&sequence(4,0.25));
+        mean.copy_from_slice(&sequence(4,0.125));
+
+        let batch_norm = BatchNormalization::new(scale.clone(),shift.clone(),mean.clone(),variance(),1e-3);
+
+        let mut builder = OnnxGraphBuilder::<f32>::new();
+
+        let x = builder.input("x",&[4,2,2]);
+        let y = builder.layer(&batch_norm,&x);
+
+        builder.output(&y);
+
+        let model = OnnxModel::parse(&builder.to_bytes()).unwrap();
+
+        assert_eq!(op_types(&model),vec!["BatchNormalization"]);
+        assert_chained(&model,"x");
+        assert_eq!(attribute(&model,0,"epsilon"),&OnnxAttribute::Float(1e-3));
+
+        let node = &model.nodes[0];
+
+        assert_eq!(tensor(&model,&node.inputs[1]),(vec![4],scale.to_vec()));
+        assert_eq!(tensor(&model,&node.inputs[2]),(vec![4],shift.to_vec()));
+        assert_eq!(tensor(&model,&node.inputs[3]),(vec![4],mean.to_vec()));
+        assert_eq!(tensor(&model,&node.inputs[4]),(vec![4],variance().to_vec()));
+    }
+}
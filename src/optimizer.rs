@@ -0,0 +1,276 @@
+use std::marker::PhantomData;
+use nncombinator::arr::{Arr, Arr4};
+use nncombinator::error::SizeMismatchError;
+use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+use nncombinator::ope::UnitValue;
+use nncombinator::optimizer::Optimizer;
+
+/// Kind of a parameter, used to decide whether weight decay is applied
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ParameterKind {
+    /// Convolution kernels and other weights
+    Weight,
+    /// Biases
+    Bias,
+    /// Scale and shift of normalization layers
+    Normalization,
+}
+/// Trait defining an optimizer of one value whose state can be read and restored, used to store it in a checkpoint
+pub trait OptimizerValueState<U> {
+    /// Number of values of the state
+    fn state_len(&self) -> usize;
+
+    /// Write the state into a slice of state_len elements
+    /// # Arguments
+    /// * `dst` - Slice to which the state is written
+    fn write_state(&self,dst:&mut [U]);
+
+    /// Restore the state from a slice of state_len elements
+    /// # Arguments
+    /// * `src` - Slice from which the state is read
+    fn read_state(&mut self,src:&[U]);
+}
+/// Optimizer of one parameter built from an nncombinator optimizer
+///
+/// An instance of the optimizer is kept for each value of the parameter,
+/// so the state of the optimizer has the same shape as the parameter.
+pub struct ParameterOptimizer<U,O> where U: UnitValue<U>, O: Optimizer<U> {
+    kind:ParameterKind,
+    optimizers:Vec<O>,
+    decay:Option<U>,
+    u:PhantomData<U>,
+}
+impl<U,O> ParameterOptimizer<U,O> where U: UnitValue<U>, O: Optimizer<U> {
+    /// Create an instance of ParameterOptimizer
+    /// # Arguments
+    /// * `kind` - Kind of the parameter
+    /// * `len` - Number of values of the parameter
+    /// * `factory` - Callback to create the optimizer of each value
+    pub fn new<F: FnMut() -> O>(kind:ParameterKind,len:usize,mut factory:F) -> ParameterOptimizer<U,O> {
+        ParameterOptimizer {
+            kind:kind,
+            optimizers:(0..len).map(|_| factory()).collect(),
+            decay:None,
+            u:PhantomData::<U>
+        }
+    }
+
+    /// Set the decoupled weight decay (as in AdamW), which shrinks each value before the optimizer updates it
+    ///
+    /// Only parameters of kind ParameterKind::Weight are decayed,
+    /// so this has no effect on biases and normalization parameters unless with_weight_decay_for_any_kind is used.
+    /// # Arguments
+    /// * `decay` - Learning rate multiplied by the coefficient of the weight decay
+    pub fn with_weight_decay(mut self,decay:U) -> ParameterOptimizer<U,O> {
+        self.decay = if self.kind == ParameterKind::Weight {
+            Some(decay)
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Set the decoupled weight decay whatever the kind of the parameter, such as to decay a bias explicitly
+    /// # Arguments
+    /// * `decay` - Learning rate multiplied by the coefficient of the weight decay
+    pub fn with_weight_decay_for_any_kind(mut self,decay:U) -> ParameterOptimizer<U,O> {
+        self.decay = Some(decay);
+        self
+    }
+
+    /// get the kind of the parameter
+    pub fn kind(&self) -> ParameterKind {
+        self.kind
+    }
+
+    /// get the optimizer of each value, which holds the state of the value
+    pub fn optimizers(&self) -> &[O] {
+        &self.optimizers
+    }
+
+    /// get the optimizer of each value as a mutable slice
+    pub fn optimizers_mut(&mut self) -> &mut [O] {
+        &mut self.optimizers
+    }
+
+    /// Update a parameter by its gradient
+    /// # Arguments
+    /// * `g` - gradient
+    /// * `w` - Values of the parameter to be updated
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`SizeMismatchError`]
+    pub fn update(&mut self,g:&[U],w:&mut [U]) -> Result<(),SizeMismatchError> {
+        if g.len() != self.optimizers.len() {
+            return Err(SizeMismatchError(g.len(),self.optimizers.len()));
+        } else if w.len() != self.optimizers.len() {
+            return Err(SizeMismatchError(w.len(),self.optimizers.len()));
+        }
+
+        for ((o,&g),w) in self.optimizers.iter_mut().zip(g.iter()).zip(w.iter_mut()) {
+            if let Some(decay) = self.decay {
+                *w = *w - decay * *w;
+            }
+
+            o.update(g,w);
+        }
+
+        Ok(())
+    }
+}
+/// Optimizer of the kernel and bias of a convolution layer
+///
+/// Decoupled weight decay is applied to the kernel only unless it is also enabled for the bias.
+pub struct ConvolutionOptimizer<U,O,const K:usize,const C:usize,const FH:usize,const FW:usize>
+    where U: UnitValue<U>, O: Optimizer<U> {
+    kernel:ParameterOptimizer<U,O>,
+    bias:ParameterOptimizer<U,O>,
+}
+impl<U,O,const K:usize,const C:usize,const FH:usize,const FW:usize> ConvolutionOptimizer<U,O,K,C,FH,FW>
+    where U: UnitValue<U>, O: Optimizer<U> {
+    /// Create an instance of ConvolutionOptimizer
+    /// # Arguments
+    /// * `factory` - Callback to create the nncombinator optimizer of each value
+    pub fn new<F: FnMut() -> O>(mut factory:F) -> ConvolutionOptimizer<U,O,K,C,FH,FW> {
+        ConvolutionOptimizer {
+            kernel:ParameterOptimizer::new(ParameterKind::Weight,K * C * FH * FW,&mut factory),
+            bias:ParameterOptimizer::new(ParameterKind::Bias,K,&mut factory)
+        }
+    }
+
+    /// Set the decoupled weight decay of the kernel (as in AdamW)
+    /// # Arguments
+    /// * `decay` - Learning rate multiplied by the coefficient of the weight decay
+    pub fn with_weight_decay(mut self,decay:U) -> ConvolutionOptimizer<U,O,K,C,FH,FW> {
+        self.kernel = self.kernel.with_weight_decay(decay);
+        self
+    }
+
+    /// Set the decoupled weight decay of the bias, which is not decayed by default
+    /// # Arguments
+    /// * `decay` - Learning rate multiplied by the coefficient of the weight decay
+    pub fn with_bias_weight_decay(mut self,decay:U) -> ConvolutionOptimizer<U,O,K,C,FH,FW> {
+        self.bias = self.bias.with_weight_decay_for_any_kind(decay);
+        self
+    }
+
+    /// get the optimizer of the kernel
+    pub fn kernel_optimizer(&self) -> &ParameterOptimizer<U,O> {
+        &self.kernel
+    }
+
+    /// get the optimizer of the bias
+    pub fn bias_optimizer(&self) -> &ParameterOptimizer<U,O> {
+        &self.bias
+    }
+
+    /// get the optimizer of the kernel as mutable
+    pub fn kernel_optimizer_mut(&mut self) -> &mut ParameterOptimizer<U,O> {
+        &mut self.kernel
+    }
+
+    /// get the optimizer of the bias as mutable
+    pub fn bias_optimizer_mut(&mut self) -> &mut ParameterOptimizer<U,O> {
+        &mut self.bias
+    }
+
+    /// Update the kernel by its gradient
+    /// # Arguments
+    /// * `g` - gradient of the kernel
+    /// * `kernel` - filter weights to be updated
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`SizeMismatchError`]
+    pub fn update_kernel(&mut self,g:&Arr4<U,K,C,FH,FW>,kernel:&mut Arr4<U,K,C,FH,FW>) -> Result<(),SizeMismatchError> {
+        self.kernel.update(g.as_raw_slice(),kernel.as_raw_mut_slice())
+    }
+
+    /// Update the bias by its gradient
+    /// # Arguments
+    /// * `g` - gradient of the bias
+    /// * `bias` - bias to be updated
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`SizeMismatchError`]
+    pub fn update_bias(&mut self,g:&Arr<U,K>,bias:&mut Arr<U,K>) -> Result<(),SizeMismatchError> {
+        self.bias.update(g,bias)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use nncombinator::arr::{Arr, Arr4};
+    use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+    use nncombinator::optimizer::Optimizer;
+
+    use crate::optimizer::{ConvolutionOptimizer, ParameterKind, ParameterOptimizer};
+
+    struct Sgd;
+    impl Optimizer<f32> for Sgd {
+        fn update(&mut self, e: f32, w: &mut f32) {
+            *w = *w - 0.5 * e;
+        }
+    }
+
+    fn update(optimizer:&mut ParameterOptimizer<f32,Sgd>) -> Vec<f32> {
+        let mut w = vec![2.,-4.];
+
+        optimizer.update(&[1.,1.],&mut w).unwrap();
+
+        w
+    }
+
+    #[test]
+    fn test_decoupled_weight_decay() {
+        assert_eq!(update(&mut ParameterOptimizer::new(ParameterKind::Weight,2,|| Sgd)),vec![1.5,-4.5]);
+
+        // w is shrunk by w * decay before the optimizer applies the gradient
+        assert_eq!(update(&mut ParameterOptimizer::new(ParameterKind::Weight,2,|| Sgd).with_weight_decay(0.25)),vec![1.,-3.5]);
+    }
+
+    #[test]
+    fn test_weight_decay_excludes_other_kinds() {
+        for &kind in [ParameterKind::Bias,ParameterKind::Normalization].iter() {
+            assert_eq!(update(&mut ParameterOptimizer::new(kind,2,|| Sgd).with_weight_decay(0.25)),vec![1.5,-4.5]);
+            assert_eq!(update(&mut ParameterOptimizer::new(kind,2,|| Sgd).with_weight_decay_for_any_kind(0.25)),vec![1.,-3.5]);
+        }
+    }
+
+    #[test]
+    fn test_convolution_optimizer_decays_kernel_only() {
+        let mut kernel = Arr4::<f32,1,1,1,2>::new();
+        let mut bias = Arr::<f32,1>::new();
+
+        let mut kernel_gradient = Arr4::<f32,1,1,1,2>::new();
+        let mut bias_gradient = Arr::<f32,1>::new();
+
+        kernel_gradient.as_raw_mut_slice().copy_from_slice(&[1.,1.]);
+        bias_gradient[0] = 1.;
+
+        let mut run = |optimizer:&mut ConvolutionOptimizer<f32,Sgd,1,1,1,2>| {
+            kernel.as_raw_mut_slice().copy_from_slice(&[2.,-4.]);
+            bias[0] = 2.;
+
+            optimizer.update_kernel(&kernel_gradient,&mut kernel).unwrap();
+            optimizer.update_bias(&bias_gradient,&mut bias).unwrap();
+
+            (kernel.as_raw_slice().to_vec(),bias[0])
+        };
+
+        assert_eq!(run(&mut ConvolutionOptimizer::new(|| Sgd).with_weight_decay(0.25)),(vec![1.,-3.5],1.5));
+        assert_eq!(run(&mut ConvolutionOptimizer::new(|| Sgd).with_weight_decay(0.25).with_bias_weight_decay(0.25)),(vec![1.,-3.5],1.));
+    }
+
+    #[test]
+    fn test_update_rejects_size_mismatch() {
+        let mut optimizer = ParameterOptimizer::new(ParameterKind::Weight,2,|| Sgd);
+
+        assert!(optimizer.update(&[1.],&mut [0.,0.]).is_err());
+        assert!(optimizer.update(&[1.,1.],&mut [0.]).is_err());
+    }
+}
@@ -0,0 +1,654 @@
+use num_traits::{FromPrimitive, ToPrimitive};
+use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+use nncombinator::ope::UnitValue;
+use rand::{Rng, SeedableRng};
+use rand_distr::StandardNormal;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::collection::{Images, VecImages};
+
+fn to_f64<U>(v:U) -> f64 where U: UnitValue<U> + ToPrimitive {
+    <U as ToPrimitive>::to_f64(&v).expect("Error in type conversion to f64.")
+}
+fn from_f64<U>(v:f64) -> U where U: UnitValue<U> + FromPrimitive {
+    <U as FromPrimitive>::from_f64(v).expect("Error in type conversion from f64.")
+}
+/// Uniform random number in [low,high], or low if the range is empty
+fn uniform(rnd:&mut XorShiftRng,low:f64,high:f64) -> f64 {
+    if high > low {
+        rnd.gen_range(low..high)
+    } else {
+        low
+    }
+}
+/// Seed of the random number generator for the sample at the specified position of a batch
+fn sample_seed(seed:u64,index:usize) -> u64 {
+    seed ^ (index as u64).wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+/// Trait defining a random transformation of a sample
+pub trait Augmentation<U,const C:usize,const H:usize,const W:usize>: Send + Sync where U: UnitValue<U> {
+    /// Apply the transformation to a sample
+    /// # Arguments
+    /// * `input` - Sample to be transformed
+    /// * `rnd` - Random number generator
+    fn apply(&self,input:&Images<U,C,H,W>,rnd:&mut XorShiftRng) -> Images<U,C,H,W>;
+
+    /// Apply the transformation to each sample of a batch in parallel
+    ///
+    /// Each sample uses a random number generator derived from the seed and its position,
+    /// so the result does not depend on the scheduling of the threads.
+    /// # Arguments
+    /// * `input` - Batch to be transformed
+    /// * `seed` - Seed of the random number generators
+    fn apply_batch(&self,input:&VecImages<U,C,H,W>,seed:u64) -> VecImages<U,C,H,W> {
+        (0..input.len()).into_par_iter().map(|i| {
+            let mut images = Images::new();
+
+            images.as_raw_mut_slice().copy_from_slice(input[i].as_raw_slice());
+
+            let mut rnd = XorShiftRng::seed_from_u64(sample_seed(seed,i));
+
+            self.apply(&images,&mut rnd)
+        }).collect::<Vec<Images<U,C,H,W>>>().into()
+    }
+}
+/// Implementation of a sequence of augmentations applied in order
+pub struct Compose<U,const C:usize,const H:usize,const W:usize> where U: UnitValue<U> {
+    augmentations:Vec<Box<dyn Augmentation<U,C,H,W>>>,
+}
+impl<U,const C:usize,const H:usize,const W:usize> Compose<U,C,H,W> where U: UnitValue<U> {
+    /// Create an empty instance of Compose
+    pub fn new() -> Compose<U,C,H,W> {
+        Compose {
+            augmentations:Vec::new()
+        }
+    }
+
+    /// Append an augmentation to the end of the sequence
+    /// # Arguments
+    /// * `augmentation` - Augmentation to be added
+    pub fn add<A: Augmentation<U,C,H,W> + 'static>(mut self,augmentation:A) -> Compose<U,C,H,W> {
+        self.augmentations.push(Box::new(augmentation));
+        self
+    }
+}
+impl<U,const C:usize,const H:usize,const W:usize> Augmentation<U,C,H,W> for Compose<U,C,H,W> where U: UnitValue<U> {
+    fn apply(&self,input:&Images<U,C,H,W>,rnd:&mut XorShiftRng) -> Images<U,C,H,W> {
+        self.augmentations.iter().fold(input.clone(),|acc,a| a.apply(&acc,rnd))
+    }
+}
+/// Random crop of the image padded on each side
+#[derive(Debug,Clone)]
+pub struct RandomCrop<U> where U: UnitValue<U> {
+    padding:usize,
+    fill:U,
+}
+impl<U> RandomCrop<U> where U: UnitValue<U> {
+    /// Create an instance of RandomCrop
+    /// # Arguments
+    /// * `padding` - Number of pixels padded on each side before cropping
+    /// * `fill` - Value of the padded pixels
+    pub fn new(padding:usize,fill:U) -> RandomCrop<U> {
+        RandomCrop {
+            padding:padding,
+            fill:fill
+        }
+    }
+}
+impl<U,const C:usize,const H:usize,const W:usize> Augmentation<U,C,H,W> for RandomCrop<U> where U: UnitValue<U> {
+    fn apply(&self,input:&Images<U,C,H,W>,rnd:&mut XorShiftRng) -> Images<U,C,H,W> {
+        if H == 0 || W == 0 {
+            return input.clone();
+        }
+
+        let p = self.padding as isize;
+        let dy = rnd.gen_range(0..=2 * p) - p;
+        let dx = rnd.gen_range(0..=2 * p) - p;
+
+        let src = input.as_raw_slice();
+        let mut output = Images::new();
+
+        for (c,image) in output.as_raw_mut_slice().chunks_mut(H * W).enumerate() {
+            for y in 0..H {
+                for x in 0..W {
+                    let sy = y as isize + dy;
+                    let sx = x as isize + dx;
+
+                    image[y * W + x] = if sy < 0 || sy >= H as isize || sx < 0 || sx >= W as isize {
+                        self.fill
+                    } else {
+                        src[c * H * W + sy as usize * W + sx as usize]
+                    };
+                }
+            }
+        }
+
+        output
+    }
+}
+/// Random horizontal flip
+#[derive(Debug,Clone)]
+pub struct HorizontalFlip {
+    p:f64,
+}
+impl HorizontalFlip {
+    /// Create an instance of HorizontalFlip
+    /// # Arguments
+    /// * `p` - Probability of flipping
+    pub fn new(p:f64) -> HorizontalFlip {
+        HorizontalFlip {
+            p:p
+        }
+    }
+}
+impl<U,const C:usize,const H:usize,const W:usize> Augmentation<U,C,H,W> for HorizontalFlip where U: UnitValue<U> {
+    fn apply(&self,input:&Images<U,C,H,W>,rnd:&mut XorShiftRng) -> Images<U,C,H,W> {
+        let mut output = input.clone();
+
+        if H == 0 || W == 0 {
+            return output;
+        }
+
+        if rnd.gen_bool(self.p.max(0.).min(1.)) {
+            for row in output.as_raw_mut_slice().chunks_mut(W) {
+                row.reverse();
+            }
+        }
+
+        output
+    }
+}
+/// Random vertical flip
+#[derive(Debug,Clone)]
+pub struct VerticalFlip {
+    p:f64,
+}
+impl VerticalFlip {
+    /// Create an instance of VerticalFlip
+    /// # Arguments
+    /// * `p` - Probability of flipping
+    pub fn new(p:f64) -> VerticalFlip {
+        VerticalFlip {
+            p:p
+        }
+    }
+}
+impl<U,const C:usize,const H:usize,const W:usize> Augmentation<U,C,H,W> for VerticalFlip where U: UnitValue<U> {
+    fn apply(&self,input:&Images<U,C,H,W>,rnd:&mut XorShiftRng) -> Images<U,C,H,W> {
+        if H == 0 || W == 0 || !rnd.gen_bool(self.p.max(0.).min(1.)) {
+            return input.clone();
+        }
+
+        let src = input.as_raw_slice();
+        let mut output = Images::new();
+
+        for (c,image) in output.as_raw_mut_slice().chunks_mut(H * W).enumerate() {
+            for (y,row) in image.chunks_mut(W).enumerate() {
+                let s = c * H * W + (H - 1 - y) * W;
+
+                row.copy_from_slice(&src[s..s + W]);
+            }
+        }
+
+        output
+    }
+}
+/// Random rotation by a multiple of 90 degrees
+///
+/// Images that are not square are only rotated by 0 or 180 degrees, since their shape must be kept.
+#[derive(Debug,Clone)]
+pub struct Rotate90;
+impl<U,const C:usize,const H:usize,const W:usize> Augmentation<U,C,H,W> for Rotate90 where U: UnitValue<U> {
+    fn apply(&self,input:&Images<U,C,H,W>,rnd:&mut XorShiftRng) -> Images<U,C,H,W> {
+        if H == 0 || W == 0 {
+            return input.clone();
+        }
+
+        let k = if H == W {
+            rnd.gen_range(0..4)
+        } else {
+            rnd.gen_range(0..2) * 2
+        };
+
+        if k == 0 {
+            return input.clone();
+        }
+
+        let src = input.as_raw_slice();
+        let mut output = Images::new();
+
+        for (c,image) in output.as_raw_mut_slice().chunks_mut(H * W).enumerate() {
+            for y in 0..H {
+                for x in 0..W {
+                    let (sy,sx) = match k {
+                        1 => (x,W - 1 - y),
+                        2 => (H - 1 - y,W - 1 - x),
+                        _ => (H - 1 - x,y),
+                    };
+
+                    image[y * W + x] = src[c * H * W + sy * W + sx];
+                }
+            }
+        }
+
+        output
+    }
+}
+/// Random rotation and scaling around the center with bilinear sampling
+#[derive(Debug,Clone)]
+pub struct RandomAffine<U> where U: UnitValue<U> {
+    max_angle:f64,
+    min_scale:f64,
+    max_scale:f64,
+    fill:U,
+}
+impl<U> RandomAffine<U> where U: UnitValue<U> {
+    /// Create an instance of RandomAffine
+    /// # Arguments
+    /// * `max_angle` - Maximum rotation angle in degrees, the angle is chosen from [-max_angle,max_angle]
+    /// * `min_scale` - Minimum scale factor
+    /// * `max_scale` - Maximum scale factor
+    /// * `fill` - Value of pixels sampled from outside the image
+    pub fn new(max_angle:f64,min_scale:f64,max_scale:f64,fill:U) -> RandomAffine<U> {
+        RandomAffine {
+            max_angle:max_angle,
+            min_scale:min_scale,
+            max_scale:max_scale,
+            fill:fill
+        }
+    }
+}
+impl<U,const C:usize,const H:usize,const W:usize> Augmentation<U,C,H,W> for RandomAffine<U>
+    where U: UnitValue<U> + FromPrimitive + ToPrimitive {
+    fn apply(&self,input:&Images<U,C,H,W>,rnd:&mut XorShiftRng) -> Images<U,C,H,W> {
+        if H == 0 || W == 0 {
+            return input.clone();
+        }
+
+        let angle = uniform(rnd,-self.max_angle,self.max_angle).to_radians();
+        let scale = uniform(rnd,self.min_scale,self.max_scale);
+
+        let (sin,cos) = angle.sin_cos();
+
+        let cy = (H as f64 - 1.) / 2.;
+        let cx = (W as f64 - 1.) / 2.;
+
+        let src = input.as_raw_slice();
+        let fill = to_f64(self.fill);
+
+        let mut output = Images::new();
+
+        for (c,image) in output.as_raw_mut_slice().chunks_mut(H * W).enumerate() {
+            let pixel = |y:isize,x:isize| {
+                if y < 0 || y >= H as isize || x < 0 || x >= W as isize {
+                    fill
+                } else {
+                    to_f64(src[c * H * W + y as usize * W + x as usize])
+                }
+            };
+
+            for y in 0..H {
+                for x in 0..W {
+                    let dy = y as f64 - cy;
+                    let dx = x as f64 - cx;
+
+                    let sx = (cos * dx + sin * dy) / scale + cx;
+                    let sy = (-sin * dx + cos * dy) / scale + cy;
+
+                    let y0 = sy.floor();
+                    let x0 = sx.floor();
+                    let fy = sy - y0;
+                    let fx = sx - x0;
+                    let y0 = y0 as isize;
+                    let x0 = x0 as isize;
+
+                    let v = pixel(y0,x0) * (1. - fy) * (1. - fx) +
+                            pixel(y0,x0 + 1) * (1. - fy) * fx +
+                            pixel(y0 + 1,x0) * fy * (1. - fx) +
+                            pixel(y0 + 1,x0 + 1) * fy * fx;
+
+                    image[y * W + x] = from_f64(v);
+                }
+            }
+        }
+
+        output
+    }
+}
+/// Random brightness and contrast jitter
+///
+/// Values are not clamped, so this can also be applied to normalized data.
+#[derive(Debug,Clone)]
+pub struct ColorJitter {
+    brightness:f64,
+    contrast:f64,
+}
+impl ColorJitter {
+    /// Create an instance of ColorJitter
+    /// # Arguments
+    /// * `brightness` - The values are multiplied by a factor chosen from [1 - brightness,1 + brightness]
+    /// * `contrast` - The deviation from the mean is multiplied by a factor chosen from [1 - contrast,1 + contrast]
+    pub fn new(brightness:f64,contrast:f64) -> ColorJitter {
+        ColorJitter {
+            brightness:brightness,
+            contrast:contrast
+        }
+    }
+}
+impl<U,const C:usize,const H:usize,const W:usize> Augmentation<U,C,H,W> for ColorJitter
+    where U: UnitValue<U> + FromPrimitive + ToPrimitive {
+    fn apply(&self,input:&Images<U,C,H,W>,rnd:&mut XorShiftRng) -> Images<U,C,H,W> {
+        let b = uniform(rnd,1. - self.brightness,1. + self.brightness).max(0.);
+        let c = uniform(rnd,1. - self.contrast,1. + self.contrast).max(0.);
+
+        let mean = input.as_raw_slice().iter().map(|&v| to_f64(v)).sum::<f64>() * b / (C * H * W).max(1) as f64;
+
+        input.map(|&v| from_f64((to_f64(v) * b - mean) * c + mean))
+    }
+}
+/// Additive Gaussian noise
+#[derive(Debug,Clone)]
+pub struct GaussianNoise {
+    std:f64,
+}
+impl GaussianNoise {
+    /// Create an instance of GaussianNoise
+    /// # Arguments
+    /// * `std` - Standard deviation of the noise
+    pub fn new(std:f64) -> GaussianNoise {
+        GaussianNoise {
+            std:std
+        }
+    }
+}
+impl<U,const C:usize,const H:usize,const W:usize> Augmentation<U,C,H,W> for GaussianNoise
+    where U: UnitValue<U> + FromPrimitive + ToPrimitive {
+    fn apply(&self,input:&Images<U,C,H,W>,rnd:&mut XorShiftRng) -> Images<U,C,H,W> {
+        let mut output = input.clone();
+
+        for v in output.as_raw_mut_slice().iter_mut() {
+            let n:f64 = rnd.sample(StandardNormal);
+
+            *v = from_f64(to_f64(*v) + n * self.std);
+        }
+
+        output
+    }
+}
+/// Cutout, which fills a square at a random position in all channels
+#[derive(Debug,Clone)]
+pub struct Cutout<U> where U: UnitValue<U> {
+    size:usize,
+    fill:U,
+}
+impl<U> Cutout<U> where U: UnitValue<U> {
+    /// Create an instance of Cutout
+    /// # Arguments
+    /// * `size` - Length of the side of the square
+    /// * `fill` - Value of the filled pixels
+    pub fn new(size:usize,fill:U) -> Cutout<U> {
+        Cutout {
+            size:size,
+            fill:fill
+        }
+    }
+}
+impl<U,const C:usize,const H:usize,const W:usize> Augmentation<U,C,H,W> for Cutout<U> where U: UnitValue<U> {
+    fn apply(&self,input:&Images<U,C,H,W>,rnd:&mut XorShiftRng) -> Images<U,C,H,W> {
+        let mut output = input.clone();
+
+        if H == 0 || W == 0 {
+            return output;
+        }
+
+        let cy = rnd.gen_range(0..H);
+        let cx = rnd.gen_range(0..W);
+
+        let top = cy.saturating_sub(self.size / 2);
+        let left = cx.saturating_sub(self.size / 2);
+        let bottom = (cy + (self.size + 1) / 2).min(H);
+        let right = (cx + (self.size + 1) / 2).min(W);
+
+        for image in output.as_raw_mut_slice().chunks_mut(H * W) {
+            for y in top..bottom {
+                for p in image[y * W + left..y * W + right].iter_mut() {
+                    *p = self.fill;
+                }
+            }
+        }
+
+        output
+    }
+}
+/// Value written by RandomErasing
+#[derive(Debug,Clone,Copy)]
+pub enum ErasingValue<U> where U: UnitValue<U> {
+    /// Constant value
+    Constant(U),
+    /// Value drawn from the standard normal distribution for each pixel
+    Normal,
+}
+/// Random Erasing, which fills a rectangle of random area and aspect ratio
+#[derive(Debug,Clone)]
+pub struct RandomErasing<U> where U: UnitValue<U> {
+    p:f64,
+    min_area:f64,
+    max_area:f64,
+    min_aspect:f64,
+    max_aspect:f64,
+    value:ErasingValue<U>,
+}
+impl<U> RandomErasing<U> where U: UnitValue<U> {
+    /// Create an instance of RandomErasing
+    /// # Arguments
+    /// * `p` - Probability of erasing
+    /// * `min_area` - Minimum area of the rectangle as a fraction of the image
+    /// * `max_area` - Maximum area of the rectangle as a fraction of the image
+    /// * `min_aspect` - Minimum aspect ratio (height / width) of the rectangle
+    /// * `max_aspect` - Maximum aspect ratio (height / width) of the rectangle
+    /// * `value` - Value written into the rectangle
+    pub fn new(p:f64,min_area:f64,max_area:f64,min_aspect:f64,max_aspect:f64,value:ErasingValue<U>) -> RandomErasing<U> {
+        RandomErasing {
+            p:p,
+            min_area:min_area,
+            max_area:max_area,
+            min_aspect:min_aspect,
+            max_aspect:max_aspect,
+            value:value
+        }
+    }
+}
+impl<U,const C:usize,const H:usize,const W:usize> Augmentation<U,C,H,W> for RandomErasing<U>
+    where U: UnitValue<U> + FromPrimitive + ToPrimitive {
+    fn apply(&self,input:&Images<U,C,H,W>,rnd:&mut XorShiftRng) -> Images<U,C,H,W> {
+        let mut output = input.clone();
+
+        if H == 0 || W == 0 || !rnd.gen_bool(self.p.max(0.).min(1.)) {
+            return output;
+        }
+
+        for _ in 0..10 {
+            let area = uniform(rnd,self.min_area,self.max_area) * (H * W) as f64;
+            let aspect = uniform(rnd,self.min_aspect.ln(),self.max_aspect.ln()).exp();
+
+            let h = (area * aspect).sqrt().round() as usize;
+            let w = (area / aspect).sqrt().round() as usize;
+
+            if h == 0 || w == 0 || h > H || w > W {
+                continue;
+            }
+
+            let top = rnd.gen_range(0..=H - h);
+            let left = rnd.gen_range(0..=W - w);
+
+            for image in output.as_raw_mut_slice().chunks_mut(H * W) {
+                for y in top..top + h {
+                    for p in image[y * W + left..y * W + left + w].iter_mut() {
+                        *p = match self.value {
+                            ErasingValue::Constant(v) => v,
+                            ErasingValue::Normal => from_f64(rnd.sample(StandardNormal)),
+                        };
+                    }
+                }
+            }
+
+            break;
+        }
+
+        output
+    }
+}
+#[cfg(test)]
+mod tests {
+    use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use rayon::ThreadPoolBuilder;
+
+    use crate::collection::{Images, VecImages};
+    use crate::preprocessing::augmentation::{sample_seed, Augmentation, ColorJitter, Compose, Cutout, ErasingValue, GaussianNoise, HorizontalFlip, RandomAffine, RandomCrop, RandomErasing, Rotate90, VerticalFlip};
+
+    fn image<const C:usize,const H:usize,const W:usize>() -> Images<f32,C,H,W> {
+        let mut image = Images::new();
+
+        for (i,p) in image.as_raw_mut_slice().iter_mut().enumerate() {
+            *p = i as f32 + 1.;
+        }
+
+        image
+    }
+
+    fn apply<A: Augmentation<f32,C,H,W>,const C:usize,const H:usize,const W:usize>(a:&A,input:&Images<f32,C,H,W>,seed:u64) -> Vec<f32> {
+        a.apply(input,&mut XorShiftRng::seed_from_u64(seed)).as_raw_slice().to_vec()
+    }
+
+    #[test]
+    fn test_apply_batch_independent_of_thread_count() {
+        let augmentation = Compose::<f32,2,6,6>::new()
+            .add(RandomCrop::new(2,0.))
+            .add(HorizontalFlip::new(0.5))
+            .add(Rotate90)
+            .add(RandomAffine::new(15.,0.9,1.1,0.))
+            .add(ColorJitter::new(0.2,0.2))
+            .add(GaussianNoise::new(0.1))
+            .add(Cutout::new(2,0.))
+            .add(RandomErasing::new(0.5,0.1,0.3,0.5,2.,ErasingValue::Normal));
+
+        let mut input = VecImages::<f32,2,6,6>::with_size(17);
+
+        for (i,p) in input.as_raw_mut_slice().iter_mut().enumerate() {
+            *p = (i % 13) as f32 * 0.25;
+        }
+
+        let results = [1,2,3,8].iter().map(|&threads| {
+            ThreadPoolBuilder::new().num_threads(threads).build().unwrap().install(|| {
+                augmentation.apply_batch(&input,42).as_raw_slice().to_vec()
+            })
+        }).collect::<Vec<Vec<f32>>>();
+
+        for r in results.iter() {
+            assert_eq!(r,&results[0]);
+        }
+
+        assert_ne!(results[0],augmentation.apply_batch(&input,43).as_raw_slice().to_vec());
+
+        let mut third = Images::new();
+
+        third.as_raw_mut_slice().copy_from_slice(input[3].as_raw_slice());
+
+        let sample = augmentation.apply(&third,&mut XorShiftRng::seed_from_u64(sample_seed(42,3)));
+
+        assert_eq!(sample.as_raw_slice(),&results[0][3 * 2 * 6 * 6..4 * 2 * 6 * 6]);
+    }
+
+    #[test]
+    fn test_flips() {
+        let input = image::<2,2,3>();
+
+        assert_eq!(apply(&HorizontalFlip::new(0.),&input,1),input.as_raw_slice());
+        assert_eq!(apply(&VerticalFlip::new(0.),&input,1),input.as_raw_slice());
+
+        assert_eq!(apply(&HorizontalFlip::new(1.),&input,1),vec![
+            3.,2.,1.,6.,5.,4.,
+            9.,8.,7.,12.,11.,10.
+        ]);
+        assert_eq!(apply(&VerticalFlip::new(1.),&input,1),vec![
+            4.,5.,6.,1.,2.,3.,
+            10.,11.,12.,7.,8.,9.
+        ]);
+
+        let flipped = HorizontalFlip::new(1.).apply(&input,&mut XorShiftRng::seed_from_u64(1));
+
+        assert_eq!(apply(&HorizontalFlip::new(1.),&flipped,1),input.as_raw_slice());
+    }
+
+    #[test]
+    fn test_rotate90() {
+        let input = image::<1,2,2>();
+
+        let rotations = [
+            vec![1.,2.,3.,4.],
+            vec![2.,4.,1.,3.],
+            vec![4.,3.,2.,1.],
+            vec![3.,1.,4.,2.]
+        ];
+
+        let mut seen = [false; 4];
+
+        for seed in 0..64 {
+            let output = apply(&Rotate90,&input,seed);
+
+            let k = rotations.iter().position(|r| *r == output).expect("The output is not a rotation of the input.");
+
+            seen[k] = true;
+        }
+
+        assert_eq!(seen,[true; 4]);
+
+        let input = image::<2,2,3>();
+        let rotated = vec![6.,5.,4.,3.,2.,1.,12.,11.,10.,9.,8.,7.];
+
+        let mut seen = [false; 2];
+
+        for seed in 0..64 {
+            let output = apply(&Rotate90,&input,seed);
+
+            if output == input.as_raw_slice() {
+                seen[0] = true;
+            } else {
+                assert_eq!(output,rotated);
+                seen[1] = true;
+            }
+        }
+
+        assert_eq!(seen,[true; 2]);
+    }
+
+    #[test]
+    fn test_zero_sized_images() {
+        fn assert_unchanged<const H:usize,const W:usize>() {
+            let input = Images::<f32,2,H,W>::new();
+
+            let augmentations:Vec<Box<dyn Augmentation<f32,2,H,W>>> = vec![
+                Box::new(RandomCrop::new(1,0.)),
+                Box::new(HorizontalFlip::new(1.)),
+                Box::new(VerticalFlip::new(1.)),
+                Box::new(Rotate90),
+                Box::new(RandomAffine::new(30.,0.5,2.,0.)),
+                Box::new(ColorJitter::new(0.5,0.5)),
+                Box::new(GaussianNoise::new(1.)),
+                Box::new(Cutout::new(2,0.)),
+                Box::new(RandomErasing::new(1.,0.1,0.5,0.5,2.,ErasingValue::Constant(0.)))
+            ];
+
+            for a in augmentations.iter() {
+                assert!(a.apply(&input,&mut XorShiftRng::seed_from_u64(1)).as_raw_slice().is_empty());
+            }
+        }
+
+        assert_unchanged::<0,3>();
+        assert_unchanged::<3,0>();
+        assert_unchanged::<0,0>();
+    }
+}
@@ -0,0 +1,324 @@
+use num_traits::FromPrimitive;
+use nncombinator::arr::Arr;
+use nncombinator::error::SizeMismatchError;
+use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+use nncombinator::ope::UnitValue;
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use rand_distr::Beta;
+use rand_xorshift::XorShiftRng;
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::collection::{Images, VecImages};
+
+/// Batch mixed by MixUp or CutMix
+///
+/// Sample i of `images` is the mix of sample i and sample `pairs[i]` of the input,
+/// where `lambdas[i]` is the weight of sample i.
+#[derive(Debug,Clone)]
+pub struct MixedBatch<U,const C:usize,const H:usize,const W:usize> where U: UnitValue<U> {
+    /// Mixed images
+    pub images:VecImages<U,C,H,W>,
+    /// Position of the sample mixed into each sample
+    pub pairs:Vec<usize>,
+    /// Weight of the original sample of each mixed sample
+    pub lambdas:Vec<f64>,
+}
+impl<U,const C:usize,const H:usize,const W:usize> MixedBatch<U,C,H,W> where U: UnitValue<U> + FromPrimitive {
+    /// Build soft targets from the class indices of the input batch
+    /// # Arguments
+    /// * `labels` - Class index of each sample of the input batch
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`SizeMismatchError`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if a class index is not less than N
+    pub fn soft_labels<const N:usize>(&self,labels:&[usize]) -> Result<Vec<Arr<U,N>>,SizeMismatchError> {
+        if labels.len() != self.pairs.len() {
+            return Err(SizeMismatchError(labels.len(),self.pairs.len()));
+        }
+
+        Ok(self.pairs.iter().zip(self.lambdas.iter()).enumerate().map(|(i,(&j,&lambda))| {
+            let mut target = Arr::<U,N>::new();
+
+            target[labels[i]] = target[labels[i]] + from_f64(lambda);
+            target[labels[j]] = target[labels[j]] + from_f64(1. - lambda);
+
+            target
+        }).collect())
+    }
+
+    /// Mix the targets of the input batch with the same weights as the images
+    /// # Arguments
+    /// * `targets` - Target of each sample of the input batch
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors
+    /// * [`SizeMismatchError`]
+    pub fn mix_targets<const N:usize>(&self,targets:&[Arr<U,N>]) -> Result<Vec<Arr<U,N>>,SizeMismatchError> {
+        if targets.len() != self.pairs.len() {
+            return Err(SizeMismatchError(targets.len(),self.pairs.len()));
+        }
+
+        Ok(self.pairs.iter().zip(self.lambdas.iter()).enumerate().map(|(i,(&j,&lambda))| {
+            let a:U = from_f64(lambda);
+            let b:U = from_f64(1. - lambda);
+
+            let mut target = Arr::<U,N>::new();
+
+            for k in 0..N {
+                target[k] = targets[i][k] * a + targets[j][k] * b;
+            }
+
+            target
+        }).collect())
+    }
+}
+fn from_f64<U>(v:f64) -> U where U: UnitValue<U> + FromPrimitive {
+    <U as FromPrimitive>::from_f64(v).expect("Error in type conversion from f64.")
+}
+/// Draw the pairing of the samples and the mixing ratios from Beta(alpha,alpha)
+fn draw(len:usize,alpha:f64,rnd:&mut XorShiftRng) -> (Vec<usize>,Vec<f64>) {
+    let beta = Beta::new(alpha,alpha).expect("alpha must be positive.");
+
+    let mut pairs = (0..len).collect::<Vec<usize>>();
+
+    pairs.shuffle(rnd);
+
+    let lambdas = (0..len).map(|_| rnd.sample(beta)).collect::<Vec<f64>>();
+
+    (pairs,lambdas)
+}
+/// Copy the sample at the specified position into Images
+fn sample<U,const C:usize,const H:usize,const W:usize>(input:&VecImages<U,C,H,W>,index:usize) -> Images<U,C,H,W>
+    where U: UnitValue<U> {
+    let mut images = Images::new();
+
+    images.as_raw_mut_slice().copy_from_slice(input[index].as_raw_slice());
+
+    images
+}
+/// MixUp, which blends each sample with another sample of the batch
+#[derive(Debug,Clone)]
+pub struct MixUp {
+    alpha:f64,
+}
+impl MixUp {
+    /// Create an instance of MixUp
+    /// # Arguments
+    /// * `alpha` - Parameter of the Beta distribution of the mixing ratio
+    pub fn new(alpha:f64) -> MixUp {
+        MixUp {
+            alpha:alpha
+        }
+    }
+
+    /// Mix the samples of a batch
+    /// # Arguments
+    /// * `input` - Batch to be mixed
+    /// * `seed` - Seed of the random number generator
+    ///
+    /// # Panics
+    ///
+    /// Panics if alpha is not positive
+    pub fn apply<U,const C:usize,const H:usize,const W:usize>(&self,input:&VecImages<U,C,H,W>,seed:u64) -> MixedBatch<U,C,H,W>
+        where U: UnitValue<U> + FromPrimitive {
+        let mut rnd = XorShiftRng::seed_from_u64(seed);
+
+        let (pairs,lambdas) = draw(input.len(),self.alpha,&mut rnd);
+
+        let images = (0..input.len()).into_par_iter().map(|i| {
+            let a:U = from_f64(lambdas[i]);
+            let b:U = from_f64(1. - lambdas[i]);
+
+            sample(input,i).zip_map(&sample(input,pairs[i]),|&x,&y| x * a + y * b)
+        }).collect::<Vec<Images<U,C,H,W>>>().into();
+
+        MixedBatch {
+            images:images,
+            pairs:pairs,
+            lambdas:lambdas
+        }
+    }
+}
+/// CutMix, which pastes a rectangle of another sample of the batch into each sample
+///
+/// The weight of each sample is the ratio of the area that was not replaced.
+#[derive(Debug,Clone)]
+pub struct CutMix {
+    alpha:f64,
+}
+impl CutMix {
+    /// Create an instance of CutMix
+    /// # Arguments
+    /// * `alpha` - Parameter of the Beta distribution of the area ratio
+    pub fn new(alpha:f64) -> CutMix {
+        CutMix {
+            alpha:alpha
+        }
+    }
+
+    /// Mix the samples of a batch
+    /// # Arguments
+    /// * `input` - Batch to be mixed
+    /// * `seed` - Seed of the random number generator
+    ///
+    /// # Panics
+    ///
+    /// Panics if alpha is not positive
+    pub fn apply<U,const C:usize,const H:usize,const W:usize>(&self,input:&VecImages<U,C,H,W>,seed:u64) -> MixedBatch<U,C,H,W>
+        where U: UnitValue<U> + FromPrimitive {
+        let mut rnd = XorShiftRng::seed_from_u64(seed);
+
+        let (pairs,lambdas) = draw(input.len(),self.alpha,&mut rnd);
+
+        let boxes = lambdas.iter().map(|&lambda| {
+            let ratio = (1. - lambda).sqrt();
+
+            let h = (H as f64 * ratio) as usize;
+            let w = (W as f64 * ratio) as usize;
+
+            let cy = rnd.gen_range(0..H.max(1));
+            let cx = rnd.gen_range(0..W.max(1));
+
+            let top = cy.saturating_sub(h / 2);
+            let left = cx.saturating_sub(w / 2);
+            let bottom = (cy + h / 2).min(H);
+            let right = (cx + w / 2).min(W);
+
+            (top,left,bottom,right)
+        }).collect::<Vec<(usize,usize,usize,usize)>>();
+
+        let lambdas = boxes.iter().map(|&(top,left,bottom,right)| {
+            1. - ((bottom - top) * (right - left)) as f64 / (H * W).max(1) as f64
+        }).collect::<Vec<f64>>();
+
+        let images = (0..input.len()).into_par_iter().map(|i| {
+            let (top,left,bottom,right) = boxes[i];
+
+            let mut images = sample(input,i);
+            let other = input[pairs[i]].as_raw_slice();
+
+            if H == 0 || W == 0 {
+                return images;
+            }
+
+            for (c,image) in images.as_raw_mut_slice().chunks_mut(H * W).enumerate() {
+                for y in top..bottom {
+                    let s = c * H * W + y * W;
+
+                    image[y * W + left..y * W + right].copy_from_slice(&other[s + left..s + right]);
+                }
+            }
+
+            images
+        }).collect::<Vec<Images<U,C,H,W>>>().into();
+
+        MixedBatch {
+            images:images,
+            pairs:pairs,
+            lambdas:lambdas
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use nncombinator::arr::Arr;
+    use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+
+    use crate::collection::VecImages;
+    use crate::preprocessing::mix::{CutMix, MixUp};
+
+    /// Batch whose samples are filled with their position plus one
+    fn batch(n:usize) -> VecImages<f64,2,5,4> {
+        let mut input = VecImages::with_size(n);
+
+        for (i,image) in input.as_raw_mut_slice().chunks_mut(2 * 5 * 4).enumerate() {
+            image.iter_mut().for_each(|p| *p = i as f64 + 1.);
+        }
+
+        input
+    }
+
+    #[test]
+    fn test_mixup_lambda_and_images() {
+        for &alpha in [0.2,1.,4.].iter() {
+            let mixed = MixUp::new(alpha).apply(&batch(16),7);
+
+            assert_eq!(mixed.images.len(),16);
+
+            for (i,(&j,&lambda)) in mixed.pairs.iter().zip(mixed.lambdas.iter()).enumerate() {
+                assert!(lambda >= 0. && lambda <= 1.,"{}",lambda);
+
+                let expected = (i as f64 + 1.) * lambda + (j as f64 + 1.) * (1. - lambda);
+
+                assert!(mixed.images[i].as_raw_slice().iter().all(|&v| (v - expected).abs() < 1e-12));
+            }
+
+            let mut pairs = mixed.pairs.clone();
+
+            pairs.sort();
+
+            assert_eq!(pairs,(0..16).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    fn test_cutmix_lambda_is_area_ratio() {
+        for seed in 0..8 {
+            let mixed = CutMix::new(1.).apply(&batch(16),seed);
+
+            for (i,(&j,&lambda)) in mixed.pairs.iter().zip(mixed.lambdas.iter()).enumerate() {
+                assert!(lambda >= 0. && lambda <= 1.,"{}",lambda);
+
+                if i == j {
+                    continue;
+                }
+
+                let image = mixed.images[i].as_raw_slice();
+
+                assert!(image.iter().all(|&v| v == i as f64 + 1. || v == j as f64 + 1.));
+
+                let pasted = image.iter().filter(|&&v| v == j as f64 + 1.).count();
+
+                assert_eq!(pasted % 2,0);
+                assert!((lambda - (1. - (pasted / 2) as f64 / (5 * 4) as f64)).abs() < 1e-12,"{} {}",lambda,pasted);
+            }
+        }
+    }
+
+    #[test]
+    fn test_soft_labels_sum_to_one() {
+        let labels = (0..12).map(|i| i % 5).collect::<Vec<usize>>();
+
+        for mixed in [MixUp::new(0.4).apply(&batch(12),3),CutMix::new(1.).apply(&batch(12),3)].iter() {
+            let soft = mixed.soft_labels::<5>(&labels).unwrap();
+
+            let one_hot = labels.iter().map(|&l| {
+                let mut t = Arr::<f64,5>::new();
+
+                t[l] = 1.;
+
+                t
+            }).collect::<Vec<Arr<f64,5>>>();
+
+            let mixed_targets = mixed.mix_targets(&one_hot).unwrap();
+
+            for (s,m) in soft.iter().zip(mixed_targets.iter()) {
+                assert!((s.iter().sum::<f64>() - 1.).abs() < 1e-12);
+                assert!(s.iter().all(|&v| v >= 0.));
+
+                for (a,b) in s.iter().zip(m.iter()) {
+                    assert!((a - b).abs() < 1e-12);
+                }
+            }
+
+            assert!(mixed.soft_labels::<5>(&labels[1..]).is_err());
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use num_traits::{FromPrimitive, ToPrimitive};
+use nncombinator::arr::Arr;
+use nncombinator::ope::UnitValue;
+
+use crate::collection::{Images, VecImages};
+
+pub mod augmentation;
+pub mod mix;
+pub mod statistics;
+
+/// Convert a value of u8 into a unit value scaled to [0,1]
+fn u8_to_unit<U>(v:u8) -> U where U: UnitValue<U> + FromPrimitive {
+    <U as FromPrimitive>::from_f64(v as f64 / 255.).expect("Error in type conversion from f64.")
+}
+/// Convert a unit value in [0,1] into a value of u8, clamping values outside the range
+fn unit_to_u8<U>(v:U) -> u8 where U: UnitValue<U> + ToPrimitive {
+    let v = <U as ToPrimitive>::to_f64(&v).expect("Error in type conversion to f64.");
+
+    (v.max(0.).min(1.) * 255.).round() as u8
+}
+impl<const C:usize,const H:usize,const W:usize> Images<u8,C,H,W> {
+    /// Convert to Images of unit values scaled to [0,1]
+    pub fn to_unit<U>(&self) -> Images<U,C,H,W> where U: UnitValue<U> + FromPrimitive {
+        self.map(|&v| u8_to_unit(v))
+    }
+}
+impl<U,const C:usize,const H:usize,const W:usize> Images<U,C,H,W> where U: UnitValue<U> + ToPrimitive {
+    /// Convert Images of unit values in [0,1] to Images of u8
+    pub fn to_u8(&self) -> Images<u8,C,H,W> {
+        self.map(|&v| unit_to_u8(v))
+    }
+}
+impl<const C:usize,const H:usize,const W:usize> VecImages<u8,C,H,W> {
+    /// Convert to VecImages of unit values scaled to [0,1] in parallel
+    pub fn to_unit<U>(&self) -> VecImages<U,C,H,W> where U: UnitValue<U> + FromPrimitive {
+        self.par_map(|&v| u8_to_unit(v))
+    }
+}
+impl<U,const C:usize,const H:usize,const W:usize> VecImages<U,C,H,W> where U: UnitValue<U> + ToPrimitive {
+    /// Convert VecImages of unit values in [0,1] to VecImages of u8 in parallel
+    pub fn to_u8(&self) -> VecImages<u8,C,H,W> {
+        self.par_map(|&v| unit_to_u8(v))
+    }
+}
+/// Per-channel normalization by mean and standard deviation
+#[derive(Debug,Clone)]
+pub struct Normalization<U,const C:usize> where U: UnitValue<U> {
+    mean:Arr<U,C>,
+    std:Arr<U,C>,
+}
+impl<U,const C:usize> Normalization<U,C> where U: UnitValue<U> {
+    /// Create an instance of Normalization
+    /// # Arguments
+    /// * `mean` - mean of each channel
+    /// * `std` - standard deviation of each channel
+    pub fn new(mean:Arr<U,C>,std:Arr<U,C>) -> Normalization<U,C> {
+        Normalization {
+            mean:mean,
+            std:std
+        }
+    }
+
+    /// get the mean of each channel
+    pub fn mean(&self) -> &Arr<U,C> {
+        &self.mean
+    }
+
+    /// get the standard deviation of each channel
+    pub fn std(&self) -> &Arr<U,C> {
+        &self.std
+    }
+
+    /// Normalize each channel to (x - mean) / std
+    /// # Arguments
+    /// * `input` - Images to be normalized
+    pub fn normalize<const H:usize,const W:usize>(&self,input:&Images<U,C,H,W>) -> Images<U,C,H,W> {
+        input.map_channels(|c,image| {
+            let mean = self.mean[c];
+            let std = self.std[c];
+
+            image.map_elements(|&v| (v - mean) / std)
+        })
+    }
+
+    /// Restore normalized channels to x * std + mean
+    /// # Arguments
+    /// * `input` - Normalized images
+    pub fn denormalize<const H:usize,const W:usize>(&self,input:&Images<U,C,H,W>) -> Images<U,C,H,W> {
+        input.map_channels(|c,image| {
+            let mean = self.mean[c];
+            let std = self.std[c];
+
+            image.map_elements(|&v| v * std + mean)
+        })
+    }
+
+    /// Normalize each channel of each element to (x - mean) / std in parallel
+    /// # Arguments
+    /// * `input` - VecImages to be normalized
+    pub fn normalize_batch<const H:usize,const W:usize>(&self,input:&VecImages<U,C,H,W>) -> VecImages<U,C,H,W> {
+        input.par_map_channels(|c,image| {
+            let mean = self.mean[c];
+            let std = self.std[c];
+
+            image.map_elements(|&v| (v - mean) / std)
+        })
+    }
+
+    /// Restore normalized channels of each element to x * std + mean in parallel
+    /// # Arguments
+    /// * `input` - Normalized VecImages
+    pub fn denormalize_batch<const H:usize,const W:usize>(&self,input:&VecImages<U,C,H,W>) -> VecImages<U,C,H,W> {
+        input.par_map_channels(|c,image| {
+            let mean = self.mean[c];
+            let std = self.std[c];
+
+            image.map_elements(|&v| v * std + mean)
+        })
+    }
+}
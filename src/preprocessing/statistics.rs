@@ -0,0 +1,302 @@
+use num_traits::{FromPrimitive, ToPrimitive};
+use nncombinator::arr::Arr;
+use nncombinator::mem::AsRawSlice;
+use nncombinator::ope::UnitValue;
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::collection::{Images, VecImages};
+use crate::preprocessing::Normalization;
+
+/// Streaming per-channel statistics (count, mean, variance, min and max) by Welford's algorithm
+///
+/// Partial statistics computed in parallel are combined with the pairwise update of Chan et al.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ChannelStatistics<const C:usize> {
+    count:u64,
+    mean:[f64; C],
+    m2:[f64; C],
+    min:[f64; C],
+    max:[f64; C],
+}
+impl<const C:usize> ChannelStatistics<C> {
+    /// Create an empty instance of ChannelStatistics
+    pub fn new() -> ChannelStatistics<C> {
+        ChannelStatistics {
+            count:0,
+            mean:[0.; C],
+            m2:[0.; C],
+            min:[f64::INFINITY; C],
+            max:[f64::NEG_INFINITY; C],
+        }
+    }
+
+    /// Add the pixels of one sample given as a slice of C planes
+    fn update_slice<T>(&mut self,data:&[T]) where T: ToPrimitive {
+        let mut other = ChannelStatistics::new();
+
+        let hw = data.len() / C.max(1);
+
+        other.count = hw as u64;
+
+        for (c,plane) in data.chunks(hw.max(1)).take(C).enumerate() {
+            let mut n = 0.;
+
+            for v in plane.iter() {
+                let v = <T as ToPrimitive>::to_f64(v).expect("Error in type conversion to f64.");
+
+                n += 1.;
+
+                let delta = v - other.mean[c];
+
+                other.mean[c] += delta / n;
+                other.m2[c] += delta * (v - other.mean[c]);
+                other.min[c] = other.min[c].min(v);
+                other.max[c] = other.max[c].max(v);
+            }
+        }
+
+        self.merge(&other);
+    }
+
+    /// Add the pixels of a sample
+    /// # Arguments
+    /// * `images` - Sample to be added
+    pub fn update<T,const H:usize,const W:usize>(&mut self,images:&Images<T,C,H,W>)
+        where T: Default + Clone + Send + ToPrimitive {
+        self.update_slice(images.as_raw_slice());
+    }
+
+    /// Add the pixels of all samples of a batch, computed in parallel
+    /// # Arguments
+    /// * `batch` - Batch to be added
+    pub fn update_batch<T,const H:usize,const W:usize>(&mut self,batch:&VecImages<T,C,H,W>)
+        where T: Default + Clone + Copy + Send + Sync + ToPrimitive {
+        let stats = (0..batch.len()).into_par_iter().fold(ChannelStatistics::new,|mut acc,i| {
+            acc.update_slice(batch[i].as_raw_slice());
+            acc
+        }).reduce(ChannelStatistics::new,|mut acc,s| {
+            acc.merge(&s);
+            acc
+        });
+
+        self.merge(&stats);
+    }
+
+    /// Compute the statistics of a sequence of batches
+    /// # Arguments
+    /// * `batches` - Iterator of batches
+    pub fn from_batches<T,I,const H:usize,const W:usize>(batches:I) -> ChannelStatistics<C>
+        where T: Default + Clone + Copy + Send + Sync + ToPrimitive,
+              I: IntoIterator<Item=VecImages<T,C,H,W>> {
+        let mut stats = ChannelStatistics::new();
+
+        for batch in batches {
+            stats.update_batch(&batch);
+        }
+
+        stats
+    }
+
+    /// Combine with statistics computed over other pixels
+    /// # Arguments
+    /// * `other` - Statistics to be combined
+    pub fn merge(&mut self,other:&ChannelStatistics<C>) {
+        if other.count == 0 {
+            return;
+        } else if self.count == 0 {
+            *self = other.clone();
+            return;
+        }
+
+        let na = self.count as f64;
+        let nb = other.count as f64;
+        let n = na + nb;
+
+        for c in 0..C {
+            let delta = other.mean[c] - self.mean[c];
+
+            self.mean[c] += delta * nb / n;
+            self.m2[c] += other.m2[c] + delta * delta * na * nb / n;
+            self.min[c] = self.min[c].min(other.min[c]);
+            self.max[c] = self.max[c].max(other.max[c]);
+        }
+
+        self.count += other.count;
+    }
+
+    /// get the number of pixels added per channel
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// get the mean of each channel
+    pub fn mean(&self) -> [f64; C] {
+        self.mean
+    }
+
+    /// get the population variance of each channel
+    pub fn variance(&self) -> [f64; C] {
+        let mut variance = [0.; C];
+
+        if self.count > 0 {
+            for c in 0..C {
+                variance[c] = self.m2[c] / self.count as f64;
+            }
+        }
+
+        variance
+    }
+
+    /// get the population standard deviation of each channel
+    pub fn std(&self) -> [f64; C] {
+        let mut std = self.variance();
+
+        for s in std.iter_mut() {
+            *s = s.sqrt();
+        }
+
+        std
+    }
+
+    /// get the minimum of each channel
+    pub fn min(&self) -> [f64; C] {
+        self.min
+    }
+
+    /// get the maximum of each channel
+    pub fn max(&self) -> [f64; C] {
+        self.max
+    }
+
+    /// Create a Normalization from the mean and standard deviation of each channel
+    ///
+    /// A channel whose standard deviation is 0 is given 1 so that normalization does not divide by 0.
+    pub fn to_normalization<U>(&self) -> Normalization<U,C> where U: UnitValue<U> + FromPrimitive {
+        let mut mean = Arr::<U,C>::new();
+        let mut std = Arr::<U,C>::new();
+
+        for (c,(&m,&s)) in self.mean.iter().zip(self.std().iter()).enumerate() {
+            mean[c] = <U as FromPrimitive>::from_f64(m).expect("Error in type conversion from f64.");
+            std[c] = <U as FromPrimitive>::from_f64(if s > 0. { s } else { 1. }).expect("Error in type conversion from f64.");
+        }
+
+        Normalization::new(mean,std)
+    }
+}
+impl<const C:usize> Default for ChannelStatistics<C> {
+    fn default() -> Self {
+        ChannelStatistics::new()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use nncombinator::mem::{AsRawMutSlice, AsRawSlice};
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use crate::collection::{Images, VecImages};
+    use crate::preprocessing::statistics::ChannelStatistics;
+
+    fn batch(n:usize,seed:u64) -> VecImages<f32,3,4,5> {
+        let mut rnd = XorShiftRng::seed_from_u64(seed);
+        let mut batch = VecImages::with_size(n);
+
+        for (i,p) in batch.as_raw_mut_slice().iter_mut().enumerate() {
+            let c = (i / (4 * 5)) % 3;
+
+            *p = rnd.gen_range(-1f32..1f32) * (c + 1) as f32 + 100. * c as f32;
+        }
+
+        batch
+    }
+
+    /// Mean and population variance of each channel computed in two passes
+    fn two_pass(batch:&VecImages<f32,3,4,5>) -> ([f64; 3],[f64; 3]) {
+        let mut mean = [0.; 3];
+        let mut variance = [0.; 3];
+
+        for c in 0..3 {
+            let values = batch.as_raw_slice().chunks(4 * 5).skip(c).step_by(3).flat_map(|p| p.iter().map(|&v| v as f64)).collect::<Vec<f64>>();
+
+            mean[c] = values.iter().sum::<f64>() / values.len() as f64;
+            variance[c] = values.iter().map(|v| (v - mean[c]) * (v - mean[c])).sum::<f64>() / values.len() as f64;
+        }
+
+        (mean,variance)
+    }
+
+    fn assert_close(a:[f64; 3],b:[f64; 3]) {
+        for (a,b) in a.iter().zip(b.iter()) {
+            assert!((a - b).abs() <= 1e-9 * a.abs().max(1.),"{} {}",a,b);
+        }
+    }
+
+    #[test]
+    fn test_merge_matches_two_pass() {
+        let batch = batch(37,1);
+        let (mean,variance) = two_pass(&batch);
+
+        let mut first = ChannelStatistics::<3>::new();
+        let mut second = ChannelStatistics::<3>::new();
+
+        for i in 0..batch.len() {
+            let mut image = Images::new();
+
+            image.as_raw_mut_slice().copy_from_slice(batch[i].as_raw_slice());
+
+            if i < 10 {
+                first.update(&image);
+            } else {
+                second.update(&image);
+            }
+        }
+
+        first.merge(&second);
+
+        assert_eq!(first.count(),37 * 4 * 5);
+        assert_close(first.mean(),mean);
+        assert_close(first.variance(),variance);
+
+        for c in 0..3 {
+            assert!((first.std()[c] - variance[c].sqrt()).abs() < 1e-9);
+        }
+
+        let mut empty = ChannelStatistics::<3>::new();
+
+        empty.merge(&first);
+        first.merge(&ChannelStatistics::new());
+
+        assert_eq!(empty,first);
+    }
+
+    #[test]
+    fn test_update_batch_matches_update() {
+        let batch = batch(53,2);
+
+        let mut sequential = ChannelStatistics::<3>::new();
+
+        for i in 0..batch.len() {
+            let mut image = Images::new();
+
+            image.as_raw_mut_slice().copy_from_slice(batch[i].as_raw_slice());
+
+            sequential.update(&image);
+        }
+
+        let mut parallel = ChannelStatistics::<3>::new();
+
+        parallel.update_batch(&batch);
+
+        assert_eq!(parallel.count(),sequential.count());
+        assert_eq!(parallel.min(),sequential.min());
+        assert_eq!(parallel.max(),sequential.max());
+        assert_close(parallel.mean(),sequential.mean());
+        assert_close(parallel.variance(),sequential.variance());
+
+        let from_batches = ChannelStatistics::<3>::from_batches(vec![batch.clone(),batch]);
+
+        assert_eq!(from_batches.count(),2 * sequential.count());
+        assert_close(from_batches.mean(),sequential.mean());
+        assert_close(from_batches.variance(),sequential.variance());
+    }
+}